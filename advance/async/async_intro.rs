@@ -2,8 +2,15 @@
 #[allow(unused)]
 struct Description;
 
-fn main() {
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+#[path = "../../errors/retry.rs"]
+mod retry;
 
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[("code_example04", code_example04)])
 }
 
 /// async / await 关键字
@@ -79,3 +86,78 @@ fn code_example02() {
 fn code_example03() {
     ()
 }
+
+/// 一个最简单的 block_on
+///
+/// 在当前线程上不断 poll 传入的 Future, Pending 时挂起线程, 直到被 Waker 唤醒
+#[allow(unused)]
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    // 唤醒时 unpark 当前线程
+    struct ThreadWaker(std::thread::Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = std::pin::pin!(fut);
+    loop {
+        match fut.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+/// 异步重试
+///
+/// 与同步的 retry 一样, 根据 AppError 的 kind 决定是否重试,
+/// 区别在于每次尝试和等待都是 .await 出来的, 等待期间不会阻塞执行器
+#[allow(unused)]
+fn code_example04() {
+    use app_error::AppError;
+    use retry::{AsyncClock, Backoff, FakeClock, RetryPolicy, SystemClock};
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    let ms = Duration::from_millis;
+    let policy = RetryPolicy::new(
+        4,
+        Backoff::Exponential {
+            base: ms(10),
+            max: ms(100),
+        },
+    );
+
+    // 模拟一个前两次服务不可用的异步请求
+    let calls = Cell::new(0);
+    let request = || async {
+        calls.set(calls.get() + 1);
+        if calls.get() <= 2 {
+            Err(AppError::new("unavailable", 503, "服务不可用"))
+        } else {
+            Ok(calls.get())
+        }
+    };
+
+    // 注入 FakeClock, 不会真的等待
+    let clock = FakeClock::default();
+    let res = block_on(retry::retry_async(&policy, &clock, request));
+    assert_eq!(res, Ok(3));
+    assert_eq!(clock.slept(), [ms(10), ms(20)]);
+    // 只有 .await 的等待才会被记录
+    let clock = FakeClock::default();
+    drop(clock.sleep_async(ms(5)));
+    block_on(clock.sleep_async(ms(7)));
+    assert_eq!(clock.slept(), [ms(7)]);
+
+    // 使用真实的时钟, 在后台线程上计时
+    calls.set(0);
+    let res = block_on(retry::retry_async(&policy, &SystemClock, request));
+    println!("第 {:?} 次请求成功", res);
+}
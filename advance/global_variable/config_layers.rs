@@ -0,0 +1,255 @@
+/// 分层加载全局配置
+///
+/// 加载器的实现见 config_loader.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod config_loader;
+mod global_config;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use app_error::AppError;
+use config_loader::{ConfigLoader, Configurable, Source, Values};
+use std::path::PathBuf;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 配置
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    a: String,
+    b: String,
+    port: u16,
+    debug: bool,
+}
+
+/// 默认值
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            a: String::from("a of config"),
+            b: String::from("b of config"),
+            port: 8080,
+            debug: false,
+        }
+    }
+}
+
+impl Configurable for Config {
+    fn to_values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("a", self.a.clone()),
+            ("b", self.b.clone()),
+            ("server.port", self.port.to_string()),
+            ("server.debug", self.debug.to_string()),
+        ]
+    }
+
+    fn from_values(values: &Values) -> Result<Self, AppError> {
+        Ok(Config {
+            a: values.get("a")?,
+            b: values.get("b")?,
+            port: values.get("server.port")?,
+            debug: values.get("server.debug")?,
+        })
+    }
+}
+
+/// 在临时目录下写入配置文件
+fn write_temp(name: &str, content: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("config_layers_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+const TOML: &str = r#"
+# 配置文件
+a = "a from file"
+
+[server]
+port = 9000 # 行尾注释
+"#;
+
+const JSON: &str = r#"{
+    "a": "a from file",
+    "server": { "port": 9000, "debug": null }
+}"#;
+
+/// # example01 只有默认值
+#[allow(unused)]
+fn example01() {
+    let loaded = ConfigLoader::new().load::<Config>().unwrap();
+    assert_eq!(loaded.config, Config::default());
+    assert_eq!(loaded.source_of("server.port"), Some(&Source::Default));
+    print!("{}", loaded.report());
+}
+
+/// # example02 优先级: 默认值 < 配置文件 < 环境变量 < 命令行参数
+#[allow(unused)]
+fn example02() {
+    let path = write_temp("app.toml", TOML);
+    let loaded = ConfigLoader::new()
+        .file(&path)
+        .env([
+            ("APP_B", "b from env"),
+            ("APP_SERVER__PORT", "9100"),
+            // 其他程序的环境变量会被忽略
+            ("APP_UNRELATED", "x"),
+            ("PATH", "/usr/bin"),
+        ])
+        .args(["--server.port=9200", "--server.debug"])
+        .load::<Config>()
+        .unwrap();
+    print!("{}", loaded.report());
+
+    assert_eq!(
+        loaded.config,
+        Config {
+            a: String::from("a from file"),
+            b: String::from("b from env"),
+            port: 9200,
+            debug: true,
+        }
+    );
+    assert_eq!(loaded.source_of("a"), Some(&Source::File(path)));
+    assert_eq!(
+        loaded.source_of("b"),
+        Some(&Source::Env(String::from("APP_B")))
+    );
+    assert_eq!(
+        loaded.source_of("server.port"),
+        Some(&Source::Cli(String::from("--server.port")))
+    );
+}
+
+/// # example03 TOML 和 JSON 得到相同的配置
+/// JSON 中的 null 视为没有设置, 使用低优先级的值
+#[allow(unused)]
+fn example03() {
+    let from_toml = ConfigLoader::new()
+        .file(write_temp("app.toml", TOML))
+        .load::<Config>()
+        .unwrap();
+    let from_json = ConfigLoader::new()
+        .file(write_temp("app.json", JSON))
+        .load::<Config>()
+        .unwrap();
+    assert_eq!(from_toml.config, from_json.config);
+    assert_eq!(from_json.source_of("server.debug"), Some(&Source::Default));
+
+    // 字符串中的转义和 `#`
+    let path = write_temp(
+        "escape.toml",
+        "a = \"x # \\\"y\\\" \\u00e9\"\nb = 'C:\\dir'\n",
+    );
+    let loaded = ConfigLoader::new().file(path).load::<Config>().unwrap();
+    assert_eq!(loaded.config.a, "x # \"y\" é");
+    assert_eq!(loaded.config.b, "C:\\dir");
+}
+
+/// # example04 错误的输入返回带有来源的 AppError
+#[allow(unused)]
+fn example04() {
+    fn load(loader: ConfigLoader) -> AppError {
+        let err = loader.load::<Config>().unwrap_err();
+        eprintln!("{err:?}");
+        err
+    }
+
+    // 语法错误, 带有行号
+    let err = load(ConfigLoader::new().file(write_temp("bad.toml", "a = \"ok\"\nport 9000\n")));
+    assert_eq!(err.kind, "parse");
+    assert!(err.message.contains("line 2"));
+
+    let err =
+        load(ConfigLoader::new().file(write_temp("bad.json", "{\"a\": \"x\",\n \"b\": [1]}")));
+    assert_eq!(err.kind, "parse");
+    assert!(err.message.contains("line 2"));
+
+    // JSON 中不合法的数字, 虽然 f64 可以解析
+    for number in ["NaN", "inf", "-Infinity", "+1", ".5", "01", "1e999"] {
+        let json = format!("{{\"server\": {{\"port\": {number}}}}}");
+        let err = load(ConfigLoader::new().file(write_temp("number.json", &json)));
+        assert_eq!(err.kind, "parse");
+    }
+
+    // TOML 中同样不合法的数字, `_` 只能出现在两个数字之间, inf / nan 只能是小写
+    for number in [
+        "NaN", "Infinity", "-Inf", "+1", ".5", "01", "1e999", "1__0", "_1", "1_", "1_.5",
+    ] {
+        let toml = format!("[server]\nport = {number}\n");
+        let err = load(ConfigLoader::new().file(write_temp("number.toml", &toml)));
+        assert_eq!(err.kind, "parse");
+        assert!(err.message.contains(&format!("invalid value `{number}`")));
+    }
+    let toml = "a = -inf\nb = 1_000.5\n";
+    let loaded = ConfigLoader::new().file(write_temp("number.toml", toml));
+    let loaded = loaded.load::<Config>().unwrap();
+    assert_eq!(
+        (loaded.config.a.as_str(), loaded.config.b.as_str()),
+        ("-inf", "1000.5")
+    );
+
+    // 重复的表
+    let err = load(ConfigLoader::new().file(write_temp("tables.toml", "[server]\n[server]\n")));
+    assert!(err.message.contains("line 2: duplicate table `server`"));
+
+    // 配置文件和命令行中的未知键
+    let path = write_temp("unknown.toml", "[server]\nhost = \"x\"\n");
+    let err = load(ConfigLoader::new().file(&path));
+    assert_eq!(
+        err.message,
+        format!("unknown key `server.host` from {}", Source::File(path))
+    );
+    let err = load(ConfigLoader::new().args(["--verbose"]));
+    assert_eq!(err.message, "unknown key `verbose` from cli --verbose");
+
+    // 类型错误, 指出是哪个环境变量给出的值
+    let err = load(ConfigLoader::new().env([("APP_SERVER__PORT", "80000")]));
+    assert_eq!(err.kind, "config");
+    assert!(err.message.contains("from env APP_SERVER__PORT"));
+
+    // 文件不存在, 不支持的格式, 命令行中的多余参数
+    let missing = std::env::temp_dir().join("config_layers_missing.toml");
+    assert_eq!(load(ConfigLoader::new().file(missing)).code, 404);
+    assert_eq!(
+        load(ConfigLoader::new().file(write_temp("app.yaml", ""))).code,
+        415
+    );
+    assert_eq!(load(ConfigLoader::new().args(["9000"])).kind, "cli");
+}
+
+/// # example05 加载后存入全局配置
+/// 使用当前进程的环境变量和命令行参数, 例如:
+/// `APP_B=hello ./config_layers --server.port 9000`
+#[allow(unused)]
+fn example05() {
+    use global_config::GlobalConfig;
+
+    static CONFIG: GlobalConfig<Config> = GlobalConfig::new();
+
+    fn init() -> Result<&'static Config, AppError> {
+        let loaded = ConfigLoader::from_process().load::<Config>()?;
+        print!("{}", loaded.report());
+        CONFIG.init_once(loaded.config)
+    }
+
+    let config = init().unwrap();
+    assert!(std::ptr::eq(config, CONFIG.get().unwrap()));
+
+    let dir = std::env::temp_dir().join(format!("config_layers_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(dir);
+}
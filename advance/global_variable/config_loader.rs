@@ -0,0 +1,632 @@
+/// 分层加载配置
+///
+/// global_variable.rs 中的 Config 都是在 `init()` 中写死的,
+/// 实际的程序通常需要按优先级从低到高合并多个来源:
+/// 1. 默认值: 来自 `Default` trait (见 base/common_traits.rs)
+/// 2. 配置文件: 支持 TOML 和 JSON, 按扩展名区分
+/// 3. 环境变量: `APP_SERVER__PORT` 对应 `server.port`
+/// 4. 命令行参数: `--server.port=8080` 或 `--server.port 8080`
+///
+/// 高优先级的来源覆盖低优先级的来源, 并且记录每个字段最终来自哪里
+///
+/// 这里只实现配置需要的 TOML / JSON 子集 (键值, 表 / 对象嵌套), 不支持数组
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 值的来源, 按优先级从低到高排列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    /// 配置文件路径
+    File(PathBuf),
+    /// 环境变量名
+    Env(String),
+    /// 命令行参数名
+    Cli(String),
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::File(path) => write!(f, "file {}", path.display()),
+            Source::Env(name) => write!(f, "env {name}"),
+            Source::Cli(flag) => write!(f, "cli {flag}"),
+        }
+    }
+}
+
+/// 合并后的一个字段
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub value: String,
+    pub source: Source,
+}
+
+/// 合并后的所有字段, 键是以 `.` 分隔的路径, 比如 `server.port`
+#[derive(Debug, Clone, Default)]
+pub struct Values {
+    entries: BTreeMap<String, Entry>,
+}
+
+#[allow(unused)]
+impl Values {
+    fn set(&mut self, key: impl Into<String>, value: impl Into<String>, source: Source) {
+        let entry = Entry {
+            value: value.into(),
+            source,
+        };
+        self.entries.insert(key.into(), entry);
+    }
+
+    /// 读取并解析字段, 解析失败时错误信息中包含值的来源
+    pub fn get<T>(&self, key: &str) -> Result<T, AppError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let entry = self
+            .entries
+            .get(key)
+            .ok_or_else(|| config_error(format!("missing key `{key}`")))?;
+        entry.value.parse().map_err(|err| {
+            config_error(format!(
+                "invalid value {:?} for `{key}` from {}: {err}",
+                entry.value, entry.source
+            ))
+        })
+    }
+
+    /// 字段最终的来源
+    pub fn source(&self, key: &str) -> Option<&Source> {
+        self.entries.get(key).map(|entry| &entry.source)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Entry)> {
+        self.entries
+            .iter()
+            .map(|(key, entry)| (key.as_str(), entry))
+    }
+}
+
+/// 可以分层加载的配置
+///
+/// 默认层由 `Default` 生成, 默认层中出现的键就是所有合法的键
+pub trait Configurable: Default {
+    /// 把配置展开为 (键, 值) 列表
+    fn to_values(&self) -> Vec<(&'static str, String)>;
+    /// 从合并后的值构建配置
+    fn from_values(values: &Values) -> Result<Self, AppError>;
+}
+
+/// 加载结果: 配置本身以及每个字段的来源
+#[derive(Debug)]
+pub struct Loaded<C> {
+    pub config: C,
+    pub values: Values,
+}
+
+#[allow(unused)]
+impl<C> Loaded<C> {
+    pub fn source_of(&self, key: &str) -> Option<&Source> {
+        self.values.source(key)
+    }
+
+    /// 每行一个字段: `键 = 值 (来源)`
+    pub fn report(&self) -> String {
+        let width = self.values.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+        self.values
+            .iter()
+            .map(|(key, entry)| format!("{key:<width$} = {:?} ({})\n", entry.value, entry.source))
+            .collect()
+    }
+}
+
+/// # ConfigLoader 配置加载器
+///
+/// 环境变量和命令行参数由调用方传入, 方便在示例中构造;
+/// 需要读取当前进程的环境变量和参数时使用 `ConfigLoader::from_process`
+#[derive(Debug, Clone)]
+pub struct ConfigLoader {
+    file: Option<PathBuf>,
+    env_prefix: String,
+    env: Vec<(String, String)>,
+    args: Vec<String>,
+}
+
+impl Default for ConfigLoader {
+    fn default() -> Self {
+        ConfigLoader::new()
+    }
+}
+
+#[allow(unused)]
+impl ConfigLoader {
+    /// 只有默认值, 环境变量前缀为 `APP_`
+    pub fn new() -> ConfigLoader {
+        ConfigLoader {
+            file: None,
+            env_prefix: String::from("APP_"),
+            env: Vec::new(),
+            args: Vec::new(),
+        }
+    }
+
+    /// 读取当前进程的环境变量和命令行参数 (不含程序名)
+    pub fn from_process() -> ConfigLoader {
+        ConfigLoader::new()
+            .env(std::env::vars())
+            .args(std::env::args().skip(1))
+    }
+
+    pub fn file(mut self, path: impl Into<PathBuf>) -> ConfigLoader {
+        self.file = Some(path.into());
+        self
+    }
+
+    pub fn env_prefix(mut self, prefix: impl Into<String>) -> ConfigLoader {
+        self.env_prefix = prefix.into();
+        self
+    }
+
+    pub fn env<K, V>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> ConfigLoader
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env
+            .extend(vars.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    pub fn args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> ConfigLoader {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// 按优先级合并所有来源并构建配置
+    ///
+    /// 配置文件和命令行中出现未知的键时返回错误;
+    /// 环境变量是整个进程共享的, 其他程序也可能使用同样的前缀, 所以未知的环境变量会被忽略
+    pub fn load<C: Configurable>(&self) -> Result<Loaded<C>, AppError> {
+        let mut values = Values::default();
+        for (key, value) in C::default().to_values() {
+            values.set(key, value, Source::Default);
+        }
+        let known = |key: &str, source: &Source| {
+            if values.entries.contains_key(key) {
+                Ok(())
+            } else {
+                Err(config_error(format!("unknown key `{key}` from {source}")))
+            }
+        };
+
+        let mut layers = Vec::new();
+        if let Some(path) = &self.file {
+            let source = Source::File(path.clone());
+            for (key, value) in read_file(path)? {
+                known(&key, &source)?;
+                layers.push((key, value, source.clone()));
+            }
+        }
+        for (name, value) in &self.env {
+            if let Some(key) = env_key(&self.env_prefix, name) {
+                if values.entries.contains_key(&key) {
+                    layers.push((key, value.clone(), Source::Env(name.clone())));
+                }
+            }
+        }
+        for (key, flag, value) in parse_args(&self.args)? {
+            let source = Source::Cli(flag);
+            known(&key, &source)?;
+            layers.push((key, value, source));
+        }
+
+        for (key, value, source) in layers {
+            values.set(key, value, source);
+        }
+        let config = C::from_values(&values)?;
+        Ok(Loaded { config, values })
+    }
+}
+
+fn config_error(message: String) -> AppError {
+    AppError::new("config", 400, message)
+}
+
+fn parse_error(path: &Path, message: String) -> AppError {
+    AppError::new("parse", 400, format!("{}: {message}", path.display()))
+}
+
+/// `APP_SERVER__PORT` -> `server.port`, 前缀不匹配时返回 None
+fn env_key(prefix: &str, name: &str) -> Option<String> {
+    let rest = name.strip_prefix(prefix)?;
+    if rest.is_empty() {
+        return None;
+    }
+    Some(rest.to_lowercase().replace("__", "."))
+}
+
+/// 解析命令行参数, 返回 (键, 参数名, 值)
+/// - `--key=value`
+/// - `--key value`
+/// - `--flag`: 后面没有值时视为 `true`
+///
+/// 参数名中的 `-` 对应键中的 `_`
+fn parse_args(args: &[String]) -> Result<Vec<(String, String, String)>, AppError> {
+    let mut parsed = Vec::new();
+    let mut iter = args.iter().peekable();
+    while let Some(arg) = iter.next() {
+        let flag = arg
+            .strip_prefix("--")
+            .filter(|flag| !flag.is_empty())
+            .ok_or_else(|| AppError::new("cli", 400, format!("unexpected argument `{arg}`")))?;
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => match iter.next_if(|next| !next.starts_with("--")) {
+                Some(value) => (flag, value.clone()),
+                None => (flag, String::from("true")),
+            },
+        };
+        parsed.push((name.replace('-', "_"), format!("--{name}"), value));
+    }
+    Ok(parsed)
+}
+
+/// 按扩展名读取配置文件, 返回展开后的 (键, 值) 列表
+fn read_file(path: &Path) -> Result<Vec<(String, String)>, AppError> {
+    let content = std::fs::read_to_string(path).map_err(|err| {
+        let code = match err.kind() {
+            std::io::ErrorKind::NotFound => 404,
+            _ => 500,
+        };
+        AppError::new("io", code, format!("read {}: {err}", path.display()))
+    })?;
+    let pairs = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => parse_toml(&content),
+        Some("json") => parse_json(&content),
+        _ => {
+            return Err(AppError::new(
+                "config",
+                415,
+                format!("{}: unsupported config format", path.display()),
+            ))
+        }
+    };
+    pairs.map_err(|message| parse_error(path, message))
+}
+
+/// 展开后的键值中不允许重复的键
+fn push_unique(
+    pairs: &mut Vec<(String, String)>,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    if pairs.iter().any(|(k, _)| *k == key) {
+        return Err(format!("duplicate key `{key}`"));
+    }
+    pairs.push((key, value));
+    Ok(())
+}
+
+/// 解析 TOML 子集: `[表]`, `键 = 值`, `#` 注释
+/// 值支持字符串, 整数, 浮点数和布尔值
+fn parse_toml(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut pairs = Vec::new();
+    let mut table = String::new();
+    let mut tables = Vec::new();
+    for (index, raw) in content.lines().enumerate() {
+        let at = |message: String| format!("line {}: {message}", index + 1);
+        let line = strip_toml_comment(raw).trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let name = header
+                .strip_suffix(']')
+                .ok_or_else(|| at(String::from("unclosed table header")))?
+                .trim();
+            if name.starts_with('[') {
+                return Err(at(String::from("arrays of tables are not supported")));
+            }
+            if !is_toml_key(name) {
+                return Err(at(format!("invalid table name `{name}`")));
+            }
+            if tables.iter().any(|t| t == name) {
+                return Err(at(format!("duplicate table `{name}`")));
+            }
+            tables.push(name.to_string());
+            table = name.to_string();
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| at(String::from("expected `key = value`")))?;
+        let key = key.trim();
+        if !is_toml_key(key) {
+            return Err(at(format!("invalid key `{key}`")));
+        }
+        let value = parse_toml_value(value.trim()).map_err(at)?;
+        let key = if table.is_empty() {
+            key.to_string()
+        } else {
+            format!("{table}.{key}")
+        };
+        push_unique(&mut pairs, key, value).map_err(at)?;
+    }
+    Ok(pairs)
+}
+
+/// 裸键 (可以用 `.` 连接多段): 字母, 数字, `_` 和 `-`
+fn is_toml_key(key: &str) -> bool {
+    key.split('.').all(|part| {
+        !part.is_empty()
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+/// 去掉行尾注释, 字符串中的 `#` 保留
+fn strip_toml_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_toml_value(value: &str) -> Result<String, String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let (text, len) = unescape(rest)?;
+        if !rest[len..].trim().is_empty() {
+            return Err(String::from("unexpected characters after string"));
+        }
+        return Ok(text);
+    }
+    if let Some(rest) = value.strip_prefix('\'') {
+        // 字面量字符串, 不处理转义
+        return match rest.strip_suffix('\'') {
+            Some(text) if !text.contains('\'') => Ok(text.to_string()),
+            _ => Err(String::from("unterminated literal string")),
+        };
+    }
+    if value.starts_with('[') || value.starts_with('{') {
+        return Err(String::from("arrays and inline tables are not supported"));
+    }
+    if value == "true" || value == "false" {
+        return Ok(value.to_string());
+    }
+    if is_toml_number(value) {
+        return Ok(value.replace('_', ""));
+    }
+    Err(format!("invalid value `{value}`"))
+}
+
+/// 解析双引号字符串中开引号之后的部分, 返回 (内容, 包含闭引号在内消耗的字节数)
+fn unescape(input: &str) -> Result<(String, usize), String> {
+    let mut text = String::new();
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((text, i + 1)),
+            '\\' => {
+                let escaped = match chars.next().map(|(_, c)| c) {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('/') => '/',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('u') => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .filter(|_| hex.len() == 4)
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid unicode escape `\\u{hex}`"))?
+                    }
+                    Some(other) => return Err(format!("invalid escape `\\{other}`")),
+                    None => break,
+                };
+                text.push(escaped);
+            }
+            '\n' => break,
+            c => text.push(c),
+        }
+    }
+    Err(String::from("unterminated string"))
+}
+
+/// 解析 JSON 子集: 顶层必须是对象, 嵌套对象展开为 `.` 分隔的键
+/// `null` 视为没有设置, 不支持数组
+fn parse_json(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut parser = JsonParser {
+        src: content,
+        pos: 0,
+    };
+    let mut pairs = Vec::new();
+    parser.skip_whitespace();
+    parser
+        .object("", &mut pairs)
+        .and_then(|_| {
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => Ok(()),
+                Some(_) => Err(String::from("trailing characters")),
+            }
+        })
+        .map_err(|message| {
+            let consumed = &content[..parser.pos];
+            let line = consumed.matches('\n').count() + 1;
+            let column = consumed.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+            format!("line {line} column {column}: {message}")
+        })?;
+    Ok(pairs)
+}
+
+struct JsonParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl JsonParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected `{expected}`, found `{c}`")),
+            None => Err(format!("expected `{expected}`, found end of input")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let (text, len) = unescape(&self.src[self.pos..])?;
+        self.pos += len;
+        Ok(text)
+    }
+
+    /// 读取一个由 pred 匹配的字符组成的单词
+    fn word(&mut self, pred: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        let rest = &self.src[start..];
+        let len = rest.find(|c| !pred(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &self.src[start..start + len]
+    }
+
+    fn object(&mut self, prefix: &str, pairs: &mut Vec<(String, String)>) -> Result<(), String> {
+        self.expect('{')?;
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+            let key = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}.{name}")
+            };
+            self.skip_whitespace();
+            self.expect(':')?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => self.object(&key, pairs)?,
+                Some('"') => {
+                    let value = self.string()?;
+                    push_unique(pairs, key, value)?;
+                }
+                Some('[') => return Err(String::from("arrays are not supported")),
+                Some(c) if c == '-' || c.is_ascii_alphanumeric() => {
+                    let start = self.pos;
+                    let word = self
+                        .word(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.'))
+                        .to_string();
+                    match word.as_str() {
+                        "null" => {}
+                        "true" | "false" => push_unique(pairs, key, word)?,
+                        number if is_json_number(number) => push_unique(pairs, key, word)?,
+                        _ => {
+                            self.pos = start;
+                            return Err(format!("invalid value `{word}`"));
+                        }
+                    }
+                }
+                Some(c) => return Err(format!("unexpected `{c}`")),
+                None => return Err(String::from("unexpected end of input")),
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(c) => return Err(format!("expected `,` or `}}`, found `{c}`")),
+                None => return Err(String::from("unexpected end of input")),
+            }
+        }
+    }
+}
+
+/// 是否是 JSON 的数字: `-?(0|[1-9][0-9]*)(.[0-9]+)?([eE][+-]?[0-9]+)?`
+/// 只用 `parse::<f64>` 判断会接受 `NaN`, `inf`, `+1`, `.5` 之类 JSON 中不合法的写法,
+/// 超出 f64 范围的 `1e999` 也同样拒绝
+fn is_json_number(word: &str) -> bool {
+    fn digits(s: &str) -> (&str, &str) {
+        let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        s.split_at(end)
+    }
+
+    let rest = word.strip_prefix('-').unwrap_or(word);
+    let (int, mut rest) = digits(rest);
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return false;
+    }
+    if let Some(frac) = rest.strip_prefix('.') {
+        let (frac, after) = digits(frac);
+        if frac.is_empty() {
+            return false;
+        }
+        rest = after;
+    }
+    if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+        let (exp, after) = digits(exp.strip_prefix(['+', '-']).unwrap_or(exp));
+        if exp.is_empty() {
+            return false;
+        }
+        rest = after;
+    }
+    rest.is_empty() && word.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+/// 是否是 TOML 的数字: 去掉 `_` 之后与 is_json_number 相同, `_` 只能出现在两个数字之间;
+/// 另外允许带符号的 `inf` / `nan` (只能是小写)
+fn is_toml_number(value: &str) -> bool {
+    let unsigned = value.strip_prefix(['+', '-']).unwrap_or(value);
+    if unsigned == "inf" || unsigned == "nan" {
+        return true;
+    }
+    let bytes = value.as_bytes();
+    let separated = bytes.iter().enumerate().all(|(i, &b)| {
+        b != b'_'
+            || (i > 0
+                && bytes[i - 1].is_ascii_digit()
+                && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+    });
+    separated && is_json_number(&value.replace('_', ""))
+}
@@ -0,0 +1,111 @@
+/// 安全的全局配置
+///
+/// example03 / example04 / example06 原先把配置放在 `static mut Option<&mut Config>` 中:
+/// - 读写 `static mut` 都需要 unsafe, 多线程同时访问就是数据竞争
+/// - 任何地方都能拿到 `&mut Config`, 别处持有的 `&Config` 随时可能失效
+/// - `Box::leak` 的内存永远不会被回收
+///
+/// `GlobalConfig<T>` 基于 `std::sync::OnceLock`:
+/// - 只能初始化一次, 多个线程同时初始化也只有一个会成功
+/// - 只交出 `&T`, 不提供任何获取 `&mut T` 的方法
+/// - 值保存在静态变量内部, 不需要泄漏内存
+///
+/// 初始化之后就不能再修改, 需要在运行中更新配置时见 hot_config.rs
+///
+/// ## 为什么拿不到 `&mut T`
+/// 唯一能拿到 `&mut T` 的途径是 `OnceLock::get_mut(&mut self)`,
+/// 而静态变量无法被可变借用, 所以下面两行去掉注释后无法通过编译.
+/// 这里只是说明, 没有测试检查 (这些示例不是 crate, 无法运行 doctest), 错误信息是手动编译时得到的:
+/// ```ignore
+/// use global_config::GlobalConfig;
+///
+/// static CONFIG: GlobalConfig<String> = GlobalConfig::new();
+/// // CONFIG.reset(); // ! cannot borrow immutable static item `CONFIG` as mutable (reset 只在 debug 编译时存在)
+/// // let s: &mut String = CONFIG.get().unwrap(); // ! types differ in mutability
+/// ```
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::sync::OnceLock;
+
+/// 配置已经初始化时返回的错误码
+pub const ALREADY_INITIALIZED: usize = 409;
+/// 配置还没有初始化时返回的错误码
+pub const NOT_INITIALIZED: usize = 503;
+
+/// # GlobalConfig 只能初始化一次的全局配置
+pub struct GlobalConfig<T> {
+    cell: OnceLock<T>,
+}
+
+#[allow(unused)]
+impl<T> GlobalConfig<T> {
+    /// 创建未初始化的配置, const fn 所以可以直接用于静态变量
+    pub const fn new() -> GlobalConfig<T> {
+        GlobalConfig {
+            cell: OnceLock::new(),
+        }
+    }
+
+    /// 初始化配置, 已经初始化过时返回错误, 原有的配置保持不变
+    pub fn init_once(&self, value: T) -> Result<&T, AppError> {
+        let mut value = Some(value);
+        let stored = self.cell.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(stored),
+            Some(_) => Err(AppError::new(
+                "config",
+                ALREADY_INITIALIZED,
+                "global config is already initialized",
+            )),
+        }
+    }
+
+    /// 未初始化时调用 init 初始化, 然后返回配置
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        self.cell.get_or_init(init)
+    }
+
+    /// 获取配置, 未初始化时返回 None
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get()
+    }
+
+    /// 获取配置, 未初始化时返回错误
+    pub fn try_get(&self) -> Result<&T, AppError> {
+        self.cell.get().ok_or_else(|| {
+            AppError::new(
+                "config",
+                NOT_INITIALIZED,
+                "global config is not initialized",
+            )
+        })
+    }
+
+    /// 重置配置, 仅用于测试, 返回原来的配置
+    ///
+    /// 需要 `&mut self`, 所以只能重置局部创建的实例 (比如每个测试用例各自创建一个),
+    /// 静态变量无法重置, 也就不会让已经交出去的 `&T` 悬空
+    #[cfg(any(test, debug_assertions))]
+    pub fn reset(&mut self) -> Option<T> {
+        self.cell.take()
+    }
+}
+
+impl<T> Default for GlobalConfig<T> {
+    fn default() -> Self {
+        GlobalConfig::new()
+    }
+}
+
+impl<T: std::fmt::Debug> std::fmt::Debug for GlobalConfig<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.cell.get() {
+            Some(value) => write!(f, "GlobalConfig({value:?})"),
+            None => write!(f, "GlobalConfig(<uninit>)"),
+        }
+    }
+}
@@ -1,5 +1,16 @@
 /// 进阶之全局变量
 
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+#[path = "../../errors/validation.rs"]
+mod validation;
+mod global_config;
+mod id_generator;
+mod metrics;
+mod registry;
+
 /// 静态常量
 /// 全局常量可以和程序任何一部分使用
 /// 但是如果定义在某个模块中, 需要引入对应的模块使用
@@ -16,6 +27,7 @@ const MAX_ID: usize = usize::MAX;
 
 /// 静态变量
 /// 静态变量允许声明一个全局的变量, 常用于全局数据统计
+/// 每个线程各有一份的 thread_local 变量见 thread_local_demo.rs
 #[allow(unused)]
 static mut REQUEST_RECV: usize = 0;
 /// 静态变量必须使用 unsafe 代码块才能访问和修改值
@@ -23,13 +35,15 @@ static mut REQUEST_RECV: usize = 0;
 
 /// 原子类型
 /// 想要全局计数器或者状态控制等等, 但又想线程安全, 可以使用原子类型
+/// 不同 Ordering 的区别见 ordering_lab.rs
 #[allow(unused_imports)]
 use std::sync::atomic::{AtomicUsize, Ordering};
 /// 初始化原子类型
+/// 需要按名字和标签区分多种指标时见 example09
 #[allow(unused)]
 static REQUEST_RECV_NEW: AtomicUsize = AtomicUsize::new(0);
 
-fn main() {
+fn main() -> panic_boundary::Report {
     println!("该程序允许最大的用户数量 {}", MAX_ID);
 
     // 更新静态变量的值
@@ -44,71 +58,149 @@ fn main() {
 
     println!("当前用户的请求数量是 {:?}", REQUEST_RECV_NEW);
 
-    example01();
-    example04();
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example06", example06),
+        ("example07", example07),
+        ("example08", example08),
+        ("example09", example09),
+    ])
 }
 
 /// # example01 全局 ID 生成器
+/// 计数器的实现见 id_generator.rs 中的 Counter
+/// 计数器每次启动都从 0 开始, 需要跨进程不重复时参考 id_persist.rs
 #[allow(unused)]
 fn example01() {
+    use app_error::AppError;
+    use id_generator::Counter;
+
     struct Factory {
         factory_id: usize,
     }
 
     // 定义全局变量的计数器
-    static GLOBAL_ID_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    static GLOBAL_ID_COUNTER: Counter = Counter::new(MAX_ID);
     const MAX_ID: usize = usize::MAX / 2;
 
     /// generate_id 生成 ID 函数
-    fn generate_id() -> usize {
-        // 获取当前计数器的累计值
-        let current_val: usize = GLOBAL_ID_COUNTER.load(Ordering::Relaxed);
-        // 第一次检查溢出情况
-        if current_val > MAX_ID {
-            panic!("Factory ids overflowed");
-        }
-        // 自增 ID
-        GLOBAL_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
-        // 获取新的 ID 的值
-        let new_id = GLOBAL_ID_COUNTER.load(Ordering::Relaxed);
-        // 再一次检查溢出情况
-        if new_id > MAX_ID {
-            panic!("Factory ids overflowed");
-        }
-        // 返回新的 ID
-        new_id
+    /// ! 不要先 load 检查再 fetch_add, 两步之间其他线程可能插进来
+    /// 检查溢出和自增必须在一次原子操作中完成, 溢出时返回错误而不是 panic
+    fn generate_id() -> Result<usize, AppError> {
+        Ok(GLOBAL_ID_COUNTER.next()?)
     }
 
     impl Factory {
         /// # new 创建工厂实例
         /// 每次创建工厂实例时会被计数累计
-        fn new() -> Factory {
-            Factory {
-                factory_id: generate_id(),
-            }
+        fn new() -> Result<Factory, AppError> {
+            Ok(Factory {
+                factory_id: generate_id()?,
+            })
         }
     }
 
     // Test usecase
-    Factory::new();
-    println!("{}", GLOBAL_ID_COUNTER.load(Ordering::Relaxed));
-    Factory::new();
-    Factory::new();
-    println!("{}", GLOBAL_ID_COUNTER.load(Ordering::Relaxed));
+    let first = Factory::new().unwrap();
+    assert_eq!(first.factory_id, 1);
+    println!("{}", GLOBAL_ID_COUNTER.current());
+    Factory::new().unwrap();
+    let third = Factory::new().unwrap();
+    assert_eq!(third.factory_id, 3);
+    println!("{}", GLOBAL_ID_COUNTER.current());
+
+    // 计数器到达上限后返回错误, 而不是 panic
+    static NEARLY_FULL: Counter = Counter::starting_at(MAX_ID - 1, MAX_ID);
+    assert_eq!(NEARLY_FULL.next(), Ok(MAX_ID));
+    let err: AppError = NEARLY_FULL.next().unwrap_err().into();
+    eprintln!("{err:?}");
+    assert_eq!(NEARLY_FULL.current(), MAX_ID);
 }
 
-/// TODO
 /// # example02 运行期初始化
-#[allow(unused_imports)]
+/// 静态变量的初始值必须在编译期算出, `String::from` 不是 const fn, 下面的代码无法编译:
+/// ```ignore
+/// static NAMES: Mutex<String> = Mutex::new(String::from("Sunface, Jack, Allen"));
+/// ```
+/// 使用 `LazyLock` 把初始化推迟到第一次访问时
+///
+/// 不同加锁方式的注册表见 registry.rs, 性能对比见 registry_bench.rs
 use std::sync::Mutex;
-// static NAMES: Mutex<String> = Mutex::new(String::from("Sunface, Jack, Allen"));
 #[allow(unused)]
-fn example02() {}
+fn example02() {
+    use registry::{Registry, RwLockRegistry, ShardedRegistry};
+    use std::sync::LazyLock;
+
+    static NAMES: LazyLock<Mutex<String>> =
+        LazyLock::new(|| Mutex::new(String::from("Sunface, Jack, Allen")));
+
+    NAMES.lock().unwrap().push_str(", Tom");
+    assert_eq!(*NAMES.lock().unwrap(), "Sunface, Jack, Allen, Tom");
+    println!("{}", NAMES.lock().unwrap());
+
+    /// 会话
+    #[derive(Debug, Clone, PartialEq)]
+    struct Session {
+        user: u64,
+        token: String,
+    }
+
+    // 读多写少的数据使用读写锁
+    static USERS: LazyLock<RwLockRegistry<u64, String>> = LazyLock::new(RwLockRegistry::new);
+    // 多个线程同时频繁读写的数据使用分片锁
+    static SESSIONS: LazyLock<ShardedRegistry<u64, Session>> =
+        LazyLock::new(ShardedRegistry::new);
+
+    for (id, name) in [(1, "Sunface"), (2, "Jack"), (3, "Allen")] {
+        USERS.insert(id, String::from(name));
+    }
+
+    // 每个线程登录 100 次, 会话 ID 互不相同
+    std::thread::scope(|s| {
+        for thread in 0..4u64 {
+            s.spawn(move || {
+                for i in 0..100 {
+                    let user = i % 3 + 1;
+                    let name = USERS.get(&user).unwrap();
+                    let session = Session {
+                        user,
+                        token: format!("{name}-{thread}-{i}"),
+                    };
+                    assert!(SESSIONS.insert(thread * 1000 + i, session).is_none());
+                }
+            });
+        }
+    });
+    assert_eq!(SESSIONS.len(), 400);
+    println!("{:?}", SESSIONS.get(&1001));
+
+    // 退出登录
+    let removed = SESSIONS.remove(&1001).unwrap();
+    assert_eq!(removed.token, "Jack-1-1");
+    assert!(SESSIONS.get(&1001).is_none());
+    assert_eq!(SESSIONS.len(), 399);
+}
 
 /// # example03 Box::leak 可用于全局变量
-/// FIXME 当不使用 lazy_static 和 Box::leak 的情况下
+/// 旧写法把局部变量的引用放进 `static mut CONFIG: Option<&mut Config>`,
+/// 局部变量活不到 'static, 无法通过编译:
+/// ```ignore
+/// static mut CONFIG: Option<&mut Config> = None;
+/// unsafe {
+///     CONFIG = Some(&mut Config {
+///         a: String::from("a of config"),
+///         b: String::from("b of config"),
+///     });
+/// }
+/// ```
+/// 使用 GlobalConfig 时值直接保存在静态变量中, 既不需要借用局部变量, 也不需要 unsafe
 #[allow(unused)]
 fn example03() {
+    use global_config::GlobalConfig;
+
     /// 配置
     #[derive(Debug)]
     struct Config {
@@ -116,65 +208,361 @@ fn example03() {
         b: String,
     }
     // 全局静态变量
-    static mut CONFIG: Option<&mut Config> = None;
-    // 初始化全局配置
-    unsafe {
-        // FIXME 以下代码会发生错误
-        // FIXME 此问题是试图将一个局部声明周期的变量赋值给全局变量的生命周期
-        // CONFIG = Some(&mut Config {
-        //     a: String::from("a of config"),
-        //     b: String::from("b of config"),
-        // });
+    static CONFIG: GlobalConfig<Config> = GlobalConfig::new();
 
-        println!("{CONFIG:?}");
-    }
+    // 初始化前读取得到错误, 而不是 None 或者悬空的引用
+    let err = CONFIG.try_get().unwrap_err();
+    assert_eq!(err.code, global_config::NOT_INITIALIZED);
+    println!("{CONFIG:?}");
+
+    // 初始化全局配置
+    CONFIG
+        .init_once(Config {
+            a: String::from("a of config"),
+            b: String::from("b of config"),
+        })
+        .unwrap();
+    assert_eq!(CONFIG.get().unwrap().a, "a of config");
+    println!("{CONFIG:?}");
 }
 
 /// # example04 Box::leak 可用于全局变量
-/// FIXME 使用 Box::leak 可以将一个变量从内存中泄露
-/// 然后再将其变为 'static 生命周期, 最终可以跟整个程序活的一样长
+/// 旧写法使用 Box::leak 将一个变量从内存中泄露, 再将其变为 'static 生命周期:
+/// ```ignore
+/// static mut CONFIG_NWE: Option<&mut Config> = None;
+/// unsafe {
+///     CONFIG_NWE = Some(Box::leak(Box::new(Config { .. })));
+/// }
+/// ```
+/// 任何地方都能再次赋值或者拿到 `&mut Config`, 泄漏的内存也永远不会被回收
+///
+/// GlobalConfig 只能初始化一次, 之后只能拿到 `&Config`
 #[allow(unused)]
 fn example04() {
+    use global_config::GlobalConfig;
+
     #[derive(Debug)]
     struct Config {
         a: String,
         b: String,
     }
     // 全局静态配置变量
-    static mut CONFIG_NWE: Option<&mut Config> = None;
-    // 开始运行时初始化静态配置变量
-    unsafe {
-        // 初始化
-        CONFIG_NWE = Some(Box::leak(Box::new(Config {
-            a: String::from("hello"),
-            b: String::from("hjkl1"),
-        })));
-        // 查看配置
-        println!("currently config {CONFIG_NWE:?}");
+    static CONFIG_NWE: GlobalConfig<Config> = GlobalConfig::new();
+
+    // 多个线程同时初始化, 只有一个会成功, 其余线程拿到错误, 配置不会被覆盖
+    let results: Vec<bool> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                s.spawn(move || {
+                    CONFIG_NWE
+                        .init_once(Config {
+                            a: String::from("hello"),
+                            b: format!("hjkl{i}"),
+                        })
+                        .is_ok()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(results.iter().filter(|ok| **ok).count(), 1);
+
+    // 再次初始化返回错误, 原有的配置保持不变
+    let current = CONFIG_NWE.get().unwrap();
+    let err = CONFIG_NWE
+        .init_once(Config {
+            a: String::from("bye"),
+            b: String::from("hjkl"),
+        })
+        .unwrap_err();
+    assert_eq!(err.code, global_config::ALREADY_INITIALIZED);
+    assert_eq!(CONFIG_NWE.get().unwrap().a, "hello");
+    assert!(std::ptr::eq(current, CONFIG_NWE.get().unwrap()));
+    // 查看配置
+    println!("currently config {current:?}");
+
+    // 局部创建的实例可以重置, 用于测试中反复初始化; reset 只在 debug 编译时存在
+    #[cfg(any(test, debug_assertions))]
+    {
+        let mut local = GlobalConfig::new();
+        local.init_once(1).unwrap();
+        assert_eq!(local.reset(), Some(1));
+        assert!(local.get().is_none());
+        local.init_once(2).unwrap();
+        assert_eq!(local.get(), Some(&2));
     }
 }
 
 /// # example05 从函数中返回全局变量
-/// 依然可以使用 Box::leak 将局部变量从内存泄漏
+/// 旧写法中 init 返回 `Option<&'static mut Config>`, 依然需要 Box::leak 泄漏内存
+///
+/// 使用 get_or_init 时, 第一次调用 init 才会创建配置, 之后都返回同一个 `&'static Config`
+/// 不需要初始化参数时也可以直接使用 `LazyLock<Config>`
+/// 从配置文件, 环境变量和命令行参数加载配置见 config_layers.rs
 #[allow(unused)]
 fn example06() {
+    use global_config::GlobalConfig;
+    use std::sync::LazyLock;
+
     #[derive(Debug)]
     struct Config {
         c: String,
         d: String,
     }
-    static mut APP_CONFIG: Option<&mut Config> = None;
+    static APP_CONFIG: GlobalConfig<Config> = GlobalConfig::new();
     // 定义初始化函数
     // 然后返回全局变量
-    fn init() -> Option<&'static mut Config> {
-        Some(Box::leak(Box::new(Config {
+    fn init() -> &'static Config {
+        APP_CONFIG.get_or_init(|| Config {
             c: String::from("halo"),
             d: String::from("world"),
-        })))
+        })
     }
     // ...
-    unsafe {
-        APP_CONFIG = init();
-        println!("currently config {APP_CONFIG:?}");
+    let config = init();
+    assert!(std::ptr::eq(config, init()));
+    println!("currently config {config:?}");
+
+    // 默认配置在第一次访问时创建
+    static DEFAULT_CONFIG: LazyLock<Config> = LazyLock::new(|| Config {
+        c: String::from("default c"),
+        d: String::from("default d"),
+    });
+    assert_eq!(DEFAULT_CONFIG.c, "default c");
+}
+
+/// # example07 校验全局配置
+/// 全局配置在初始化前需要校验, 使用 Validation 一次性收集所有字段的错误
+/// 而不是遇到第一个错误就返回
+#[allow(unused)]
+fn example07() {
+    use app_error::AppError;
+    use validation::Validation;
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        a: String,
+        b: String,
+    }
+
+    // 校验单个字段: 不能为空, 不能超过 16 个字符, 只能是 ASCII
+    // 每条规则各自校验, 所有失败的规则都会被收集
+    fn validate_field(name: &str, value: &str) -> Validation<String, AppError> {
+        let rules = [
+            (!value.is_empty(), "不能为空"),
+            (value.chars().count() <= 16, "不能超过 16 个字符"),
+            (value.is_ascii(), "只能包含 ASCII 字符"),
+        ];
+        rules
+            .into_iter()
+            .map(|(ok, reason)| {
+                Validation::check((), ok, AppError::new("config", 400, format!("{name} {reason}")))
+            })
+            .collect::<Validation<Vec<()>, AppError>>()
+            .map(|_| value.to_string())
+    }
+
+    // 校验整个配置, 各字段的错误会被累积起来
+    fn validate_config(a: &str, b: &str) -> Validation<Config, AppError> {
+        validate_field("a", a).combine(validate_field("b", b), |a, b| Config { a, b })
     }
-}
\ No newline at end of file
+
+    // Test usecase
+    // 所有字段都通过
+    let config = validate_config("hello", "hjkl1").into_result();
+    assert_eq!(
+        config,
+        Ok(Config {
+            a: String::from("hello"),
+            b: String::from("hjkl1"),
+        })
+    );
+
+    // 两个字段都失败, 两个错误都被收集
+    let invalid = validate_config("", "一个很长很长很长很长很长很长的配置");
+    let messages: Vec<&str> = invalid.errors().iter().map(|e| e.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        ["a 不能为空", "b 不能超过 16 个字符", "b 只能包含 ASCII 字符"]
+    );
+    for err in invalid.errors() {
+        eprintln!("{err:?}");
+    }
+
+    // 转为 Result 后可以继续使用 ? 等短路语义
+    let res: Result<Config, Vec<AppError>> = validate_config("", "").into();
+    assert_eq!(res.unwrap_err().len(), 2);
+
+    // 从 Result 转换, 并与其他校验结果组合
+    let parsed: Validation<usize, AppError> = "x"
+        .parse::<usize>()
+        .map_err(|e| AppError::new("config", 400, e.to_string()))
+        .into();
+    let zipped = parsed.zip(validate_field("a", ""));
+    assert_eq!(zipped.errors().len(), 2);
+
+    // 收集多个字段
+    let all: Validation<Vec<String>, AppError> = ["a", "b", ""]
+        .iter()
+        .map(|v| validate_field("c", v))
+        .collect();
+    assert!(!all.is_valid());
+    assert_eq!(all.errors().len(), 1);
+}
+
+/// # example08 雪花 ID
+/// 64 位 ID = 41 位毫秒时间戳 + 10 位机器 ID + 12 位序列号
+/// 不同机器使用不同的机器 ID, 无需协调就能生成全局唯一且大致按时间递增的 ID
+#[allow(unused)]
+fn example08() {
+    use id_generator::{IdError, Snowflake, SnowflakeParts, TimeSource, EPOCH_MS, MAX_SEQUENCE};
+    use std::sync::atomic::AtomicU64;
+
+    // 可以手动拨动的时钟
+    struct ManualClock(AtomicU64);
+    impl ManualClock {
+        fn set(&self, ms: u64) {
+            self.0.store(ms, Ordering::Relaxed);
+        }
+    }
+    impl TimeSource for ManualClock {
+        fn now_ms(&self) -> u64 {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    // 使用系统时钟
+    let generator = Snowflake::new(1).unwrap();
+    let a = generator.next_id().unwrap();
+    let b = generator.next_id().unwrap();
+    assert!(a < b);
+    println!("snowflake ids: {a} {b}, {:?}", SnowflakeParts::from_id(b));
+
+    // 机器 ID 只有 10 位
+    assert_eq!(
+        Snowflake::new(1024).err(),
+        Some(IdError::InvalidWorkerId(1024))
+    );
+
+    // 同一毫秒内序列号递增, 可以拆解出各个部分
+    let now = EPOCH_MS + 1_000;
+    let clock = ManualClock(AtomicU64::new(now));
+    let generator = Snowflake::with_clock(7, 5, &clock).unwrap();
+    let first = generator.next_id().unwrap();
+    let second = generator.next_id().unwrap();
+    assert_eq!(
+        SnowflakeParts::from_id(first),
+        SnowflakeParts {
+            timestamp_ms: now,
+            worker_id: 7,
+            sequence: 0,
+        }
+    );
+    assert_eq!(SnowflakeParts::from_id(second).sequence, 1);
+    // 拆解之后可以重新组装; 早于纪元的时间戳无法组装
+    assert_eq!(SnowflakeParts::from_id(second).to_id(), Ok(second));
+    let before_epoch = SnowflakeParts {
+        timestamp_ms: EPOCH_MS - 1,
+        ..SnowflakeParts::from_id(second)
+    };
+    assert_eq!(
+        before_epoch.to_id(),
+        Err(IdError::TimestampOutOfRange {
+            timestamp_ms: EPOCH_MS - 1
+        })
+    );
+    // 时钟早于纪元时同样无法生成 ID
+    let early = ManualClock(AtomicU64::new(EPOCH_MS - 1));
+    assert_eq!(
+        Snowflake::with_clock(7, 5, &early).unwrap().next_id(),
+        Err(IdError::TimestampOutOfRange {
+            timestamp_ms: EPOCH_MS - 1
+        })
+    );
+
+    // 时间前进后序列号归零
+    clock.set(now + 1);
+    let third = generator.next_id().unwrap();
+    assert_eq!(SnowflakeParts::from_id(third).sequence, 0);
+    assert!(third > second);
+
+    // 小幅回拨: 沿用上一次的时间戳, ID 依然递增
+    clock.set(now - 3);
+    let fourth = generator.next_id().unwrap();
+    assert!(fourth > third);
+    assert_eq!(SnowflakeParts::from_id(fourth).timestamp_ms, now + 1);
+
+    // 大幅回拨: 返回错误
+    clock.set(now - 100);
+    assert_eq!(
+        generator.next_id(),
+        Err(IdError::ClockMovedBackwards {
+            last_ms: now + 1,
+            now_ms: now - 100,
+        })
+    );
+
+    // 序列号用完后借用下一毫秒, 超出容忍范围后返回错误
+    clock.set(now + 10);
+    let mut last = 0;
+    for _ in 0..=MAX_SEQUENCE {
+        let id = generator.next_id().unwrap();
+        assert!(id > last);
+        last = id;
+    }
+    let borrowed = generator.next_id().unwrap();
+    assert_eq!(SnowflakeParts::from_id(borrowed).timestamp_ms, now + 11);
+    let mut result = Ok(0);
+    for _ in 0..(MAX_SEQUENCE + 1) * 6 {
+        result = generator.next_id();
+        if result.is_err() {
+            break;
+        }
+    }
+    assert!(matches!(result, Err(IdError::SequenceExhausted { .. })));
+}
+
+/// # example09 全局指标
+/// `REQUEST_RECV_NEW` 只能统计总请求数, 按路径和状态码区分, 统计耗时时使用指标注册表
+/// 注册表的实现见 metrics.rs, 通过 HTTP 提供 `/metrics` 见 metrics_server.rs
+#[allow(unused)]
+fn example09() {
+    use metrics::DEFAULT_BUCKETS;
+    use std::time::Instant;
+
+    let registry = metrics::global();
+
+    /// 处理一个请求并记录指标
+    fn handle(path: &str) {
+        let registry = metrics::global();
+        let start = Instant::now();
+        let code = if path.starts_with("/api") { "200" } else { "404" };
+        registry
+            .counter("requests_total", "请求数", &[("path", path), ("code", code)])
+            .unwrap()
+            .inc();
+        registry
+            .histogram("request_seconds", "请求耗时", DEFAULT_BUCKETS, &[])
+            .unwrap()
+            .observe(start.elapsed().as_secs_f64());
+    }
+
+    let in_flight = registry.gauge("requests_in_flight", "正在处理的请求数", &[]).unwrap();
+    std::thread::scope(|s| {
+        for path in ["/api/users", "/api/users", "/favicon.ico"] {
+            let in_flight = &in_flight;
+            s.spawn(move || {
+                in_flight.inc();
+                handle(path);
+                in_flight.dec();
+            });
+        }
+    });
+
+    let ok = registry
+        .counter("requests_total", "请求数", &[("code", "200"), ("path", "/api/users")])
+        .unwrap();
+    assert_eq!(ok.get(), 2);
+    assert_eq!(in_flight.get(), 0.0);
+    print!("{}", registry.render_prometheus());
+}
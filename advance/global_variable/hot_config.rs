@@ -0,0 +1,241 @@
+/// 可热更新的全局配置
+///
+/// GlobalConfig 只能初始化一次, 而服务在运行中往往需要修改配置
+///
+/// - `ArcCell<T>`: 类似 arc-swap 的 `ArcSwap`, 读者拿到 `Arc<T>` 快照,
+///   更新时整体替换, 已经拿到的快照不受影响, 也不会看到更新了一半的配置
+/// - `HotConfig<C>`: 通过加载函数重新加载配置, 加载失败时保留原来的配置并报告错误,
+///   成功或失败都会通知订阅者 (回调或者 channel)
+/// - `FileWatcher`: 轮询文件内容, 发生变化时调用回调
+///
+/// ! arc-swap 的读取是无锁的; 这里用 RwLock 实现, 读锁只在克隆 Arc 时持有, 开销很小
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// # ArcCell 可以整体替换的 Arc
+pub struct ArcCell<T> {
+    inner: RwLock<Arc<T>>,
+}
+
+#[allow(unused)]
+impl<T> ArcCell<T> {
+    pub fn new(value: T) -> ArcCell<T> {
+        ArcCell {
+            inner: RwLock::new(Arc::new(value)),
+        }
+    }
+
+    /// 当前值的快照
+    pub fn load(&self) -> Arc<T> {
+        Arc::clone(&self.inner.read().unwrap())
+    }
+
+    /// 替换为新值, 返回旧值
+    pub fn store(&self, value: Arc<T>) -> Arc<T> {
+        std::mem::replace(&mut *self.inner.write().unwrap(), value)
+    }
+}
+
+/// 配置变化的通知
+#[derive(Debug)]
+pub enum ConfigEvent<C> {
+    /// 重新加载成功, version 从 1 开始递增
+    Updated {
+        old: Arc<C>,
+        new: Arc<C>,
+        version: u64,
+    },
+    /// 重新加载失败, 配置保持不变
+    Rejected(AppError),
+}
+
+// derive(Clone) 会要求 C: Clone, 而这里只需要克隆 Arc
+impl<C> Clone for ConfigEvent<C> {
+    fn clone(&self) -> Self {
+        match self {
+            ConfigEvent::Updated { old, new, version } => ConfigEvent::Updated {
+                old: Arc::clone(old),
+                new: Arc::clone(new),
+                version: *version,
+            },
+            ConfigEvent::Rejected(err) => ConfigEvent::Rejected(err.clone()),
+        }
+    }
+}
+
+type Loader<C> = Box<dyn Fn() -> Result<C, AppError> + Send + Sync>;
+type Callback<C> = Box<dyn Fn(&ConfigEvent<C>) + Send + Sync>;
+
+/// # HotConfig 可热更新的配置
+pub struct HotConfig<C> {
+    current: ArcCell<C>,
+    version: AtomicU64,
+    loader: Loader<C>,
+    // 保证多次重新加载依次进行, 通知的顺序和版本号一致
+    reload_lock: Mutex<()>,
+    callbacks: Mutex<Vec<Callback<C>>>,
+    channels: Mutex<Vec<Sender<ConfigEvent<C>>>>,
+}
+
+#[allow(unused)]
+impl<C> HotConfig<C> {
+    /// 使用 loader 加载初始配置, 初始配置加载失败时直接返回错误
+    pub fn load(
+        loader: impl Fn() -> Result<C, AppError> + Send + Sync + 'static,
+    ) -> Result<HotConfig<C>, AppError> {
+        let initial = loader()?;
+        Ok(HotConfig {
+            current: ArcCell::new(initial),
+            version: AtomicU64::new(0),
+            loader: Box::new(loader),
+            reload_lock: Mutex::new(()),
+            callbacks: Mutex::new(Vec::new()),
+            channels: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// 当前配置的快照
+    pub fn snapshot(&self) -> Arc<C> {
+        self.current.load()
+    }
+
+    /// 成功加载的次数
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Acquire)
+    }
+
+    /// 注册回调, 在调用 reload 的线程中同步执行
+    ///
+    /// ! 回调中不能再调用 subscribe 或 reload, 否则会死锁
+    pub fn subscribe(&self, callback: impl Fn(&ConfigEvent<C>) + Send + Sync + 'static) {
+        self.callbacks.lock().unwrap().push(Box::new(callback));
+    }
+
+    /// 通过 channel 接收通知, Receiver 被丢弃后自动取消订阅
+    pub fn watch(&self) -> Receiver<ConfigEvent<C>> {
+        let (tx, rx) = mpsc::channel();
+        self.channels.lock().unwrap().push(tx);
+        rx
+    }
+
+    /// 重新加载配置
+    /// - 成功: 替换配置, 返回新的快照
+    /// - 失败: 保留原来的配置, 返回错误
+    pub fn reload(&self) -> Result<Arc<C>, AppError> {
+        let _guard = self.reload_lock.lock().unwrap();
+        let (event, result) = match (self.loader)() {
+            Ok(config) => {
+                let new = Arc::new(config);
+                let old = self.current.store(Arc::clone(&new));
+                let version = self.version.fetch_add(1, Ordering::AcqRel) + 1;
+                let event = ConfigEvent::Updated {
+                    old,
+                    new: Arc::clone(&new),
+                    version,
+                };
+                (event, Ok(new))
+            }
+            Err(err) => (ConfigEvent::Rejected(err.clone()), Err(err)),
+        };
+        self.notify(event);
+        result
+    }
+
+    fn notify(&self, event: ConfigEvent<C>) {
+        for callback in self.callbacks.lock().unwrap().iter() {
+            callback(&event);
+        }
+        let mut channels = self.channels.lock().unwrap();
+        channels.retain(|tx| tx.send(event.clone()).is_ok());
+    }
+}
+
+impl<C: std::fmt::Debug> std::fmt::Debug for HotConfig<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotConfig")
+            .field("version", &self.version())
+            .field("current", &self.snapshot())
+            .finish()
+    }
+}
+
+/// # FileWatcher 轮询文件变化
+///
+/// 每隔 interval 读取一次文件内容并计算哈希, 与上一次不同时调用 on_change
+/// 只比较修改时间的话, 一些文件系统的时间精度是秒级, 同一秒内的两次修改会被漏掉
+///
+/// 被丢弃时停止后台线程
+pub struct FileWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[allow(unused)]
+impl FileWatcher {
+    pub fn spawn(
+        path: impl Into<PathBuf>,
+        interval: Duration,
+        mut on_change: impl FnMut() + Send + 'static,
+    ) -> FileWatcher {
+        let path = path.into();
+        let stop = Arc::new(AtomicBool::new(false));
+        // 在返回之前记录初始状态, 之后的任何修改都会被发现
+        let mut last = fingerprint(&path);
+        let handle = std::thread::spawn({
+            let stop = Arc::clone(&stop);
+            move || {
+                while !stop.load(Ordering::Acquire) {
+                    std::thread::park_timeout(interval);
+                    let current = fingerprint(&path);
+                    if current != last {
+                        last = current;
+                        on_change();
+                    }
+                }
+            }
+        });
+        FileWatcher {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// 停止轮询并等待后台线程退出
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stop.store(true, Ordering::Release);
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+/// 文件内容的哈希, 文件暂时不存在 (比如正在被替换) 也算一种状态
+fn fingerprint(path: &std::path::Path) -> u64 {
+    let content = std::fs::read(path).ok();
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,267 @@
+/// 热更新全局配置
+///
+/// ArcCell / HotConfig / FileWatcher 的实现见 hot_config.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod config_loader;
+mod global_config;
+mod hot_config;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use app_error::AppError;
+use config_loader::{ConfigLoader, Configurable, Values};
+use hot_config::{ArcCell, ConfigEvent, FileWatcher, HotConfig};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 配置
+#[derive(Debug, Clone, PartialEq)]
+struct Config {
+    name: String,
+    port: u16,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            name: String::from("v8080"),
+            port: 8080,
+        }
+    }
+}
+
+impl Configurable for Config {
+    fn to_values(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("name", self.name.clone()),
+            ("server.port", self.port.to_string()),
+        ]
+    }
+
+    fn from_values(values: &Values) -> Result<Self, AppError> {
+        let config = Config {
+            name: values.get("name")?,
+            port: values.get("server.port")?,
+        };
+        // 能解析但不合法的配置同样会被拒绝
+        if config.port < 1024 {
+            return Err(AppError::new(
+                "config",
+                400,
+                format!("server.port {} is a privileged port", config.port),
+            ));
+        }
+        Ok(config)
+    }
+}
+
+/// 从配置文件加载的 loader
+fn file_loader(path: &Path) -> impl Fn() -> Result<Config, AppError> + Send + Sync + 'static {
+    let path = path.to_path_buf();
+    move || {
+        ConfigLoader::new()
+            .file(&path)
+            .load::<Config>()
+            .map(|loaded| loaded.config)
+    }
+}
+
+/// 先写临时文件再 rename, 避免 FileWatcher 读到写了一半的文件
+fn write_config(path: &Path, port: u16) {
+    let temp = path.with_extension("tmp");
+    std::fs::write(
+        &temp,
+        format!("name = \"v{port}\"\n[server]\nport = {port}\n"),
+    )
+    .unwrap();
+    std::fs::rename(temp, path).unwrap();
+}
+
+/// 在临时目录下创建配置文件
+fn temp_config(name: &str, port: u16) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hot_reload_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.toml"));
+    write_config(&path, port);
+    path
+}
+
+/// # example01 ArcCell 快照
+/// 替换之后, 之前拿到的快照依然有效且保持不变
+#[allow(unused)]
+fn example01() {
+    let cell = ArcCell::new(Config::default());
+    let before = cell.load();
+
+    let old = cell.store(Arc::new(Config {
+        name: String::from("v9000"),
+        port: 9000,
+    }));
+    assert!(Arc::ptr_eq(&before, &old));
+    assert_eq!(before.port, 8080);
+    assert_eq!(cell.load().port, 9000);
+    // cell 中一份, 加上这次 load 得到的一份
+    assert_eq!(Arc::strong_count(&cell.load()), 2);
+}
+
+/// # example02 重新加载并通知订阅者
+#[allow(unused)]
+fn example02() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let path = temp_config("reload", 8080);
+    let config = HotConfig::load(file_loader(&path)).unwrap();
+    assert_eq!(config.snapshot().port, 8080);
+
+    // 回调订阅
+    let last_version = Arc::new(AtomicU64::new(0));
+    config.subscribe({
+        let last_version = Arc::clone(&last_version);
+        move |event| {
+            if let ConfigEvent::Updated { version, .. } = event {
+                last_version.store(*version, Ordering::Relaxed);
+            }
+        }
+    });
+    // channel 订阅
+    let events = config.watch();
+
+    write_config(&path, 9000);
+    let new = config.reload().unwrap();
+    assert_eq!(new.port, 9000);
+    assert_eq!(last_version.load(Ordering::Relaxed), 1);
+    match events.try_recv().unwrap() {
+        ConfigEvent::Updated { old, new, version } => {
+            println!("v{version}: {old:?} -> {new:?}");
+            assert_eq!((old.port, new.port, version), (8080, 9000, 1));
+        }
+        ConfigEvent::Rejected(err) => panic!("{err:?}"),
+    }
+
+    // Receiver 被丢弃后不再发送
+    drop(events);
+    write_config(&path, 9001);
+    config.reload().unwrap();
+    assert_eq!(last_version.load(Ordering::Relaxed), 2);
+}
+
+/// # example03 拒绝不合法的配置
+/// 语法错误或者校验失败时, 配置保持不变, 订阅者收到 Rejected
+#[allow(unused)]
+fn example03() {
+    let path = temp_config("reject", 8080);
+    let config = HotConfig::load(file_loader(&path)).unwrap();
+    let events = config.watch();
+
+    std::fs::write(&path, "name = \"broken\n").unwrap();
+    let err = config.reload().unwrap_err();
+    assert_eq!(err.kind, "parse");
+
+    write_config(&path, 80);
+    let err = config.reload().unwrap_err();
+    assert!(err.message.contains("privileged port"));
+
+    for event in events.try_iter() {
+        match event {
+            ConfigEvent::Rejected(err) => eprintln!("rejected: {err:?}"),
+            ConfigEvent::Updated { .. } => panic!("invalid config should be rejected"),
+        }
+    }
+    assert_eq!(config.snapshot().port, 8080);
+    assert_eq!(config.version(), 0);
+
+    // 初始配置就不合法时无法创建
+    assert!(HotConfig::load(file_loader(&path)).is_err());
+}
+
+/// # example04 文件变化时自动重新加载
+#[allow(unused)]
+fn example04() {
+    let path = temp_config("watch", 8080);
+    let config = Arc::new(HotConfig::load(file_loader(&path)).unwrap());
+    let events = config.watch();
+
+    let watcher = FileWatcher::spawn(&path, Duration::from_millis(10), {
+        let config = Arc::clone(&config);
+        move || {
+            // 失败的结果通过 Rejected 通知订阅者
+            let _ = config.reload();
+        }
+    });
+
+    let mut next = |timeout| events.recv_timeout(timeout).unwrap();
+    write_config(&path, 9000);
+    match next(Duration::from_secs(5)) {
+        ConfigEvent::Updated { new, .. } => assert_eq!(new.port, 9000),
+        ConfigEvent::Rejected(err) => panic!("{err:?}"),
+    }
+    write_config(&path, 80);
+    assert!(matches!(
+        next(Duration::from_secs(5)),
+        ConfigEvent::Rejected(_)
+    ));
+    assert_eq!(config.snapshot().port, 9000);
+
+    watcher.stop();
+    write_config(&path, 9100);
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(config.snapshot().port, 9000);
+}
+
+/// # example05 全局热更新配置
+/// 读线程在重新加载的同时不断读取, 每次读到的都是完整的一份配置
+#[allow(unused)]
+fn example05() {
+    use global_config::GlobalConfig;
+
+    static CONFIG: GlobalConfig<HotConfig<Config>> = GlobalConfig::new();
+
+    let path = temp_config("global", 8080);
+    let loaded = HotConfig::load(file_loader(&path)).unwrap();
+    let config: &'static HotConfig<Config> = CONFIG.init_once(loaded).unwrap();
+
+    std::thread::scope(|s| {
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut last_port = 0;
+                    for _ in 0..10_000 {
+                        let snapshot = CONFIG.get().unwrap().snapshot();
+                        // name 和 port 总是来自同一次加载
+                        assert_eq!(snapshot.name, format!("v{}", snapshot.port));
+                        // 版本只会前进
+                        assert!(snapshot.port >= last_port);
+                        last_port = snapshot.port;
+                    }
+                })
+            })
+            .collect();
+        for port in 8081..8131 {
+            write_config(&path, port);
+            config.reload().unwrap();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    });
+    println!("{config:?}");
+    assert_eq!(config.version(), 50);
+
+    let dir = std::env::temp_dir().join(format!("hot_reload_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,196 @@
+/// 其他格式的 ID: UUIDv4 / UUIDv7 / ULID / Base62 短 ID
+///
+/// 各种格式的实现见 id_format.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod id_format;
+mod id_generator;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use id_format::{IdFormat, IdGenerator};
+use id_generator::TimeSource;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 可以手动拨动的时钟
+struct ManualClock(AtomicU64);
+
+impl ManualClock {
+    fn new(ms: u64) -> ManualClock {
+        ManualClock(AtomicU64::new(ms))
+    }
+
+    fn set(&self, ms: u64) {
+        self.0.store(ms, Ordering::Relaxed);
+    }
+}
+
+impl TimeSource for ManualClock {
+    fn now_ms(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// 生成 n 个 ID, 检查格式化后能解析回原值, 并返回所有 ID
+fn round_trip<G: IdGenerator>(generator: &G, n: usize) -> Vec<G::Id> {
+    (0..n)
+        .map(|_| {
+            let id = generator.generate().unwrap();
+            let text = id.format();
+            assert_eq!(G::Id::parse(&text).unwrap(), id, "{text}");
+            id
+        })
+        .collect()
+}
+
+/// 检查 ID 和格式化后的字符串都严格递增
+fn assert_monotonic<T: IdFormat>(ids: &[T]) {
+    for pair in ids.windows(2) {
+        assert!(pair[0] < pair[1], "{:?} >= {:?}", pair[0], pair[1]);
+        assert!(pair[0].format() < pair[1].format());
+    }
+}
+
+/// # example01 UUIDv4
+/// 122 位随机数, 4 位版本号, 2 位变体
+#[allow(unused)]
+fn example01() {
+    use id_format::{Uuid, UuidV4Generator};
+
+    let generator = UuidV4Generator::new();
+    let ids = round_trip(&generator, 1_000);
+    println!("uuid v4: {}", ids[0].format());
+    assert!(ids
+        .iter()
+        .all(|id| id.version() == 4 && id.is_rfc_variant()));
+
+    // 固定种子时可以复现
+    let a = UuidV4Generator::with_seed(7).generate().unwrap();
+    let b = UuidV4Generator::with_seed(7).generate().unwrap();
+    assert_eq!(a, b);
+
+    // 解析已知的 UUID, 大写也可以
+    let id = Uuid::parse("F81D4FAE-7DEC-11D0-A765-00A0C91E6BF6").unwrap();
+    assert_eq!(id.format(), "f81d4fae-7dec-11d0-a765-00a0c91e6bf6");
+    assert_eq!(id.version(), 1);
+
+    // 格式错误
+    for bad in [
+        "",
+        "f81d4fae7dec11d0a76500a0c91e6bf6",
+        "g81d4fae-7dec-11d0-a765-00a0c91e6bf6",
+    ] {
+        let err = Uuid::parse(bad).unwrap_err();
+        assert_eq!(err.kind, "parse");
+    }
+}
+
+/// # example02 UUIDv7
+/// 时间戳在最高位, 同一毫秒内随机部分递增, 所以严格按生成顺序排序
+#[allow(unused)]
+fn example02() {
+    use id_format::UuidV7Generator;
+
+    let clock = ManualClock::new(1_700_000_000_000);
+    let generator = UuidV7Generator::with_clock(&clock, 42);
+    // 同一毫秒内生成
+    let mut ids = round_trip(&generator, 1_000);
+    assert!(ids
+        .iter()
+        .all(|id| id.timestamp_ms() == Some(1_700_000_000_000)));
+    // 时间前进
+    clock.set(1_700_000_000_001);
+    ids.extend(round_trip(&generator, 10));
+    // 时钟回拨, 依然递增
+    clock.set(1_699_999_999_000);
+    ids.extend(round_trip(&generator, 10));
+    assert_monotonic(&ids);
+    assert!(ids
+        .iter()
+        .all(|id| id.version() == 7 && id.is_rfc_variant()));
+    println!("uuid v7: {}", ids[0].format());
+
+    // 系统时钟
+    let generator = UuidV7Generator::new();
+    assert_monotonic(&round_trip(&generator, 1_000));
+}
+
+/// # example03 ULID
+#[allow(unused)]
+fn example03() {
+    use id_format::{Ulid, UlidGenerator};
+
+    let clock = ManualClock::new(1_469_918_176_385);
+    let generator = UlidGenerator::with_clock(&clock, 1);
+    let mut ids = round_trip(&generator, 1_000);
+    clock.set(1_469_918_176_386);
+    ids.extend(round_trip(&generator, 10));
+    assert_monotonic(&ids);
+    assert_eq!(ids[0].timestamp_ms(), 1_469_918_176_385);
+    // 时间戳编码在前 10 个字符中
+    assert!(ids[0].format().starts_with("01ARYZ6S41"));
+    println!("ulid: {}", ids[0].format());
+
+    // 不区分大小写, I L O 会被当作 1 1 0
+    let id = Ulid::parse("01ARYZ6S41TSV4RRFFQ69G5FAV").unwrap();
+    assert_eq!(Ulid::parse("01aryz6s41tsv4rrffq69g5fav").unwrap(), id);
+    assert_eq!(
+        Ulid::parse("OIARYZ6S4LTSV4RRFFQ69G5FAV").unwrap(),
+        Ulid::parse("01ARYZ6S41TSV4RRFFQ69G5FAV").unwrap()
+    );
+    // 最大值是 7ZZZZZZZZZZZZZZZZZZZZZZZZZ
+    assert_eq!(Ulid(u128::MAX).format(), "7ZZZZZZZZZZZZZZZZZZZZZZZZZ");
+    assert!(Ulid::parse("8ZZZZZZZZZZZZZZZZZZZZZZZZZ").is_err());
+    assert!(Ulid::parse("01ARYZ6S41TSV4RRFFQ69G5FAU").is_err());
+}
+
+/// # example04 Base62 短 ID
+#[allow(unused)]
+fn example04() {
+    use id_format::{ShortId, ShortIdGenerator};
+
+    let generator = ShortIdGenerator::new(1).unwrap();
+    let ids = round_trip(&generator, 1_000);
+    assert_monotonic(&ids);
+    println!("short id: {}", ids[0].format());
+
+    assert_eq!(ShortId(0).format(), "00000000000");
+    assert_eq!(ShortId(61).format(), "0000000000z");
+    assert_eq!(ShortId(u64::MAX).format(), "LygHa16AHYF");
+    assert_eq!(ShortId::parse("z").unwrap(), ShortId(61));
+    // 超过 u64 的范围
+    assert!(ShortId::parse("LygHa16AHYG").is_err());
+    assert!(ShortId::parse("abc-").is_err());
+
+    // 机器 ID 超出范围的错误来自 id_generator, 通过 ? 转为 AppError
+    assert_eq!(ShortIdGenerator::new(4096).err().unwrap().code, 400);
+}
+
+/// # example05 通过 IdFormat 统一处理各种 ID
+#[allow(unused)]
+fn example05() {
+    use id_format::{ShortIdGenerator, UlidGenerator, UuidV4Generator, UuidV7Generator};
+
+    fn describe<G: IdGenerator>(generator: &G) -> String {
+        let id = generator.generate().unwrap();
+        format!("{:>8}: {}", <G::Id as IdFormat>::NAME, id.format())
+    }
+
+    println!("{}", describe(&UuidV4Generator::new()));
+    println!("{}", describe(&UuidV7Generator::new()));
+    println!("{}", describe(&UlidGenerator::new()));
+    println!("{}", describe(&ShortIdGenerator::new(2).unwrap()));
+}
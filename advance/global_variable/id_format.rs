@@ -0,0 +1,393 @@
+/// 其他格式的 ID
+///
+/// `Factory` 的 usize ID 只在一个进程内唯一, 服务之间常用的还有:
+/// - UUIDv4: 122 位随机数
+/// - UUIDv7: 48 位毫秒时间戳 + 随机数, 按时间排序
+/// - ULID: 48 位毫秒时间戳 + 80 位随机数, 用 Crockford Base32 编码成 26 个字符
+/// - ShortId: 雪花 ID 用 Base62 编码成 11 个字符, 适合放在 URL 中
+///
+/// 所有格式都实现了 `IdFormat`, 可以格式化为字符串, 也可以从字符串解析回来
+///
+/// ! 随机数来自标准库的 RandomState 和 SplitMix64, 不是密码学安全的, 不要把这些 ID 当作令牌使用
+///
+/// *依赖 crate 根引入的 `app_error` 和 `id_generator` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use crate::id_generator::{Snowflake, SystemTimeSource, TimeSource};
+use std::sync::Mutex;
+
+/// ID 格式: 格式化与解析互为逆操作
+pub trait IdFormat: Sized + Copy + Ord + std::fmt::Debug {
+    /// 格式名称, 用于错误信息
+    const NAME: &'static str;
+
+    /// 格式化为字符串
+    fn format(&self) -> String;
+
+    /// 从字符串解析
+    fn parse(s: &str) -> Result<Self, AppError>;
+}
+
+/// ID 生成器, 与 Factory 的 `generate_id()` 相同, 每次调用生成一个新的 ID
+pub trait IdGenerator {
+    type Id: IdFormat;
+
+    fn generate(&self) -> Result<Self::Id, AppError>;
+}
+
+fn parse_error<T: IdFormat>(s: &str, reason: &str) -> AppError {
+    AppError::new("parse", 400, format!("invalid {} {s:?}: {reason}", T::NAME))
+}
+
+/// SplitMix64 随机数生成器
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64(seed)
+    }
+
+    /// 用标准库 HashMap 的随机种子初始化, 每个进程都不一样
+    pub fn from_entropy() -> SplitMix64 {
+        use std::hash::{BuildHasher, Hasher};
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u128(SystemTimeSource.now_ms() as u128);
+        SplitMix64(hasher.finish())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u128(&mut self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+}
+
+/// 48 位毫秒时间戳的最大值, 可以用到公元 10889 年
+const MAX_TIMESTAMP_48: u64 = (1 << 48) - 1;
+
+/// 同一毫秒内保持单调递增的状态
+/// - 时间前进: 使用新的时间戳, 随机部分重新生成
+/// - 同一毫秒或者时钟回拨: 沿用上一次的时间戳, 随机部分 + 1
+/// - 随机部分溢出: 借用下一毫秒
+struct Monotonic {
+    last_ms: u64,
+    last_random: u128,
+}
+
+impl Monotonic {
+    /// random_bits 是随机部分的位数, 返回 (时间戳, 随机部分)
+    fn next(&mut self, now_ms: u64, random_bits: u32, rng: &mut SplitMix64) -> (u64, u128) {
+        let mask = (1u128 << random_bits) - 1;
+        if now_ms > self.last_ms {
+            self.last_ms = now_ms;
+            // 最高位留 0, 给同一毫秒内的递增留出空间
+            self.last_random = rng.next_u128() & (mask >> 1);
+        } else if self.last_random < mask {
+            self.last_random += 1;
+        } else {
+            self.last_ms += 1;
+            self.last_random = rng.next_u128() & (mask >> 1);
+        }
+        (self.last_ms.min(MAX_TIMESTAMP_48), self.last_random)
+    }
+}
+
+/// # Uuid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(pub u128);
+
+#[allow(unused)]
+impl Uuid {
+    /// 版本号, 位于第 48..52 位
+    pub fn version(&self) -> u8 {
+        ((self.0 >> 76) & 0xf) as u8
+    }
+
+    /// RFC 9562 规定的变体, 最高两位为 0b10
+    pub fn is_rfc_variant(&self) -> bool {
+        (self.0 >> 62) & 0b11 == 0b10
+    }
+
+    /// UUIDv7 中的毫秒时间戳
+    pub fn timestamp_ms(&self) -> Option<u64> {
+        (self.version() == 7).then_some((self.0 >> 80) as u64)
+    }
+
+    fn with_version(bits: u128, version: u8) -> Uuid {
+        let bits = (bits & !(0xf << 76)) | ((version as u128) << 76);
+        let bits = (bits & !(0b11 << 62)) | (0b10 << 62);
+        Uuid(bits)
+    }
+}
+
+impl IdFormat for Uuid {
+    const NAME: &'static str = "uuid";
+
+    /// 8-4-4-4-12 个小写十六进制字符
+    fn format(&self) -> String {
+        let hex = format!("{:032x}", self.0);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+
+    fn parse(s: &str) -> Result<Uuid, AppError> {
+        let groups: Vec<&str> = s.split('-').collect();
+        if groups.iter().map(|g| g.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12] {
+            return Err(parse_error::<Uuid>(s, "expected 8-4-4-4-12 groups"));
+        }
+        let hex = groups.concat();
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(parse_error::<Uuid>(s, "invalid hex digit"));
+        }
+        u128::from_str_radix(&hex, 16)
+            .map(Uuid)
+            .map_err(|e| parse_error::<Uuid>(s, &e.to_string()))
+    }
+}
+
+/// # UuidV4Generator 随机 UUID
+pub struct UuidV4Generator {
+    rng: Mutex<SplitMix64>,
+}
+
+#[allow(unused)]
+impl UuidV4Generator {
+    pub fn new() -> UuidV4Generator {
+        UuidV4Generator::with_seed(SplitMix64::from_entropy().next_u64())
+    }
+
+    /// 固定种子, 生成的序列可以复现
+    pub fn with_seed(seed: u64) -> UuidV4Generator {
+        UuidV4Generator {
+            rng: Mutex::new(SplitMix64::new(seed)),
+        }
+    }
+}
+
+impl IdGenerator for UuidV4Generator {
+    type Id = Uuid;
+
+    fn generate(&self) -> Result<Uuid, AppError> {
+        let bits = self.rng.lock().unwrap().next_u128();
+        Ok(Uuid::with_version(bits, 4))
+    }
+}
+
+/// # UuidV7Generator 按时间排序的 UUID
+///
+/// 48 位时间戳 | 4 位版本 | 12 位 rand_a | 2 位变体 | 62 位 rand_b
+///
+/// rand_a 和 rand_b 一共 74 位作为随机部分, 同一毫秒内递增, 所以生成的 UUID 严格递增
+pub struct UuidV7Generator<C: TimeSource = SystemTimeSource> {
+    clock: C,
+    state: Mutex<(Monotonic, SplitMix64)>,
+}
+
+#[allow(unused)]
+impl UuidV7Generator<SystemTimeSource> {
+    pub fn new() -> Self {
+        UuidV7Generator::with_clock(SystemTimeSource, SplitMix64::from_entropy().next_u64())
+    }
+}
+
+#[allow(unused)]
+impl<C: TimeSource> UuidV7Generator<C> {
+    pub fn with_clock(clock: C, seed: u64) -> Self {
+        UuidV7Generator {
+            clock,
+            state: Mutex::new((
+                Monotonic {
+                    last_ms: 0,
+                    last_random: 0,
+                },
+                SplitMix64::new(seed),
+            )),
+        }
+    }
+}
+
+impl<C: TimeSource> IdGenerator for UuidV7Generator<C> {
+    type Id = Uuid;
+
+    fn generate(&self) -> Result<Uuid, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let (monotonic, rng) = &mut *state;
+        let (ms, random) = monotonic.next(self.clock.now_ms(), 74, rng);
+        // 随机部分的高 12 位放到 rand_a, 低 62 位放到 rand_b
+        let rand_a = random >> 62;
+        let rand_b = random & ((1 << 62) - 1);
+        let bits = ((ms as u128) << 80) | (rand_a << 64) | rand_b;
+        Ok(Uuid::with_version(bits, 7))
+    }
+}
+
+/// Crockford Base32 字母表, 去掉了容易混淆的 I L O U
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// # Ulid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ulid(pub u128);
+
+#[allow(unused)]
+impl Ulid {
+    pub fn timestamp_ms(&self) -> u64 {
+        (self.0 >> 80) as u64
+    }
+}
+
+impl IdFormat for Ulid {
+    const NAME: &'static str = "ulid";
+
+    /// 26 个字符, 每个字符 5 位, 第一个字符只用到低 3 位
+    fn format(&self) -> String {
+        (0..26)
+            .rev()
+            .map(|i| CROCKFORD[((self.0 >> (i * 5)) & 0x1f) as usize] as char)
+            .collect()
+    }
+
+    /// 不区分大小写, 并按 Crockford 规范把 I L 当作 1, O 当作 0
+    fn parse(s: &str) -> Result<Ulid, AppError> {
+        if s.len() != 26 {
+            return Err(parse_error::<Ulid>(s, "expected 26 characters"));
+        }
+        let mut bits: u128 = 0;
+        for (i, c) in s.bytes().enumerate() {
+            let c = match c.to_ascii_uppercase() {
+                b'I' | b'L' => b'1',
+                b'O' => b'0',
+                c => c,
+            };
+            let digit = CROCKFORD
+                .iter()
+                .position(|&d| d == c)
+                .ok_or_else(|| parse_error::<Ulid>(s, "invalid character"))?;
+            if i == 0 && digit > 7 {
+                return Err(parse_error::<Ulid>(s, "overflows 128 bits"));
+            }
+            bits = (bits << 5) | digit as u128;
+        }
+        Ok(Ulid(bits))
+    }
+}
+
+/// # UlidGenerator 单调递增的 ULID
+pub struct UlidGenerator<C: TimeSource = SystemTimeSource> {
+    clock: C,
+    state: Mutex<(Monotonic, SplitMix64)>,
+}
+
+#[allow(unused)]
+impl UlidGenerator<SystemTimeSource> {
+    pub fn new() -> Self {
+        UlidGenerator::with_clock(SystemTimeSource, SplitMix64::from_entropy().next_u64())
+    }
+}
+
+#[allow(unused)]
+impl<C: TimeSource> UlidGenerator<C> {
+    pub fn with_clock(clock: C, seed: u64) -> Self {
+        UlidGenerator {
+            clock,
+            state: Mutex::new((
+                Monotonic {
+                    last_ms: 0,
+                    last_random: 0,
+                },
+                SplitMix64::new(seed),
+            )),
+        }
+    }
+}
+
+impl<C: TimeSource> IdGenerator for UlidGenerator<C> {
+    type Id = Ulid;
+
+    fn generate(&self) -> Result<Ulid, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let (monotonic, rng) = &mut *state;
+        let (ms, random) = monotonic.next(self.clock.now_ms(), 80, rng);
+        Ok(Ulid(((ms as u128) << 80) | random))
+    }
+}
+
+/// Base62 字母表, 按 ASCII 顺序排列, 所以定长编码后字符串顺序和数值顺序一致
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+/// u64 最多需要 11 个 Base62 字符
+const SHORT_ID_LEN: usize = 11;
+
+/// # ShortId Base62 编码的 64 位 ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShortId(pub u64);
+
+impl IdFormat for ShortId {
+    const NAME: &'static str = "short id";
+
+    /// 固定 11 个字符, 不足时左侧补 0
+    fn format(&self) -> String {
+        let mut buf = [b'0'; SHORT_ID_LEN];
+        let mut n = self.0;
+        for slot in buf.iter_mut().rev() {
+            *slot = BASE62[(n % 62) as usize];
+            n /= 62;
+        }
+        String::from_utf8(buf.to_vec()).unwrap()
+    }
+
+    /// 也接受没有补 0 的短字符串
+    fn parse(s: &str) -> Result<ShortId, AppError> {
+        if s.is_empty() || s.len() > SHORT_ID_LEN {
+            return Err(parse_error::<ShortId>(s, "expected 1 to 11 characters"));
+        }
+        s.bytes()
+            .try_fold(0u64, |acc, c| {
+                let digit = BASE62.iter().position(|&d| d == c)? as u64;
+                acc.checked_mul(62)?.checked_add(digit)
+            })
+            .map(ShortId)
+            .ok_or_else(|| parse_error::<ShortId>(s, "invalid character or overflow"))
+    }
+}
+
+/// # ShortIdGenerator 把雪花 ID 编码为短 ID
+pub struct ShortIdGenerator<C: TimeSource = SystemTimeSource> {
+    snowflake: Snowflake<C>,
+}
+
+#[allow(unused)]
+impl ShortIdGenerator<SystemTimeSource> {
+    pub fn new(worker_id: u16) -> Result<Self, AppError> {
+        Ok(ShortIdGenerator {
+            snowflake: Snowflake::new(worker_id)?,
+        })
+    }
+}
+
+#[allow(unused)]
+impl<C: TimeSource> ShortIdGenerator<C> {
+    pub fn with_snowflake(snowflake: Snowflake<C>) -> Self {
+        ShortIdGenerator { snowflake }
+    }
+}
+
+impl<C: TimeSource> IdGenerator for ShortIdGenerator<C> {
+    type Id = ShortId;
+
+    fn generate(&self) -> Result<ShortId, AppError> {
+        Ok(ShortId(self.snowflake.next_id()?))
+    }
+}
@@ -0,0 +1,271 @@
+/// ID 生成器
+///
+/// example01 中的 `generate_id` 先 load, 再 fetch_add, 最后再 load,
+/// 三步之间其他线程可以插进来, 两个线程可能拿到同一个 ID; 并且溢出时直接 panic
+///
+/// 这里提供两种生成器:
+/// - `Counter`: 单调递增计数器, 用 fetch_update + checked_add 一步完成检查和自增, 溢出时返回错误
+/// - `Snowflake`: 64 位雪花 ID, 由时间戳 / 机器 ID / 序列号组成, 可以在多台机器上独立生成
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 生成 ID 时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdError {
+    /// 计数器超过上限
+    Overflow { max: usize },
+    /// 时钟回拨超过了容忍范围
+    ClockMovedBackwards { last_ms: u64, now_ms: u64 },
+    /// 同一毫秒内的序列号用完, 且借用未来时间超过了容忍范围
+    SequenceExhausted { timestamp_ms: u64 },
+    /// 机器 ID 超出范围
+    InvalidWorkerId(u16),
+    /// 时间戳早于纪元, 或者超出了 41 位能表示的范围
+    TimestampOutOfRange { timestamp_ms: u64 },
+}
+
+impl std::fmt::Display for IdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdError::Overflow { max } => write!(f, "Factory ids overflowed (max {max})"),
+            IdError::ClockMovedBackwards { last_ms, now_ms } => {
+                write!(f, "clock moved backwards from {last_ms} to {now_ms}")
+            }
+            IdError::SequenceExhausted { timestamp_ms } => {
+                write!(f, "sequence exhausted at {timestamp_ms}")
+            }
+            IdError::InvalidWorkerId(id) => {
+                write!(f, "worker id {id} is out of range 0..={MAX_WORKER_ID}")
+            }
+            IdError::TimestampOutOfRange { timestamp_ms } => {
+                write!(f, "timestamp {timestamp_ms} is out of the snowflake range")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IdError {}
+
+/// 将 IdError 转为 AppError, 之后就可以在返回 AppError 的函数中使用 ?
+impl From<IdError> for AppError {
+    fn from(value: IdError) -> Self {
+        let code = match value {
+            IdError::InvalidWorkerId(_) | IdError::TimestampOutOfRange { .. } => 400,
+            _ => 503,
+        };
+        AppError::new("id", code, value.to_string())
+    }
+}
+
+/// # Counter 单调递增计数器
+///
+/// 可以直接用作静态变量: `static COUNTER: Counter = Counter::new(usize::MAX / 2);`
+pub struct Counter {
+    value: AtomicUsize,
+    max: usize,
+}
+
+#[allow(unused)]
+impl Counter {
+    /// 创建计数器, 生成的 ID 从 1 开始, 不超过 max
+    pub const fn new(max: usize) -> Counter {
+        Counter {
+            value: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    /// 从 start 开始计数, 下一个 ID 是 start + 1
+    pub const fn starting_at(start: usize, max: usize) -> Counter {
+        Counter {
+            value: AtomicUsize::new(start),
+            max,
+        }
+    }
+
+    /// 生成下一个 ID
+    ///
+    /// fetch_update 内部是一个 compare_exchange 循环:
+    /// 读取旧值, 计算新值, 只有旧值没被其他线程改过时才写入, 否则重新读取再试
+    /// 所以 "检查是否溢出" 和 "自增" 是一个整体, 每个线程拿到的 ID 都不一样
+    ///
+    /// 这里只需要保证计数器本身的原子性, 不用它同步其他内存, 所以 Relaxed 就够了
+    /// 需要同步其他内存时 Relaxed 就不够了, 见 ordering_lab.rs
+    pub fn next(&self) -> Result<usize, IdError> {
+        self.value
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+                current.checked_add(1).filter(|next| *next <= self.max)
+            })
+            .map(|previous| previous + 1)
+            .map_err(|_| IdError::Overflow { max: self.max })
+    }
+
+    /// 最近一次生成的 ID, 还没有生成过时为 0
+    pub fn current(&self) -> usize {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// 时间戳占 41 位, 机器 ID 占 10 位, 序列号占 12 位, 最高位恒为 0
+pub const TIMESTAMP_BITS: u32 = 41;
+pub const WORKER_ID_BITS: u32 = 10;
+pub const SEQUENCE_BITS: u32 = 12;
+pub const MAX_WORKER_ID: u16 = (1 << WORKER_ID_BITS) - 1;
+pub const MAX_SEQUENCE: u64 = (1 << SEQUENCE_BITS) - 1;
+
+/// 自定义纪元: 2024-01-01 00:00:00 UTC, 41 位毫秒时间戳可以用到 2093 年
+pub const EPOCH_MS: u64 = 1_704_067_200_000;
+
+/// 时间来源, 可以注入假的时钟来模拟时钟回拨
+pub trait TimeSource {
+    /// 自 UNIX 纪元以来的毫秒数
+    fn now_ms(&self) -> u64;
+}
+
+/// 系统时钟
+pub struct SystemTimeSource;
+
+impl TimeSource for SystemTimeSource {
+    fn now_ms(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64)
+    }
+}
+
+/// 时钟的引用也可以作为时间来源, 方便在多个生成器之间共享同一个假时钟
+impl<T: TimeSource> TimeSource for &T {
+    fn now_ms(&self) -> u64 {
+        (**self).now_ms()
+    }
+}
+
+/// 雪花 ID 的组成部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnowflakeParts {
+    /// 自 UNIX 纪元以来的毫秒数
+    pub timestamp_ms: u64,
+    pub worker_id: u16,
+    pub sequence: u64,
+}
+
+#[allow(unused)]
+impl SnowflakeParts {
+    /// 拆解雪花 ID
+    pub fn from_id(id: u64) -> SnowflakeParts {
+        SnowflakeParts {
+            timestamp_ms: (id >> (WORKER_ID_BITS + SEQUENCE_BITS)) + EPOCH_MS,
+            worker_id: ((id >> SEQUENCE_BITS) & MAX_WORKER_ID as u64) as u16,
+            sequence: id & MAX_SEQUENCE,
+        }
+    }
+
+    /// 组装雪花 ID
+    ///
+    /// 时间戳必须在 `EPOCH_MS..EPOCH_MS + 2^41` 之内, 否则移位之后会覆盖其他部分或者溢出
+    pub fn to_id(self) -> Result<u64, IdError> {
+        let timestamp = self
+            .timestamp_ms
+            .checked_sub(EPOCH_MS)
+            .filter(|&t| t < 1 << TIMESTAMP_BITS)
+            .ok_or(IdError::TimestampOutOfRange {
+                timestamp_ms: self.timestamp_ms,
+            })?;
+        if self.worker_id > MAX_WORKER_ID {
+            return Err(IdError::InvalidWorkerId(self.worker_id));
+        }
+        Ok((timestamp << (WORKER_ID_BITS + SEQUENCE_BITS))
+            | ((self.worker_id as u64) << SEQUENCE_BITS)
+            | (self.sequence & MAX_SEQUENCE))
+    }
+}
+
+/// # Snowflake 雪花 ID 生成器
+///
+/// ## 时钟回拨
+/// 系统时间可能被 NTP 调回去, 直接使用回拨后的时间会生成重复的 ID
+/// - 回拨不超过 tolerance_ms 时继续使用上一次的时间戳, 保证 ID 依然递增
+/// - 回拨超过 tolerance_ms 时返回 `IdError::ClockMovedBackwards`
+///
+/// 同一毫秒内序列号用完时, 借用下一毫秒的时间戳, 同样不能超前 tolerance_ms
+pub struct Snowflake<C: TimeSource = SystemTimeSource> {
+    worker_id: u16,
+    tolerance_ms: u64,
+    clock: C,
+    // (上一次的时间戳, 上一次的序列号)
+    state: Mutex<(u64, u64)>,
+}
+
+#[allow(unused)]
+impl Snowflake<SystemTimeSource> {
+    /// 使用系统时钟创建生成器, 默认容忍 10ms 的时钟回拨
+    pub fn new(worker_id: u16) -> Result<Self, IdError> {
+        Snowflake::with_clock(worker_id, 10, SystemTimeSource)
+    }
+}
+
+#[allow(unused)]
+impl<C: TimeSource> Snowflake<C> {
+    /// 使用指定的时钟创建生成器
+    pub fn with_clock(worker_id: u16, tolerance_ms: u64, clock: C) -> Result<Self, IdError> {
+        if worker_id > MAX_WORKER_ID {
+            return Err(IdError::InvalidWorkerId(worker_id));
+        }
+        Ok(Snowflake {
+            worker_id,
+            tolerance_ms,
+            clock,
+            state: Mutex::new((0, 0)),
+        })
+    }
+
+    /// 生成下一个 ID
+    pub fn next_id(&self) -> Result<u64, IdError> {
+        let now_ms = self.clock.now_ms();
+        // 早于纪元的时钟与 to_id 一样报错, 不能当作纪元本身生成 ID
+        let now = now_ms
+            .checked_sub(EPOCH_MS)
+            .ok_or(IdError::TimestampOutOfRange {
+                timestamp_ms: now_ms,
+            })?;
+        let mut state = self.state.lock().unwrap();
+        let (last, sequence) = *state;
+
+        if now + self.tolerance_ms < last {
+            return Err(IdError::ClockMovedBackwards {
+                last_ms: last + EPOCH_MS,
+                now_ms: now + EPOCH_MS,
+            });
+        }
+
+        let (timestamp, sequence) = if now > last {
+            (now, 0)
+        } else if sequence < MAX_SEQUENCE {
+            // 同一毫秒, 或者小幅回拨: 沿用上一次的时间戳
+            (last, sequence + 1)
+        } else {
+            // 序列号用完, 借用下一毫秒
+            (last + 1, 0)
+        };
+        if timestamp > now + self.tolerance_ms {
+            return Err(IdError::SequenceExhausted {
+                timestamp_ms: last + EPOCH_MS,
+            });
+        }
+
+        *state = (timestamp, sequence);
+        let parts = SnowflakeParts {
+            timestamp_ms: timestamp + EPOCH_MS,
+            worker_id: self.worker_id,
+            sequence,
+        };
+        parts.to_id()
+    }
+}
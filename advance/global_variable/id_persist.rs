@@ -0,0 +1,177 @@
+/// ID 生成器持久化与崩溃恢复
+///
+/// 存储和计数器的实现见 id_store.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod id_store;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use id_store::{CrashPoint, FileStore, MemoryStore, PersistentCounter};
+use std::path::PathBuf;
+
+/// 设置了该环境变量时, 当前进程作为 example05 中被杀掉的子进程运行
+const CHILD_ENV: &str = "ID_PERSIST_CHILD";
+
+fn main() -> panic_boundary::Report {
+    if let Ok(path) = std::env::var(CHILD_ENV) {
+        run_child(PathBuf::from(path));
+        return panic_boundary::Report::default();
+    }
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 在临时目录下创建一个空的工作目录
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("id_persist_{}_{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// # example01 按块预留
+/// 每次预留 10 个 ID, 只有用完一块时才写一次存储
+#[allow(unused)]
+fn example01() {
+    let store = MemoryStore::default();
+
+    {
+        let counter = PersistentCounter::open(&store, 10).unwrap();
+        let ids: Vec<usize> = (0..25).map(|_| counter.next().unwrap()).collect();
+        assert_eq!(ids, (1..=25).collect::<Vec<_>>());
+        assert_eq!(counter.reserved(), 30);
+        // 离开作用域即 "停止", PersistentCounter 没有 Drop, 不会写回已经用到的 25
+    }
+
+    // "重启": 从高水位 30 继续, 26..=30 被跳过, 但不会重复
+    let counter = PersistentCounter::open(&store, 10).unwrap();
+    assert_eq!(counter.next().unwrap(), 31);
+    assert_eq!(counter.reserved(), 40);
+}
+
+/// # example02 文件存储
+#[allow(unused)]
+fn example02() {
+    let dir = temp_dir("file");
+    let path = dir.join("factory.id");
+
+    let counter = PersistentCounter::open(FileStore::new(&path), 100).unwrap();
+    for expected in 1..=150 {
+        assert_eq!(counter.next().unwrap(), expected);
+    }
+    println!("{}", std::fs::read_to_string(&path).unwrap().trim());
+
+    let counter = PersistentCounter::open(FileStore::new(&path), 100).unwrap();
+    assert_eq!(counter.next().unwrap(), 201);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// # example03 预留过程中崩溃
+/// 无论崩溃在临时文件写了一半, 还是写完但没来得及 rename,
+/// 正式文件依然是上一次的高水位, 重启后不会发放重复的 ID
+#[allow(unused)]
+fn example03() {
+    for point in [CrashPoint::MidTempWrite, CrashPoint::BeforeRename] {
+        let dir = temp_dir("crash");
+        let path = dir.join("factory.id");
+
+        let counter = PersistentCounter::open(FileStore::new(&path), 10).unwrap();
+        let issued: Vec<usize> = (0..10).map(|_| counter.next().unwrap()).collect();
+        drop(counter);
+
+        // 预留第二块时崩溃, 没有发放任何 ID
+        let crashing = PersistentCounter::open(FileStore::new(&path).crash_at(point), 10).unwrap();
+        let err = crashing.next().unwrap_err();
+        assert_eq!(err.kind, "crash");
+        assert!(dir.join("factory.id.tmp").exists());
+
+        // 重启后从第一块的高水位继续, 残留的临时文件会被下一次写入覆盖
+        let counter = PersistentCounter::open(FileStore::new(&path), 10).unwrap();
+        let next = counter.next().unwrap();
+        assert!(issued.iter().all(|id| *id < next), "{point:?}");
+        assert_eq!(next, 11);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
+
+/// # example04 文件被破坏时返回错误, 而不是从 0 重新开始
+#[allow(unused)]
+fn example04() {
+    let dir = temp_dir("corrupted");
+    let path = dir.join("factory.id");
+    std::fs::write(&path, "idgen-v1 12").unwrap();
+    assert!(PersistentCounter::open(FileStore::new(&path), 10).is_ok());
+
+    std::fs::write(&path, "idge").unwrap();
+    let err = PersistentCounter::open(FileStore::new(&path), 10)
+        .err()
+        .unwrap();
+    eprintln!("{err:?}");
+    assert_eq!(err.kind, "corrupted");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// example05 的子进程: 不停地生成 ID 并打印, 直到被杀掉
+fn run_child(path: PathBuf) {
+    use std::io::Write;
+
+    let counter = PersistentCounter::open(FileStore::new(path), 5).unwrap();
+    let mut stdout = std::io::stdout();
+    loop {
+        let id = counter.next().unwrap();
+        if writeln!(stdout, "{id}")
+            .and_then(|_| stdout.flush())
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// # example05 真的杀掉进程
+/// 子进程一边预留一边发放 ID, 父进程读到一些 ID 后直接 kill 掉它,
+/// 再用同一个文件重启计数器, 新的 ID 必须大于子进程发放过的所有 ID
+#[allow(unused)]
+fn example05() {
+    use std::io::{BufRead, BufReader};
+    use std::process::{Command, Stdio};
+
+    let dir = temp_dir("kill");
+    let path = dir.join("factory.id");
+
+    for round in 0..3 {
+        let mut child = Command::new(std::env::current_exe().unwrap())
+            .env(CHILD_ENV, &path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let seen: Vec<usize> = BufReader::new(stdout)
+            .lines()
+            .take(500 + round * 37)
+            .map(|line| line.unwrap().parse().unwrap())
+            .collect();
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let max_seen = *seen.iter().max().unwrap();
+        let counter = PersistentCounter::open(FileStore::new(&path), 5).unwrap();
+        let next = counter.next().unwrap();
+        println!("第 {round} 轮: 子进程最大 ID {max_seen}, 重启后的 ID {next}");
+        assert!(next > max_seen);
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
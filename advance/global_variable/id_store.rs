@@ -0,0 +1,211 @@
+/// ID 生成器持久化
+///
+/// `GLOBAL_ID_COUNTER` 每次启动都从 0 开始, 重启后 Factory 的 ID 会重复
+///
+/// 每生成一个 ID 就写一次文件太慢, 所以按块预留 (block reservation):
+/// 1. 先把 "已预留的最大 ID" (高水位) 持久化
+/// 2. 再在内存中逐个发放这一块中的 ID
+///
+/// 进程崩溃时, 这一块中没发完的 ID 会被跳过, 但永远不会重复
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 存储后端, 只需要保存和读取高水位
+pub trait IdStore {
+    /// 读取高水位, 从未保存过时返回 0
+    fn load(&self) -> Result<usize, AppError>;
+    /// 保存高水位, 返回 Ok 时必须已经持久化
+    fn store(&self, high_water: usize) -> Result<(), AppError>;
+}
+
+fn io_error(action: &str, path: &Path, err: std::io::Error) -> AppError {
+    AppError::new("io", 500, format!("{action} {}: {err}", path.display()))
+}
+
+/// 文件格式的前缀, 用于识别文件是否被破坏
+const FILE_HEADER: &str = "idgen-v1";
+
+/// 模拟崩溃的位置, 用于测试崩溃恢复
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrashPoint {
+    /// 临时文件写了一半
+    MidTempWrite,
+    /// 临时文件已写完并 fsync, 但还没有 rename
+    BeforeRename,
+}
+
+/// # FileStore 基于本地文件的存储
+///
+/// 写入过程:
+/// 1. 写入同目录下的临时文件并 fsync
+/// 2. rename 覆盖正式文件 (同一文件系统上 rename 是原子的)
+/// 3. fsync 所在目录, 保证 rename 本身也落盘
+///
+/// 任何一步崩溃, 正式文件要么是旧值, 要么是新值, 不会是写了一半的内容
+pub struct FileStore {
+    path: PathBuf,
+    crash_at: Option<CrashPoint>,
+}
+
+#[allow(unused)]
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> FileStore {
+        FileStore {
+            path: path.into(),
+            crash_at: None,
+        }
+    }
+
+    /// 之后每一次 store 都会在 point 处 "崩溃": 留下中间状态并返回错误; 模拟重启时重新创建一个不崩溃的 FileStore
+    pub fn crash_at(mut self, point: CrashPoint) -> FileStore {
+        self.crash_at = Some(point);
+        self
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".tmp");
+        self.path.with_file_name(name)
+    }
+}
+
+impl IdStore for FileStore {
+    fn load(&self) -> Result<usize, AppError> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(io_error("read", &self.path, err)),
+        };
+        content
+            .trim_end()
+            .strip_prefix(FILE_HEADER)
+            .and_then(|rest| rest.trim().parse().ok())
+            .ok_or_else(|| {
+                AppError::new(
+                    "corrupted",
+                    500,
+                    format!("{} is not a valid id file", self.path.display()),
+                )
+            })
+    }
+
+    fn store(&self, high_water: usize) -> Result<(), AppError> {
+        let temp = self.temp_path();
+        let content = format!("{FILE_HEADER} {high_water}\n");
+        let mut file = File::create(&temp).map_err(|e| io_error("create", &temp, e))?;
+        if self.crash_at == Some(CrashPoint::MidTempWrite) {
+            let _ = file.write_all(&content.as_bytes()[..content.len() / 2]);
+            return Err(AppError::new(
+                "crash",
+                500,
+                "crashed while writing temp file",
+            ));
+        }
+        file.write_all(content.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|e| io_error("write", &temp, e))?;
+        if self.crash_at == Some(CrashPoint::BeforeRename) {
+            return Err(AppError::new("crash", 500, "crashed before rename"));
+        }
+        fs::rename(&temp, &self.path).map_err(|e| io_error("rename", &self.path, e))?;
+        sync_parent_dir(&self.path)
+    }
+}
+
+/// rename 修改的是目录项, 需要 fsync 目录才能保证掉电后依然可见
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<(), AppError> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)
+        .and_then(|d| d.sync_all())
+        .map_err(|e| io_error("sync", dir, e))
+}
+
+/// Windows 上无法打开目录进行 fsync, rename 由文件系统保证
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<(), AppError> {
+    Ok(())
+}
+
+/// # MemoryStore 内存存储, 用于测试
+#[derive(Default)]
+pub struct MemoryStore {
+    high_water: Mutex<usize>,
+}
+
+impl IdStore for MemoryStore {
+    fn load(&self) -> Result<usize, AppError> {
+        Ok(*self.high_water.lock().unwrap())
+    }
+
+    fn store(&self, high_water: usize) -> Result<(), AppError> {
+        *self.high_water.lock().unwrap() = high_water;
+        Ok(())
+    }
+}
+
+/// 存储的引用也可以作为存储, 方便多个计数器 (模拟多次启动) 共享同一个存储
+impl<S: IdStore> IdStore for &S {
+    fn load(&self) -> Result<usize, AppError> {
+        (**self).load()
+    }
+
+    fn store(&self, high_water: usize) -> Result<(), AppError> {
+        (**self).store(high_water)
+    }
+}
+
+/// # PersistentCounter 可持久化的计数器
+pub struct PersistentCounter<S: IdStore> {
+    store: S,
+    block_size: usize,
+    // (最近发放的 ID, 已预留的最大 ID)
+    state: Mutex<(usize, usize)>,
+}
+
+#[allow(unused)]
+impl<S: IdStore> PersistentCounter<S> {
+    /// 从存储中恢复计数器, 之后发放的 ID 都大于上次预留的最大 ID
+    pub fn open(store: S, block_size: usize) -> Result<Self, AppError> {
+        let high_water = store.load()?;
+        Ok(PersistentCounter {
+            store,
+            block_size: block_size.max(1),
+            state: Mutex::new((high_water, high_water)),
+        })
+    }
+
+    /// 生成下一个 ID, 当前块用完时先持久化下一块再发放
+    pub fn next(&self) -> Result<usize, AppError> {
+        let mut state = self.state.lock().unwrap();
+        let (last, reserved) = *state;
+        let next = last
+            .checked_add(1)
+            .ok_or_else(|| AppError::new("id", 503, "Factory ids overflowed"))?;
+        if next > reserved {
+            let new_reserved = reserved.saturating_add(self.block_size);
+            // 持久化失败时不发放 ID, 内存状态保持不变
+            self.store.store(new_reserved)?;
+            state.1 = new_reserved;
+        }
+        state.0 = next;
+        Ok(next)
+    }
+
+    /// 已持久化的高水位
+    pub fn reserved(&self) -> usize {
+        self.state.lock().unwrap().1
+    }
+}
@@ -0,0 +1,252 @@
+/// ID 生成器并发压力测试
+///
+/// example01 最初的 `generate_id` 先 fetch_add 再 load 读取新 ID,
+/// 两个线程都 fetch_add 之后再 load, 会读到同一个值, 于是拿到重复的 ID
+///
+/// 这里用两种方式验证:
+/// 1. 模型检查: 穷举两个线程所有可能的交错执行顺序 (和 loom 的思路一样, 只是状态很小, 手写即可)
+/// 2. 压力测试: 多个线程同时生成大量 ID, 检查是否有重复
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod id_generator;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 最初的实现: 检查 / 自增 / 读取分成了三步
+fn racy_generate_id(counter: &AtomicUsize) -> usize {
+    let current_val = counter.load(Ordering::Relaxed);
+    if current_val > usize::MAX / 2 {
+        panic!("Factory ids overflowed");
+    }
+    counter.fetch_add(1, Ordering::Relaxed);
+    counter.load(Ordering::Relaxed)
+}
+
+/// 模型中线程的一步原子操作
+#[derive(Clone, Copy)]
+enum Op {
+    /// 读取计数器到寄存器
+    Load,
+    /// 计数器 + 1
+    FetchAdd,
+    /// 读取计数器作为 ID
+    LoadId,
+    /// 寄存器中的值没变时写入 寄存器 + 1, 并以它作为 ID; 否则回到第 0 步重试
+    CasId,
+}
+
+/// 最初的实现: load (溢出检查) / fetch_add / load
+const RACY: &[Op] = &[Op::Load, Op::FetchAdd, Op::LoadId];
+/// fetch_update 的实现: load / compare_exchange, 失败时重试
+const FIXED: &[Op] = &[Op::Load, Op::CasId];
+
+#[derive(Clone)]
+struct Thread {
+    pc: usize,
+    reg: usize,
+    id: Option<usize>,
+}
+
+/// 模型检查的结果
+#[derive(Debug, Default)]
+struct ModelReport {
+    /// 所有可能的交错执行顺序数
+    interleavings: usize,
+    /// 出现重复 ID 的执行顺序数
+    duplicated: usize,
+    /// 一个出现重复 ID 的执行顺序 (线程编号)
+    counterexample: Option<Vec<usize>>,
+}
+
+/// 穷举 threads 个线程各执行一次 program 的所有交错顺序
+fn check_model(program: &[Op], threads: usize) -> ModelReport {
+    fn explore(
+        program: &[Op],
+        counter: usize,
+        threads: &mut Vec<Thread>,
+        trace: &mut Vec<usize>,
+        report: &mut ModelReport,
+    ) {
+        let runnable: Vec<usize> = (0..threads.len())
+            .filter(|&t| threads[t].id.is_none())
+            .collect();
+        if runnable.is_empty() {
+            report.interleavings += 1;
+            let ids: HashSet<usize> = threads.iter().filter_map(|t| t.id).collect();
+            if ids.len() != threads.len() {
+                report.duplicated += 1;
+                report.counterexample.get_or_insert_with(|| trace.clone());
+            }
+            return;
+        }
+        for t in runnable {
+            let saved = threads[t].clone();
+            let mut next_counter = counter;
+            let thread = &mut threads[t];
+            thread.pc = match program[thread.pc] {
+                Op::Load => {
+                    thread.reg = counter;
+                    thread.pc + 1
+                }
+                Op::FetchAdd => {
+                    next_counter += 1;
+                    thread.pc + 1
+                }
+                Op::LoadId => {
+                    thread.id = Some(counter);
+                    thread.pc + 1
+                }
+                Op::CasId if counter == thread.reg => {
+                    next_counter += 1;
+                    thread.id = Some(next_counter);
+                    thread.pc + 1
+                }
+                // compare_exchange 失败, 回到第 0 步重新读取
+                Op::CasId => 0,
+            };
+            trace.push(t);
+            explore(program, next_counter, threads, trace, report);
+            trace.pop();
+            threads[t] = saved;
+        }
+    }
+
+    let mut report = ModelReport::default();
+    let mut state = vec![
+        Thread {
+            pc: 0,
+            reg: 0,
+            id: None,
+        };
+        threads
+    ];
+    explore(program, 0, &mut state, &mut Vec::new(), &mut report);
+    report
+}
+
+/// 压力测试的结果
+#[derive(Debug)]
+struct StressReport {
+    total: usize,
+    duplicates: usize,
+}
+
+/// threads 个线程同时起跑, 每个线程调用 per_thread 次 generate
+fn stress<T>(threads: usize, per_thread: usize, generate: impl Fn() -> T + Sync) -> StressReport
+where
+    T: std::hash::Hash + Eq + Send,
+{
+    let barrier = Barrier::new(threads);
+    let ids: Vec<Vec<T>> = std::thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    barrier.wait();
+                    (0..per_thread).map(|_| generate()).collect::<Vec<T>>()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    let total = ids.iter().map(Vec::len).sum();
+    let unique: HashSet<T> = ids.into_iter().flatten().collect();
+    StressReport {
+        total,
+        duplicates: total - unique.len(),
+    }
+}
+
+/// # example01 模型检查最初的实现
+/// 两个线程一共 20 种交错顺序, 其中一部分会生成重复的 ID
+#[allow(unused)]
+fn example01() {
+    let report = check_model(RACY, 2);
+    println!("racy: {report:?}");
+    assert_eq!(report.interleavings, 20);
+    assert!(report.duplicated > 0);
+    // 例如: 线程 0 和线程 1 都先 fetch_add, 再各自 load, 都读到 2
+    assert!(report.counterexample.is_some());
+
+    let report = check_model(RACY, 3);
+    assert!(report.duplicated > 0);
+}
+
+/// # example02 模型检查 fetch_update 的实现
+/// 无论怎么交错, 每个线程拿到的 ID 都不一样
+#[allow(unused)]
+fn example02() {
+    for threads in 2..=3 {
+        let report = check_model(FIXED, threads);
+        println!("fixed x{threads}: {report:?}");
+        assert!(report.interleavings > 0);
+        assert_eq!(report.duplicated, 0);
+    }
+}
+
+/// # example03 压力测试最初的实现
+/// 是否真的出现重复取决于线程调度, 多跑几轮通常就能看到
+#[allow(unused)]
+fn example03() {
+    for round in 1..=20 {
+        let counter = AtomicUsize::new(0);
+        let report = stress(8, 20_000, || racy_generate_id(&counter));
+        if report.duplicates > 0 {
+            println!(
+                "racy: 第 {round} 轮出现了 {} 个重复 ID, {report:?}",
+                report.duplicates
+            );
+            return;
+        }
+    }
+    println!("racy: 20 轮都没有出现重复 ID, 可能是机器核数太少");
+}
+
+/// # example04 压力测试 Counter
+#[allow(unused)]
+fn example04() {
+    let counter = id_generator::Counter::new(usize::MAX / 2);
+    let report = stress(8, 50_000, || counter.next().unwrap());
+    println!("counter: {report:?}");
+    assert_eq!(report.total, 400_000);
+    assert_eq!(report.duplicates, 0);
+    assert_eq!(counter.current(), 400_000);
+
+    // 接近上限时, 并发生成也不会越过上限, 失败的线程拿到错误
+    let counter = id_generator::Counter::starting_at(1_000 - 100, 1_000);
+    let report = stress(8, 50, || counter.next().ok());
+    assert_eq!(counter.current(), 1_000);
+    // 100 个成功的 ID 互不相同, 剩下 300 次都是 None
+    assert_eq!(report.duplicates, 300 - 1);
+}
+
+/// # example05 压力测试 Snowflake
+#[allow(unused)]
+fn example05() {
+    let generator = id_generator::Snowflake::new(3).unwrap();
+    // 每毫秒最多 4096 个 ID, 多线程生成时可能借用未来的时间戳, 失败时重试
+    let report = stress(8, 10_000, || loop {
+        if let Ok(id) = generator.next_id() {
+            break id;
+        }
+        std::thread::yield_now();
+    });
+    println!("snowflake: {report:?}");
+    assert_eq!(report.duplicates, 0);
+}
@@ -0,0 +1,423 @@
+/// 内存顺序模型检查
+///
+/// 和 id_stress.rs 一样穷举所有的执行, 但这里不只是穷举线程交错:
+/// 弱内存顺序下, 一次 load 不一定读到最新的 store, 而是可以读到任何一个 "还没被它看见的覆盖" 的 store
+///
+/// 模型 (与 loom 的思路相同, 只保留 litmus test 需要的部分):
+/// - 每个地址按修改顺序保存所有 store 的历史
+/// - 每个线程维护一个向量时钟, 记录它 "已经知道" 其他线程的哪些操作 (happens-before)
+/// - load 可以读取历史中的任何一个 store, 只要没有更新的 store 已经 happens-before 这次 load,
+///   且不早于本线程在这个地址上读写过的 store (同一地址上的读写一致性)
+/// - Release store 带上当前线程的时钟, Acquire load 读到它时合并这个时钟, 于是建立 happens-before
+/// - SeqCst 操作按执行的先后组成一个全序, SeqCst load 不能读到比最近一次 SeqCst store 更旧的值
+/// - 读改写 (RMW) 总是读取最新的 store, 并延续被读取 store 的 release 序列
+///
+/// 不包含 fence, consume 以及 SeqCst 与非 SeqCst 操作混用时的细节
+#[allow(unused)]
+struct Description;
+
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
+
+/// 线程中的一条指令
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    /// 写入常量
+    Store {
+        loc: usize,
+        value: u64,
+        ordering: Ordering,
+    },
+    /// 读取到寄存器
+    Load {
+        loc: usize,
+        reg: usize,
+        ordering: Ordering,
+    },
+    /// 写入 寄存器 + add
+    StoreReg {
+        loc: usize,
+        reg: usize,
+        add: u64,
+        ordering: Ordering,
+    },
+    /// 等待 loc 的最新值为 expected, 然后用一次 RMW 写入 new
+    /// 相当于自旋锁的 `while compare_exchange(expected, new).is_err() {}`,
+    /// 模型中条件不满足时线程暂停, 不需要真的自旋
+    CasWait {
+        loc: usize,
+        expected: u64,
+        new: u64,
+        ordering: Ordering,
+    },
+}
+
+/// # Litmus 一个 litmus test
+#[derive(Debug, Clone)]
+pub struct Litmus {
+    pub name: &'static str,
+    /// 地址的数量, 初始值都为 0
+    pub locations: usize,
+    /// 每个线程的指令
+    pub threads: Vec<Vec<Op>>,
+}
+
+/// 一次执行的结果
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Outcome {
+    /// 每个线程的寄存器
+    pub registers: Vec<Vec<u64>>,
+    /// 每个地址最终的值 (修改顺序中的最后一个)
+    pub memory: Vec<u64>,
+}
+
+fn is_release(ordering: Ordering) -> bool {
+    matches!(
+        ordering,
+        Ordering::Release | Ordering::AcqRel | Ordering::SeqCst
+    )
+}
+
+fn is_acquire(ordering: Ordering) -> bool {
+    matches!(
+        ordering,
+        Ordering::Acquire | Ordering::AcqRel | Ordering::SeqCst
+    )
+}
+
+/// 向量时钟: 第 i 项是已知的线程 i 执行到的步数
+type Clock = Vec<u32>;
+
+fn join(clock: &mut Clock, other: &Clock) {
+    for (a, b) in clock.iter_mut().zip(other) {
+        *a = (*a).max(*b);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StoreEvent {
+    value: u64,
+    /// 执行 store 的线程和它在该线程中的步数, 初始值为 None
+    origin: Option<(usize, u32)>,
+    /// Acquire 读到这个 store 时获得的时钟
+    release: Clock,
+}
+
+#[derive(Debug, Clone)]
+struct Location {
+    history: Vec<StoreEvent>,
+    /// 最近一次 SeqCst store 在 history 中的位置
+    last_seq_cst: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ThreadState {
+    pc: usize,
+    registers: Vec<u64>,
+    clock: Clock,
+    /// 每个地址上本线程读写过的最新位置
+    seen: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    locations: Vec<Location>,
+    threads: Vec<ThreadState>,
+}
+
+impl State {
+    fn new(litmus: &Litmus) -> State {
+        let n = litmus.threads.len();
+        let init = Location {
+            history: vec![StoreEvent {
+                value: 0,
+                origin: None,
+                release: vec![0; n],
+            }],
+            last_seq_cst: 0,
+        };
+        let registers = |ops: &Vec<Op>| {
+            ops.iter()
+                .filter_map(|op| match op {
+                    Op::Load { reg, .. } => Some(reg + 1),
+                    _ => None,
+                })
+                .max()
+                .unwrap_or(0)
+        };
+        State {
+            locations: vec![init; litmus.locations],
+            threads: litmus
+                .threads
+                .iter()
+                .map(|ops| ThreadState {
+                    pc: 0,
+                    registers: vec![0; registers(ops)],
+                    clock: vec![0; n],
+                    seen: vec![0; litmus.locations],
+                })
+                .collect(),
+        }
+    }
+
+    /// store 是否已经 happens-before 线程 t 的当前位置
+    fn happens_before(&self, store: &StoreEvent, t: usize) -> bool {
+        match store.origin {
+            None => true,
+            Some((thread, step)) => step <= self.threads[t].clock[thread],
+        }
+    }
+
+    /// 线程 t 以 ordering 读取 loc 时可以读到的 store
+    fn readable(&self, t: usize, loc: usize, ordering: Ordering) -> std::ops::Range<usize> {
+        let location = &self.locations[loc];
+        let mut oldest = self.threads[t].seen[loc];
+        if let Some(newest_known) = location
+            .history
+            .iter()
+            .rposition(|store| self.happens_before(store, t))
+        {
+            oldest = oldest.max(newest_known);
+        }
+        if ordering == Ordering::SeqCst {
+            oldest = oldest.max(location.last_seq_cst);
+        }
+        oldest..location.history.len()
+    }
+
+    fn read(&mut self, t: usize, loc: usize, index: usize, ordering: Ordering) -> u64 {
+        let store = self.locations[loc].history[index].clone();
+        let thread = &mut self.threads[t];
+        thread.seen[loc] = thread.seen[loc].max(index);
+        if is_acquire(ordering) {
+            join(&mut thread.clock, &store.release);
+        }
+        store.value
+    }
+
+    /// rmw_from 为 RMW 读取的 store, 新的 store 延续它的 release 序列
+    fn write(
+        &mut self,
+        t: usize,
+        loc: usize,
+        value: u64,
+        ordering: Ordering,
+        rmw_from: Option<usize>,
+    ) {
+        let n = self.threads.len();
+        let thread = &self.threads[t];
+        let mut release = if is_release(ordering) {
+            thread.clock.clone()
+        } else {
+            vec![0; n]
+        };
+        if let Some(index) = rmw_from {
+            join(&mut release, &self.locations[loc].history[index].release);
+        }
+        let origin = Some((t, thread.clock[t]));
+        let location = &mut self.locations[loc];
+        location.history.push(StoreEvent {
+            value,
+            origin,
+            release,
+        });
+        let index = location.history.len() - 1;
+        if ordering == Ordering::SeqCst {
+            location.last_seq_cst = index;
+        }
+        self.threads[t].seen[loc] = index;
+    }
+
+    fn outcome(&self) -> Outcome {
+        Outcome {
+            registers: self.threads.iter().map(|t| t.registers.clone()).collect(),
+            memory: self
+                .locations
+                .iter()
+                .map(|loc| loc.history.last().unwrap().value)
+                .collect(),
+        }
+    }
+}
+
+/// 穷举所有的执行, 返回每种结果出现在多少种执行中
+pub fn explore(litmus: &Litmus) -> BTreeMap<Outcome, usize> {
+    fn step(litmus: &Litmus, state: State, outcomes: &mut BTreeMap<Outcome, usize>) {
+        let mut finished = true;
+        for t in 0..litmus.threads.len() {
+            let Some(op) = litmus.threads[t].get(state.threads[t].pc).copied() else {
+                continue;
+            };
+            finished = false;
+            // 每一步先推进本线程的时钟
+            let mut next = state.clone();
+            next.threads[t].pc += 1;
+            next.threads[t].clock[t] += 1;
+            match op {
+                Op::Store {
+                    loc,
+                    value,
+                    ordering,
+                } => {
+                    next.write(t, loc, value, ordering, None);
+                    step(litmus, next, outcomes);
+                }
+                Op::StoreReg {
+                    loc,
+                    reg,
+                    add,
+                    ordering,
+                } => {
+                    let value = next.threads[t].registers[reg] + add;
+                    next.write(t, loc, value, ordering, None);
+                    step(litmus, next, outcomes);
+                }
+                Op::Load { loc, reg, ordering } => {
+                    // 每一个可以读到的 store 都是一种执行
+                    for index in next.readable(t, loc, ordering) {
+                        let mut branch = next.clone();
+                        let value = branch.read(t, loc, index, ordering);
+                        branch.threads[t].registers[reg] = value;
+                        step(litmus, branch, outcomes);
+                    }
+                }
+                Op::CasWait {
+                    loc,
+                    expected,
+                    new,
+                    ordering,
+                } => {
+                    let latest = next.locations[loc].history.len() - 1;
+                    if next.locations[loc].history[latest].value == expected {
+                        next.read(t, loc, latest, ordering);
+                        next.write(t, loc, new, ordering, Some(latest));
+                        step(litmus, next, outcomes);
+                    }
+                }
+            }
+        }
+        if finished {
+            *outcomes.entry(state.outcome()).or_default() += 1;
+        }
+    }
+
+    let mut outcomes = BTreeMap::new();
+    step(litmus, State::new(litmus), &mut outcomes);
+    outcomes
+}
+
+/// # message_passing 消息传递
+/// ```text
+/// 线程 0                      线程 1
+/// data.store(42, Relaxed)     r0 = flag.load(load)
+/// flag.store(1, store)        r1 = data.load(Relaxed)
+/// ```
+/// 看到 flag == 1 时是否一定能看到 data == 42
+pub fn message_passing(store: Ordering, load: Ordering) -> Litmus {
+    const DATA: usize = 0;
+    const FLAG: usize = 1;
+    Litmus {
+        name: "message passing",
+        locations: 2,
+        threads: vec![
+            vec![
+                Op::Store {
+                    loc: DATA,
+                    value: 42,
+                    ordering: Ordering::Relaxed,
+                },
+                Op::Store {
+                    loc: FLAG,
+                    value: 1,
+                    ordering: store,
+                },
+            ],
+            vec![
+                Op::Load {
+                    loc: FLAG,
+                    reg: 0,
+                    ordering: load,
+                },
+                Op::Load {
+                    loc: DATA,
+                    reg: 1,
+                    ordering: Ordering::Relaxed,
+                },
+            ],
+        ],
+    }
+}
+
+/// # store_buffering 存储缓冲
+/// ```text
+/// 线程 0                  线程 1
+/// x.store(1, store)       y.store(1, store)
+/// r0 = y.load(load)       r0 = x.load(load)
+/// ```
+/// 两个线程是否可能都读到 0
+pub fn store_buffering(store: Ordering, load: Ordering) -> Litmus {
+    const X: usize = 0;
+    const Y: usize = 1;
+    let thread = |mine: usize, other: usize| {
+        vec![
+            Op::Store {
+                loc: mine,
+                value: 1,
+                ordering: store,
+            },
+            Op::Load {
+                loc: other,
+                reg: 0,
+                ordering: load,
+            },
+        ]
+    };
+    Litmus {
+        name: "store buffering",
+        locations: 2,
+        threads: vec![thread(X, Y), thread(Y, X)],
+    }
+}
+
+/// # spinlock 自旋锁
+/// ```text
+/// 两个线程都执行:
+/// while lock.compare_exchange(0, 1, lock_ordering, Relaxed).is_err() {}
+/// r0 = data.load(Relaxed)
+/// data.store(r0 + 1, Relaxed)
+/// lock.store(0, unlock_ordering)
+/// ```
+/// data 最终是否一定为 2
+pub fn spinlock(lock_ordering: Ordering, unlock_ordering: Ordering) -> Litmus {
+    const LOCK: usize = 0;
+    const DATA: usize = 1;
+    let thread = vec![
+        Op::CasWait {
+            loc: LOCK,
+            expected: 0,
+            new: 1,
+            ordering: lock_ordering,
+        },
+        Op::Load {
+            loc: DATA,
+            reg: 0,
+            ordering: Ordering::Relaxed,
+        },
+        Op::StoreReg {
+            loc: DATA,
+            reg: 0,
+            add: 1,
+            ordering: Ordering::Relaxed,
+        },
+        Op::Store {
+            loc: LOCK,
+            value: 0,
+            ordering: unlock_ordering,
+        },
+    ];
+    Litmus {
+        name: "spinlock",
+        locations: 2,
+        threads: vec![thread.clone(), thread],
+    }
+}
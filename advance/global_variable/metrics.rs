@@ -0,0 +1,635 @@
+/// 进程级的指标注册表
+///
+/// global_variable.rs 中的 `REQUEST_RECV_NEW` 只能统计一个数字,
+/// 实际的服务需要按名字和标签区分多种指标:
+/// - `Counter`: 只增不减的计数, 比如请求数
+/// - `Gauge`: 可增可减的当前值, 比如连接数
+/// - `Histogram`: 按区间统计的分布, 比如请求耗时
+///
+/// 注册时需要锁住注册表, 之后调用方持有 `Arc<Counter>` 等句柄, 更新时只有原子操作, 不需要加锁
+///
+/// 导出格式: Prometheus 文本格式和 JSON, `MetricsServer` 通过 HTTP 提供 `/metrics`
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// # Counter 只增不减的计数
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+#[allow(unused)]
+impl Counter {
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// 以 f64 的位模式保存在 AtomicU64 中
+#[derive(Debug, Default)]
+struct AtomicF64 {
+    bits: AtomicU64,
+}
+
+impl AtomicF64 {
+    fn load(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+
+    fn store(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    fn add(&self, delta: f64) {
+        // 没有浮点数的 fetch_add, 使用 CAS 循环
+        let _ = self
+            .bits
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + delta).to_bits())
+            });
+    }
+}
+
+/// # Gauge 可增可减的当前值
+#[derive(Debug, Default)]
+pub struct Gauge {
+    value: AtomicF64,
+}
+
+#[allow(unused)]
+impl Gauge {
+    pub fn set(&self, value: f64) {
+        self.value.store(value);
+    }
+
+    pub fn add(&self, delta: f64) {
+        self.value.add(delta);
+    }
+
+    pub fn inc(&self) {
+        self.add(1.0);
+    }
+
+    pub fn dec(&self) {
+        self.add(-1.0);
+    }
+
+    pub fn get(&self) -> f64 {
+        self.value.load()
+    }
+}
+
+/// 默认的区间上限, 单位秒, 与 Prometheus 客户端库一致
+#[allow(unused)]
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// # Histogram 按区间统计的分布
+///
+/// 每个区间只记录落在其中的次数, 导出时再累加为 Prometheus 要求的 "小于等于上限的次数"
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Box<[f64]>,
+    // 比 bounds 多一个, 最后一个对应 +Inf
+    counts: Box<[AtomicU64]>,
+    sum: AtomicF64,
+}
+
+/// 直方图某一时刻的数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    /// (上限, 小于等于上限的次数), 最后一项的上限是 +Inf
+    pub buckets: Vec<(f64, u64)>,
+    pub sum: f64,
+    pub count: u64,
+}
+
+#[allow(unused)]
+impl Histogram {
+    fn new(bounds: &[f64]) -> Histogram {
+        Histogram {
+            bounds: bounds.into(),
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicF64::default(),
+        }
+    }
+
+    /// NaN 和 ±Inf 会被忽略: NaN 不属于任何区间, 而且一旦加入 sum, sum 就永远是 NaN
+    pub fn observe(&self, value: f64) {
+        if !value.is_finite() {
+            return;
+        }
+        let index = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[index].fetch_add(1, Ordering::Relaxed);
+        self.sum.add(value);
+    }
+
+    /// 分别读取每个原子变量, 并发更新时各项之间可能有细微出入
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+        let buckets = self
+            .bounds
+            .iter()
+            .chain([&f64::INFINITY])
+            .zip(self.counts.iter())
+            .map(|(bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+        HistogramSnapshot {
+            buckets,
+            sum: self.sum.load(),
+            count: cumulative,
+        }
+    }
+}
+
+/// 一个指标及其类型
+#[derive(Debug, Clone)]
+enum Metric {
+    Counter(Arc<Counter>),
+    Gauge(Arc<Gauge>),
+    Histogram(Arc<Histogram>),
+}
+
+impl Metric {
+    fn type_name(&self) -> &'static str {
+        match self {
+            Metric::Counter(_) => "counter",
+            Metric::Gauge(_) => "gauge",
+            Metric::Histogram(_) => "histogram",
+        }
+    }
+}
+
+/// 排好序的标签, 同一组标签无论传入顺序如何都对应同一个序列
+type Labels = Vec<(String, String)>;
+
+/// 同名的一组指标
+#[derive(Debug)]
+struct Family {
+    help: String,
+    kind: &'static str,
+    series: BTreeMap<Labels, Metric>,
+}
+
+/// # MetricsRegistry 指标注册表
+#[derive(Debug, Default)]
+pub struct MetricsRegistry {
+    families: RwLock<BTreeMap<String, Family>>,
+}
+
+/// 进程级的全局注册表
+pub fn global() -> &'static MetricsRegistry {
+    static GLOBAL: LazyLock<MetricsRegistry> = LazyLock::new(MetricsRegistry::new);
+    &GLOBAL
+}
+
+fn metrics_error(code: usize, message: String) -> AppError {
+    AppError::new("metrics", code, message)
+}
+
+/// 指标名: `[a-zA-Z_:][a-zA-Z0-9_:]*`
+fn valid_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// 标签名: `[a-zA-Z_][a-zA-Z0-9_]*`, `__` 开头的名字是保留的
+fn valid_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    !name.starts_with("__")
+        && chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[allow(unused)]
+impl MetricsRegistry {
+    pub fn new() -> MetricsRegistry {
+        MetricsRegistry::default()
+    }
+
+    /// 获取或注册计数器
+    pub fn counter(
+        &self,
+        name: &str,
+        help: &str,
+        labels: &[(&str, &str)],
+    ) -> Result<Arc<Counter>, AppError> {
+        let metric = self.register(name, help, "counter", labels, || {
+            Metric::Counter(Arc::default())
+        })?;
+        match metric {
+            Metric::Counter(counter) => Ok(counter),
+            _ => unreachable!("kind is checked in register"),
+        }
+    }
+
+    /// 获取或注册 Gauge
+    pub fn gauge(
+        &self,
+        name: &str,
+        help: &str,
+        labels: &[(&str, &str)],
+    ) -> Result<Arc<Gauge>, AppError> {
+        let metric = self.register(
+            name,
+            help,
+            "gauge",
+            labels,
+            || Metric::Gauge(Arc::default()),
+        )?;
+        match metric {
+            Metric::Gauge(gauge) => Ok(gauge),
+            _ => unreachable!("kind is checked in register"),
+        }
+    }
+
+    /// 获取或注册直方图, buckets 为各区间的上限, 必须严格递增
+    /// 已经注册过时沿用第一次注册的区间
+    pub fn histogram(
+        &self,
+        name: &str,
+        help: &str,
+        buckets: &[f64],
+        labels: &[(&str, &str)],
+    ) -> Result<Arc<Histogram>, AppError> {
+        let increasing = buckets.windows(2).all(|w| w[0] < w[1]);
+        if buckets.is_empty() || !increasing || buckets.iter().any(|b| b.is_nan()) {
+            return Err(metrics_error(
+                400,
+                format!("buckets of `{name}` must be non-empty and strictly increasing"),
+            ));
+        }
+        if labels.iter().any(|(label, _)| *label == "le") {
+            return Err(metrics_error(
+                400,
+                format!("label `le` is reserved for histogram `{name}`"),
+            ));
+        }
+        let metric = self.register(name, help, "histogram", labels, || {
+            Metric::Histogram(Arc::new(Histogram::new(buckets)))
+        })?;
+        match metric {
+            Metric::Histogram(histogram) => Ok(histogram),
+            _ => unreachable!("kind is checked in register"),
+        }
+    }
+
+    fn register(
+        &self,
+        name: &str,
+        help: &str,
+        kind: &'static str,
+        labels: &[(&str, &str)],
+        create: impl FnOnce() -> Metric,
+    ) -> Result<Metric, AppError> {
+        if !valid_metric_name(name) {
+            return Err(metrics_error(400, format!("invalid metric name `{name}`")));
+        }
+        let mut sorted: Labels = Vec::with_capacity(labels.len());
+        for (label, value) in labels {
+            if !valid_label_name(label) {
+                return Err(metrics_error(
+                    400,
+                    format!("invalid label name `{label}` for `{name}`"),
+                ));
+            }
+            sorted.push((label.to_string(), value.to_string()));
+        }
+        sorted.sort();
+        if sorted.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(metrics_error(
+                400,
+                format!("duplicate label names for `{name}`"),
+            ));
+        }
+
+        // 已注册的序列只需要读锁
+        if let Some(family) = self.families.read().unwrap().get(name) {
+            if let Some(metric) = family.series.get(&sorted) {
+                if metric.type_name() != kind {
+                    return Err(conflict(name, family.kind, kind));
+                }
+                return Ok(metric.clone());
+            }
+        }
+
+        let mut families = self.families.write().unwrap();
+        let family = families.entry(name.to_string()).or_insert_with(|| Family {
+            help: help.to_string(),
+            kind,
+            series: BTreeMap::new(),
+        });
+        if family.kind != kind {
+            return Err(conflict(name, family.kind, kind));
+        }
+        Ok(family.series.entry(sorted).or_insert_with(create).clone())
+    }
+
+    /// Prometheus 文本格式
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        for (name, family) in self.families.read().unwrap().iter() {
+            let help = family.help.replace('\\', "\\\\").replace('\n', "\\n");
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {}", family.kind);
+            for (labels, metric) in &family.series {
+                match metric {
+                    Metric::Counter(counter) => {
+                        let _ =
+                            writeln!(out, "{name}{} {}", prom_labels(labels, None), counter.get());
+                    }
+                    Metric::Gauge(gauge) => {
+                        let _ = writeln!(
+                            out,
+                            "{name}{} {}",
+                            prom_labels(labels, None),
+                            prom_float(gauge.get())
+                        );
+                    }
+                    Metric::Histogram(histogram) => {
+                        let snapshot = histogram.snapshot();
+                        for (bound, count) in &snapshot.buckets {
+                            let le = prom_float(*bound);
+                            let labels = prom_labels(labels, Some(&le));
+                            let _ = writeln!(out, "{name}_bucket{labels} {count}");
+                        }
+                        let labels = prom_labels(labels, None);
+                        let _ = writeln!(out, "{name}_sum{labels} {}", prom_float(snapshot.sum));
+                        let _ = writeln!(out, "{name}_count{labels} {}", snapshot.count);
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// JSON 格式, 按指标名组织:
+    /// `{"name": {"type": "counter", "help": "..", "series": [{"labels": {..}, "value": 1}]}}`
+    pub fn render_json(&self) -> String {
+        let families = self.families.read().unwrap();
+        let rendered: Vec<String> = families
+            .iter()
+            .map(|(name, family)| {
+                let series: Vec<String> = family
+                    .series
+                    .iter()
+                    .map(|(labels, metric)| {
+                        let labels: Vec<String> = labels
+                            .iter()
+                            .map(|(k, v)| format!("{}:{}", json_string(k), json_string(v)))
+                            .collect();
+                        let labels = format!("{{{}}}", labels.join(","));
+                        match metric {
+                            Metric::Counter(counter) => {
+                                format!("{{\"labels\":{labels},\"value\":{}}}", counter.get())
+                            }
+                            Metric::Gauge(gauge) => format!(
+                                "{{\"labels\":{labels},\"value\":{}}}",
+                                json_float(gauge.get())
+                            ),
+                            Metric::Histogram(histogram) => {
+                                let snapshot = histogram.snapshot();
+                                let buckets: Vec<String> = snapshot
+                                    .buckets
+                                    .iter()
+                                    .map(|(bound, count)| {
+                                        // JSON 中没有无穷大, 用字符串表示
+                                        let le = if bound.is_finite() {
+                                            json_float(*bound)
+                                        } else {
+                                            json_string("+Inf")
+                                        };
+                                        format!("{{\"le\":{le},\"count\":{count}}}")
+                                    })
+                                    .collect();
+                                format!(
+                                    "{{\"labels\":{labels},\"buckets\":[{}],\"sum\":{},\"count\":{}}}",
+                                    buckets.join(","),
+                                    json_float(snapshot.sum),
+                                    snapshot.count
+                                )
+                            }
+                        }
+                    })
+                    .collect();
+                format!(
+                    "{}:{{\"type\":{},\"help\":{},\"series\":[{}]}}",
+                    json_string(name),
+                    json_string(family.kind),
+                    json_string(&family.help),
+                    series.join(",")
+                )
+            })
+            .collect();
+        format!("{{{}}}", rendered.join(","))
+    }
+}
+
+fn conflict(name: &str, registered: &str, requested: &str) -> AppError {
+    metrics_error(
+        409,
+        format!("`{name}` is registered as {registered}, not {requested}"),
+    )
+}
+
+/// `{a="1",b="2"}`, 没有标签时为空字符串
+fn prom_labels(labels: &Labels, le: Option<&str>) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    };
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+        .collect();
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn prom_float(value: f64) -> String {
+    if value.is_nan() {
+        String::from("NaN")
+    } else if value.is_infinite() {
+        String::from(if value > 0.0 { "+Inf" } else { "-Inf" })
+    } else {
+        value.to_string()
+    }
+}
+
+/// JSON 中没有 NaN 和无穷大, 输出 null
+fn json_float(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        String::from("null")
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// 单个连接读写请求的超时时间
+///
+/// 连接在后台线程中依次处理, 没有超时的话一个只连接不发送请求的客户端会让后面的抓取和 stop 一直等待
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// # MetricsServer 提供 `/metrics` 的 HTTP 服务
+///
+/// - `GET /metrics`: Prometheus 文本格式
+/// - `GET /metrics.json`: JSON 格式
+///
+/// 每个连接只处理一个请求, 足够 Prometheus 抓取使用; 被丢弃时停止后台线程
+pub struct MetricsServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+#[allow(unused)]
+impl MetricsServer {
+    /// 监听 addr, 端口为 0 时由系统分配, 通过 local_addr 获取实际地址
+    ///
+    /// registry 可以是 `&'static MetricsRegistry` (比如 `global()`) 或者 `Arc<MetricsRegistry>`
+    pub fn bind<R>(addr: impl ToSocketAddrs, registry: R) -> Result<MetricsServer, AppError>
+    where
+        R: Deref<Target = MetricsRegistry> + Send + 'static,
+    {
+        let io_error = |err: std::io::Error| AppError::new("io", 500, format!("bind: {err}"));
+        let listener = TcpListener::bind(addr).map_err(io_error)?;
+        let addr = listener.local_addr().map_err(io_error)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = std::thread::spawn({
+            let stop = Arc::clone(&stop);
+            move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::Acquire) {
+                        break;
+                    }
+                    // 单个连接出错不影响后面的请求
+                    if let Ok(stream) = stream {
+                        let _ = handle_connection(stream, &registry);
+                    }
+                }
+            }
+        });
+        Ok(MetricsServer {
+            addr,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            self.stop.store(true, Ordering::Release);
+            // accept 是阻塞的, 连接一次让后台线程醒来检查 stop
+            let _ = TcpStream::connect(self.addr);
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn handle_connection(stream: TcpStream, registry: &MetricsRegistry) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // 读完请求头, 不关心其内容
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            registry.render_prometheus(),
+        ),
+        (Some("GET"), Some("/metrics.json")) => {
+            ("200 OK", "application/json", registry.render_json())
+        }
+        (Some("GET"), Some(_)) => ("404 Not Found", "text/plain", String::from("not found\n")),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            String::from("method not allowed\n"),
+        ),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
@@ -0,0 +1,237 @@
+/// 指标注册表与 `/metrics` 服务
+///
+/// 注册表和 HTTP 服务的实现见 metrics.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod metrics;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use metrics::{MetricsRegistry, MetricsServer};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 发送一个 HTTP 请求, 返回 (状态行, 响应体)
+fn http(addr: SocketAddr, method: &str, path: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{method} {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.lines().next().unwrap().to_string();
+    (status, body.to_string())
+}
+
+/// # example01 三种指标
+#[allow(unused)]
+fn example01() {
+    let registry = MetricsRegistry::new();
+
+    let ok = registry
+        .counter("http_requests_total", "请求数", &[("code", "200")])
+        .unwrap();
+    ok.inc();
+    ok.inc_by(2);
+    // 同名同标签返回同一个计数器, 标签的顺序无关
+    let same = registry
+        .counter("http_requests_total", "请求数", &[("code", "200")])
+        .unwrap();
+    assert!(Arc::ptr_eq(&ok, &same));
+    assert_eq!(same.get(), 3);
+
+    let connections = registry.gauge("connections", "当前连接数", &[]).unwrap();
+    connections.inc();
+    connections.inc();
+    connections.dec();
+    assert_eq!(connections.get(), 1.0);
+
+    let latency = registry
+        .histogram("latency_seconds", "请求耗时", &[0.1, 0.5, 1.0], &[])
+        .unwrap();
+    for value in [0.05, 0.1, 0.3, 2.0] {
+        latency.observe(value);
+    }
+    let snapshot = latency.snapshot();
+    // 上限是 "小于等于", 0.1 落在第一个区间
+    assert_eq!(
+        snapshot.buckets,
+        [(0.1, 2), (0.5, 3), (1.0, 3), (f64::INFINITY, 4)]
+    );
+    assert_eq!(snapshot.count, 4);
+    assert!((snapshot.sum - 2.45).abs() < 1e-9);
+
+    // 非有限值被忽略, 不会改变任何区间和 sum
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        latency.observe(value);
+    }
+    assert_eq!(latency.snapshot(), snapshot);
+    assert!(registry
+        .render_prometheus()
+        .contains("latency_seconds_sum 2.45"));
+}
+
+/// # example02 错误的注册返回 AppError
+#[allow(unused)]
+fn example02() {
+    let registry = MetricsRegistry::new();
+    registry.counter("jobs", "任务数", &[]).unwrap();
+
+    let errors = [
+        // 同名但类型不同
+        registry.gauge("jobs", "任务数", &[]).err().unwrap(),
+        registry.counter("1jobs", "", &[]).err().unwrap(),
+        registry
+            .counter("jobs", "", &[("__name", "x")])
+            .err()
+            .unwrap(),
+        registry
+            .counter("jobs", "", &[("a", "1"), ("a", "2")])
+            .err()
+            .unwrap(),
+        registry.histogram("h", "", &[1.0, 0.5], &[]).err().unwrap(),
+        registry
+            .histogram("h", "", &[1.0], &[("le", "1")])
+            .err()
+            .unwrap(),
+    ];
+    for err in &errors {
+        eprintln!("{err:?}");
+        assert_eq!(err.kind, "metrics");
+    }
+    assert_eq!(errors[0].code, 409);
+}
+
+/// # example03 多个线程同时更新全局注册表
+/// 注册之后每个线程持有自己的句柄, 更新时不会锁住注册表
+#[allow(unused)]
+fn example03() {
+    let registry = metrics::global();
+    std::thread::scope(|s| {
+        for thread in 0..8 {
+            s.spawn(move || {
+                let code = if thread % 2 == 0 { "200" } else { "500" };
+                let counter = registry
+                    .counter("worker_requests_total", "请求数", &[("code", code)])
+                    .unwrap();
+                for _ in 0..10_000 {
+                    counter.inc();
+                }
+            });
+        }
+    });
+    for code in ["200", "500"] {
+        let counter = registry
+            .counter("worker_requests_total", "请求数", &[("code", code)])
+            .unwrap();
+        assert_eq!(counter.get(), 40_000);
+    }
+}
+
+/// # example04 导出为 Prometheus 文本格式和 JSON
+#[allow(unused)]
+fn example04() {
+    let registry = MetricsRegistry::new();
+    registry
+        .counter(
+            "requests_total",
+            "请求数",
+            &[("path", "/a\"b"), ("code", "200")],
+        )
+        .unwrap()
+        .inc();
+    registry
+        .gauge("temperature", "温度", &[])
+        .unwrap()
+        .set(-1.5);
+    registry
+        .histogram("size_bytes", "大小", &[100.0], &[])
+        .unwrap()
+        .observe(42.0);
+
+    let text = registry.render_prometheus();
+    print!("{text}");
+    assert_eq!(
+        text,
+        concat!(
+            "# HELP requests_total 请求数\n",
+            "# TYPE requests_total counter\n",
+            "requests_total{code=\"200\",path=\"/a\\\"b\"} 1\n",
+            "# HELP size_bytes 大小\n",
+            "# TYPE size_bytes histogram\n",
+            "size_bytes_bucket{le=\"100\"} 1\n",
+            "size_bytes_bucket{le=\"+Inf\"} 1\n",
+            "size_bytes_sum 42\n",
+            "size_bytes_count 1\n",
+            "# HELP temperature 温度\n",
+            "# TYPE temperature gauge\n",
+            "temperature -1.5\n",
+        )
+    );
+
+    let json = registry.render_json();
+    println!("{json}");
+    assert!(json.starts_with(
+        r#"{"requests_total":{"type":"counter","help":"请求数","series":[{"labels":{"code":"200","path":"/a\"b"},"value":1}]}"#
+    ));
+    assert!(json.contains(
+        r#""buckets":[{"le":100,"count":1},{"le":"+Inf","count":1}],"sum":42,"count":1"#
+    ));
+    assert!(json.ends_with(r#""series":[{"labels":{},"value":-1.5}]}}"#));
+}
+
+/// # example05 通过 HTTP 访问 /metrics
+/// 只监听本机回环地址, 端口由系统分配
+#[allow(unused)]
+fn example05() {
+    let registry = Arc::new(MetricsRegistry::new());
+    let hits = registry.counter("hits_total", "访问次数", &[]).unwrap();
+    hits.inc_by(7);
+
+    let server = MetricsServer::bind("127.0.0.1:0", Arc::clone(&registry)).unwrap();
+    let addr = server.local_addr();
+    println!("serving on http://{addr}/metrics");
+
+    let (status, body) = http(addr, "GET", "/metrics");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(body.contains("hits_total 7\n"));
+
+    // 服务读取的是同一个注册表, 更新立即可见
+    hits.inc();
+    let (status, body) = http(addr, "GET", "/metrics.json");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+    assert!(body.contains(r#""value":8"#));
+
+    assert_eq!(http(addr, "GET", "/other").0, "HTTP/1.1 404 Not Found");
+    assert_eq!(
+        http(addr, "POST", "/metrics").0,
+        "HTTP/1.1 405 Method Not Allowed"
+    );
+
+    // 全局注册表也可以直接提供服务
+    let global = MetricsServer::bind("127.0.0.1:0", metrics::global()).unwrap();
+    let (status, _) = http(global.local_addr(), "GET", "/metrics");
+    assert_eq!(status, "HTTP/1.1 200 OK");
+
+    // 只连接不发送请求的客户端最多占用 CONNECTION_TIMEOUT, 之后的请求和 stop 都不会一直等待
+    let idle = TcpStream::connect(addr).unwrap();
+    let start = std::time::Instant::now();
+    assert_eq!(http(addr, "GET", "/metrics").0, "HTTP/1.1 200 OK");
+    server.stop();
+    assert!(start.elapsed() < metrics::CONNECTION_TIMEOUT * 3);
+    drop(idle);
+    assert!(TcpStream::connect(addr).is_err());
+}
@@ -0,0 +1,232 @@
+/// 内存顺序实验
+///
+/// global_variable.rs 中的计数器都使用 `Ordering::Relaxed`:
+/// 计数器只需要自身的读改写是原子的, 不需要通过它同步其他内存, 所以 Relaxed 足够;
+/// 而 `generate_id` 最初的问题在于 "检查" 和 "自增" 分成了两次操作, 换成更强的 Ordering 也无法修复 (见 id_stress.rs)
+///
+/// 一旦要用一个原子变量 "发布" 其他数据 (标志位, 锁), Relaxed 就不够了
+///
+/// 每个 litmus test 分别使用不同的 Ordering:
+/// 1. 模型检查 (见 memory_model.rs): 列出内存模型允许的所有结果
+/// 2. 真实运行: 统计实际观察到的结果出现的次数
+///
+/// 模型允许的结果不一定能在真实机器上观察到: x86 的内存模型比 Rust 的要强,
+/// 单核机器上更是几乎看不到; 但模型禁止的结果一定不会出现
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod memory_model;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use memory_model::{explore, message_passing, spinlock, store_buffering, Litmus, Outcome};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Barrier;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+    ])
+}
+
+/// 真实运行的次数
+const ITERATIONS: usize = 10_000;
+
+/// 打印模型允许的结果, 返回结果的集合
+fn print_model<T: Ord + std::fmt::Debug>(
+    label: &str,
+    litmus: &Litmus,
+    project: impl Fn(&Outcome) -> T,
+) -> BTreeSet<T> {
+    let mut allowed = BTreeMap::new();
+    for (outcome, executions) in explore(litmus) {
+        *allowed.entry(project(&outcome)).or_insert(0) += executions;
+    }
+    println!("{} / {label}: 模型允许的结果", litmus.name);
+    for (outcome, executions) in &allowed {
+        println!("  {outcome:?}: {executions} 种执行");
+    }
+    allowed.into_keys().collect()
+}
+
+/// 打印真实运行时观察到的结果
+fn print_observed<T: Ord + std::fmt::Debug>(label: &str, observed: &BTreeMap<T, usize>) {
+    println!("  {label}: 真实运行 {ITERATIONS} 次");
+    for (outcome, count) in observed {
+        println!("    {outcome:?}: {count} 次");
+    }
+}
+
+/// 两个线程各自执行 ITERATIONS 轮, 每轮开始前重置, 返回每种结果的次数
+/// 每轮都用 Barrier 让两个线程同时起跑, 尽量增加交错的机会
+fn run_pair<T: Ord>(
+    reset: impl Fn(),
+    thread0: impl Fn() -> u64 + Sync,
+    thread1: impl Fn() -> u64 + Sync,
+    combine: impl Fn(u64, u64) -> T,
+) -> BTreeMap<T, usize> {
+    let start = Barrier::new(2);
+    let end = Barrier::new(2);
+    let mut observed = BTreeMap::new();
+    std::thread::scope(|s| {
+        let other = s.spawn(|| {
+            (0..ITERATIONS)
+                .map(|_| {
+                    start.wait();
+                    let r = thread1();
+                    end.wait();
+                    r
+                })
+                .collect::<Vec<_>>()
+        });
+        let mut mine = Vec::with_capacity(ITERATIONS);
+        for _ in 0..ITERATIONS {
+            reset();
+            start.wait();
+            mine.push(thread0());
+            end.wait();
+        }
+        for (r0, r1) in mine.into_iter().zip(other.join().unwrap()) {
+            *observed.entry(combine(r0, r1)).or_insert(0) += 1;
+        }
+    });
+    observed
+}
+
+/// # example01 消息传递
+/// 线程 0 写入数据后设置标志, 线程 1 看到标志后读取数据
+/// - Relaxed: 可能看到标志却读到旧数据 (1, 0)
+/// - Release / Acquire: 看到标志就一定能看到数据
+#[allow(unused)]
+fn example01() {
+    for (label, store, load) in [
+        ("Relaxed", Ordering::Relaxed, Ordering::Relaxed),
+        ("Release/Acquire", Ordering::Release, Ordering::Acquire),
+        ("SeqCst", Ordering::SeqCst, Ordering::SeqCst),
+    ] {
+        let allowed = print_model(label, &message_passing(store, load), |o| {
+            (o.registers[1][0], o.registers[1][1])
+        });
+        assert_eq!(allowed.contains(&(1, 0)), store == Ordering::Relaxed);
+
+        let data = AtomicU64::new(0);
+        let flag = AtomicU64::new(0);
+        let observed = run_pair(
+            || {
+                data.store(0, Ordering::Relaxed);
+                flag.store(0, Ordering::Relaxed);
+            },
+            || {
+                data.store(42, Ordering::Relaxed);
+                flag.store(1, store);
+                0
+            },
+            || {
+                let r0 = flag.load(load);
+                r0 * 100 + data.load(Ordering::Relaxed)
+            },
+            |_, r| (r / 100, r % 100),
+        );
+        print_observed(label, &observed);
+        assert!(observed.keys().all(|outcome| allowed.contains(outcome)));
+    }
+}
+
+/// # example02 存储缓冲
+/// 两个线程各自先写自己的变量, 再读对方的变量
+/// - Relaxed 和 Release / Acquire: 都可能读到 0, 因为写入可能还停留在各自的缓冲区中
+/// - SeqCst: 所有 SeqCst 操作有一个全序, 后执行的 load 一定能看到先执行的 store
+#[allow(unused)]
+fn example02() {
+    for (label, store, load) in [
+        ("Relaxed", Ordering::Relaxed, Ordering::Relaxed),
+        ("Release/Acquire", Ordering::Release, Ordering::Acquire),
+        ("SeqCst", Ordering::SeqCst, Ordering::SeqCst),
+    ] {
+        let allowed = print_model(label, &store_buffering(store, load), |o| {
+            (o.registers[0][0], o.registers[1][0])
+        });
+        assert_eq!(allowed.contains(&(0, 0)), store != Ordering::SeqCst);
+
+        let x = AtomicU64::new(0);
+        let y = AtomicU64::new(0);
+        let observed = run_pair(
+            || {
+                x.store(0, Ordering::Relaxed);
+                y.store(0, Ordering::Relaxed);
+            },
+            || {
+                x.store(1, store);
+                y.load(load)
+            },
+            || {
+                y.store(1, store);
+                x.load(load)
+            },
+            |r0, r1| (r0, r1),
+        );
+        print_observed(label, &observed);
+        assert!(observed.keys().all(|outcome| allowed.contains(outcome)));
+    }
+}
+
+/// # example03 自旋锁
+/// 加锁使用 Acquire, 解锁使用 Release, 临界区中的读写才能被下一个持有锁的线程看到
+/// 全部使用 Relaxed 时, 第二个线程可能读到旧的 data, 导致丢失一次更新
+#[allow(unused)]
+fn example03() {
+    for (label, lock, unlock) in [
+        ("Relaxed", Ordering::Relaxed, Ordering::Relaxed),
+        ("Acquire/Release", Ordering::Acquire, Ordering::Release),
+    ] {
+        let allowed = print_model(label, &spinlock(lock, unlock), |o| o.memory[1]);
+        assert_eq!(allowed.contains(&1), lock == Ordering::Relaxed);
+        assert!(allowed.contains(&2));
+    }
+}
+
+/// # example04 真实的自旋锁
+/// 多个线程在锁的保护下对 data 做非原子的 "读取 + 写入"
+#[allow(unused)]
+fn example04() {
+    for (label, lock, unlock) in [
+        ("Relaxed", Ordering::Relaxed, Ordering::Relaxed),
+        ("Acquire/Release", Ordering::Acquire, Ordering::Release),
+    ] {
+        let locked = AtomicU64::new(0);
+        let data = AtomicU64::new(0);
+        let threads = 4;
+        std::thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    for _ in 0..ITERATIONS {
+                        while locked
+                            .compare_exchange_weak(0, 1, lock, Ordering::Relaxed)
+                            .is_err()
+                        {
+                            std::hint::spin_loop();
+                        }
+                        let value = data.load(Ordering::Relaxed);
+                        data.store(value + 1, Ordering::Relaxed);
+                        locked.store(0, unlock);
+                    }
+                });
+            }
+        });
+        let total = data.load(Ordering::Relaxed);
+        let expected = (threads * ITERATIONS) as u64;
+        println!(
+            "spinlock / {label}: {total} / {expected}, 丢失 {} 次更新",
+            expected - total
+        );
+        if lock == Ordering::Acquire {
+            assert_eq!(total, expected);
+        }
+    }
+}
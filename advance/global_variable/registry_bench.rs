@@ -0,0 +1,116 @@
+/// 全局注册表性能对比
+///
+/// 多个线程同时读写同一个注册表, 比较 Mutex / RwLock / 分片读写锁的吞吐量
+///
+/// 使用 `Instant` 计时, 结果和机器核数, 调度有关, 只用于比较相对快慢;
+/// 请使用 `rustc -O` 编译后运行
+///
+/// ! 单核机器上线程不会真正并行, 锁几乎没有争用, 分片反而因为多算一次哈希而更慢
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+mod registry;
+
+use registry::{MutexRegistry, Registry, RwLockRegistry, ShardedRegistry};
+use std::sync::Barrier;
+use std::time::{Duration, Instant};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[("example01", example01), ("example02", example02)])
+}
+
+/// 预先插入的键的数量
+const KEYS: u64 = 10_000;
+
+/// 一组负载
+struct Workload {
+    name: &'static str,
+    threads: usize,
+    ops_per_thread: u64,
+    /// 每 100 次操作中写操作的次数
+    writes_per_100: u64,
+}
+
+/// 运行一组负载, 返回耗时
+fn run<R: Registry<u64, u64>>(registry: &R, workload: &Workload) -> Duration {
+    for key in 0..KEYS {
+        registry.insert(key, key);
+    }
+    let barrier = Barrier::new(workload.threads + 1);
+    let start = std::thread::scope(|s| {
+        for thread in 0..workload.threads as u64 {
+            let barrier = &barrier;
+            s.spawn(move || {
+                // 每个线程使用不同的序列, 避免所有线程同时访问同一个键;
+                // 保留完整的 64 位状态, 只在取键时取模, 否则序列很快进入只有几十个键的循环
+                let mut state = thread * 7919;
+                barrier.wait();
+                for op in 0..workload.ops_per_thread {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    // 高位的随机性更好
+                    let key = (state >> 33) % KEYS;
+                    if op % 100 < workload.writes_per_100 {
+                        registry.insert(key, op);
+                    } else {
+                        assert!(registry.get(&key).is_some());
+                    }
+                }
+            });
+        }
+        barrier.wait();
+        let start = Instant::now();
+        // 离开 scope 时等待所有线程结束
+        start
+    });
+    let elapsed = start.elapsed();
+    // 只会覆盖已有的键, 不会增加或丢失
+    assert_eq!(registry.len(), KEYS as usize);
+    elapsed
+}
+
+/// 依次对三种注册表运行同一组负载并打印结果
+fn compare(workload: &Workload) -> [(&'static str, Duration); 3] {
+    let results = [
+        ("Mutex", run(&MutexRegistry::new(), workload)),
+        ("RwLock", run(&RwLockRegistry::new(), workload)),
+        ("Sharded", run(&ShardedRegistry::new(), workload)),
+    ];
+    let total_ops = workload.threads as u64 * workload.ops_per_thread;
+    println!(
+        "{} ({} 个线程, 写操作占 {}%)",
+        workload.name, workload.threads, workload.writes_per_100
+    );
+    for (name, elapsed) in &results {
+        let ops = total_ops as f64 / elapsed.as_secs_f64();
+        println!("  {name:<8} {:>8.2?} {:>12.0} ops/s", elapsed, ops);
+    }
+    results
+}
+
+/// # example01 读多写少
+#[allow(unused)]
+fn example01() {
+    compare(&Workload {
+        name: "读多写少",
+        threads: 8,
+        ops_per_thread: 200_000,
+        writes_per_100: 5,
+    });
+}
+
+/// # example02 读写各半
+#[allow(unused)]
+fn example02() {
+    compare(&Workload {
+        name: "读写各半",
+        threads: 8,
+        ops_per_thread: 200_000,
+        writes_per_100: 50,
+    });
+}
@@ -0,0 +1,225 @@
+/// 线程局部变量与作用域内的全局变量
+///
+/// LocalBuffer / ScopedContext 的实现见 thread_locals.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+mod thread_locals;
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Barrier, Mutex};
+use thread_locals::{LocalBuffer, Overrides, ScopedContext};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// # example01 线程局部计数器
+/// 每个线程都有自己的一份 CALLS, 互不影响, 也不需要原子操作
+#[allow(unused)]
+fn example01() {
+    thread_local! {
+        static CALLS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn record_call() -> usize {
+        CALLS.with(|calls| {
+            calls.set(calls.get() + 1);
+            calls.get()
+        })
+    }
+
+    record_call();
+    std::thread::scope(|s| {
+        for n in 1..=4 {
+            s.spawn(move || {
+                for _ in 0..n * 100 {
+                    record_call();
+                }
+                // 新线程从 0 开始, 看不到主线程和其他线程的调用
+                assert_eq!(CALLS.get(), n * 100);
+            });
+        }
+    });
+    assert_eq!(CALLS.get(), 1);
+}
+
+/// 全局请求数, 与 global_variable.rs 中的 REQUEST_RECV_NEW 相同
+static REQUEST_RECV_NEW: AtomicUsize = AtomicUsize::new(0);
+
+/// # example02 线程内累加, 线程退出时合并到全局计数
+/// 每次请求只修改线程自己的 Cell, 线程退出时 LocalBuffer 被 drop, 把剩余的计数加到 REQUEST_RECV_NEW
+#[allow(unused)]
+fn example02() {
+    thread_local! {
+        // 只在线程退出时合并
+        static ON_EXIT: LocalBuffer = const { LocalBuffer::new(&REQUEST_RECV_NEW, 0) };
+        // 每 64 次合并一次, 全局计数的延迟有上限
+        static BATCHED: LocalBuffer = const { LocalBuffer::new(&REQUEST_RECV_NEW, 64) };
+    }
+
+    REQUEST_RECV_NEW.store(0, Ordering::Relaxed);
+    let started = Barrier::new(9);
+    let counted = Barrier::new(9);
+    std::thread::scope(|s| {
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                s.spawn(|| {
+                    started.wait();
+                    for _ in 0..1000 {
+                        ON_EXIT.with(|buffer| buffer.add(1));
+                    }
+                    assert_eq!(ON_EXIT.with(LocalBuffer::pending), 1000);
+                    counted.wait();
+                    // 主线程在这里检查全局计数
+                    counted.wait();
+                })
+            })
+            .collect();
+        started.wait();
+        counted.wait();
+        // 所有线程都计数完毕, 但都还没有退出
+        assert_eq!(REQUEST_RECV_NEW.load(Ordering::Relaxed), 0);
+        counted.wait();
+        // ! scope 结束只表示线程的闭包已经返回, thread_local 的析构可能还没有执行;
+        // join 会等待线程真正退出, 之后析构一定已经完成
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    });
+    assert_eq!(REQUEST_RECV_NEW.load(Ordering::Relaxed), 8000);
+
+    REQUEST_RECV_NEW.store(0, Ordering::Relaxed);
+    std::thread::spawn(|| {
+        for _ in 0..1000 {
+            BATCHED.with(|buffer| buffer.add(1));
+        }
+        // 1000 = 15 * 64 + 40
+        assert_eq!(BATCHED.with(LocalBuffer::pending), 40);
+        assert_eq!(REQUEST_RECV_NEW.load(Ordering::Relaxed), 960);
+    })
+    .join()
+    .unwrap();
+    assert_eq!(REQUEST_RECV_NEW.load(Ordering::Relaxed), 1000);
+    println!("当前用户的请求数量是 {:?}", REQUEST_RECV_NEW);
+}
+
+/// # example03 thread_local 的析构
+/// 线程退出时, 该线程中已经初始化过的 thread_local 会被 drop; 从未访问过的不会被初始化, 也不会被 drop
+#[allow(unused)]
+fn example03() {
+    static DROPPED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct Tracer(&'static str);
+    impl Drop for Tracer {
+        fn drop(&mut self) {
+            let thread = std::thread::current();
+            let name = thread.name().unwrap_or("unnamed");
+            DROPPED
+                .lock()
+                .unwrap()
+                .push(format!("{} in {name}", self.0));
+        }
+    }
+
+    thread_local! {
+        static USED: Tracer = const { Tracer("used") };
+        static UNUSED: Tracer = const { Tracer("unused") };
+    }
+
+    for name in ["worker-1", "worker-2"] {
+        std::thread::Builder::new()
+            .name(String::from(name))
+            .spawn(|| USED.with(|_| {}))
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+    let dropped = DROPPED.lock().unwrap();
+    println!("{dropped:?}");
+    assert_eq!(*dropped, ["used in worker-1", "used in worker-2"]);
+}
+
+/// 日志级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Debug,
+    Info,
+    Warn,
+}
+
+thread_local! {
+    static LEVEL_OVERRIDES: Overrides<Level> = const { RefCell::new(Vec::new()) };
+}
+/// 全局的日志级别, 默认为 Info
+static LEVEL: ScopedContext<Level> = ScopedContext::new(&LEVEL_OVERRIDES, || Level::Info);
+
+/// 读取全局日志级别的业务代码
+fn should_log(level: Level) -> bool {
+    level as u8 >= *LEVEL.current() as u8
+}
+
+/// # example04 在作用域内覆盖全局值
+/// 覆盖只在当前线程, 当前作用域内生效, 可以嵌套, 发生 panic 也会恢复
+#[allow(unused)]
+fn example04() {
+    assert!(!should_log(Level::Debug));
+
+    LEVEL.set(Level::Debug, || {
+        assert!(should_log(Level::Debug));
+        LEVEL.set(Level::Warn, || assert!(!should_log(Level::Info)));
+        assert_eq!(*LEVEL.current(), Level::Debug);
+    });
+    assert_eq!(*LEVEL.current(), Level::Info);
+
+    let result = std::panic::catch_unwind(|| {
+        LEVEL.set(Level::Warn, || panic!("在覆盖期间 panic"));
+    });
+    assert!(result.is_err());
+    assert_eq!(LEVEL.depth(), 0);
+    assert_eq!(*LEVEL.current(), Level::Info);
+
+    // 并行运行的 "测试" 各自覆盖, 互不影响
+    let barrier = Barrier::new(3);
+    std::thread::scope(|s| {
+        for level in [Level::Debug, Level::Warn, Level::Info] {
+            let barrier = &barrier;
+            s.spawn(move || {
+                LEVEL.set(level, || {
+                    // 三个线程同时处于覆盖期间
+                    barrier.wait();
+                    for _ in 0..1000 {
+                        assert_eq!(*LEVEL.current(), level);
+                    }
+                    barrier.wait();
+                });
+            });
+        }
+    });
+}
+
+/// # example05 把覆盖的值传递给新线程
+/// 新线程看不到父线程的覆盖, 需要先用 current 取出再在新线程中 set
+#[allow(unused)]
+fn example05() {
+    LEVEL.set(Level::Debug, || {
+        let inherited = LEVEL.current();
+        std::thread::scope(|s| {
+            s.spawn(|| assert_eq!(*LEVEL.current(), Level::Info));
+            s.spawn(|| {
+                LEVEL.set_arc(inherited, || assert!(should_log(Level::Debug)));
+            });
+        });
+    });
+}
@@ -0,0 +1,143 @@
+/// 线程局部变量与作用域内的全局变量
+///
+/// `static` 在所有线程之间共享, 每次访问都可能和其他线程争用;
+/// `thread_local!` 中的变量每个线程各有一份, 访问时不需要同步
+///
+/// - `LocalBuffer`: 先在线程内累加, 攒够一批或者线程退出时再加到全局的 `AtomicUsize` 中
+///   (线程退出时 thread_local 中的值会被 drop, 在 Drop 中完成最后一次合并)
+/// - `ScopedContext`: 全局的默认值 + 每个线程自己的覆盖栈,
+///   `set(value, f)` 只在 f 执行期间, 且只在当前线程中生效, 适合在并行运行的测试中替换全局配置
+///
+/// 两者都需要调用方自己声明 `thread_local!`, 因为 thread_local 不能是泛型的
+#[allow(unused)]
+struct Description;
+
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::LocalKey;
+
+/// # LocalBuffer 线程内的计数缓冲
+///
+/// ```ignore
+/// static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+/// thread_local! {
+///     static LOCAL_REQUESTS: LocalBuffer = const { LocalBuffer::new(&REQUESTS, 64) };
+/// }
+/// LOCAL_REQUESTS.with(|buffer| buffer.add(1));
+/// ```
+///
+/// ! 线程退出时才会合并剩余的计数, 在此之前全局计数可能偏小;
+/// 主线程的 thread_local 在进程退出时不保证会被 drop, 需要时手动调用 flush
+pub struct LocalBuffer {
+    pending: Cell<usize>,
+    target: &'static AtomicUsize,
+    flush_every: usize,
+}
+
+#[allow(unused)]
+impl LocalBuffer {
+    /// 累计到 flush_every 时合并一次, 为 0 时只在 flush 和线程退出时合并
+    pub const fn new(target: &'static AtomicUsize, flush_every: usize) -> LocalBuffer {
+        LocalBuffer {
+            pending: Cell::new(0),
+            target,
+            flush_every,
+        }
+    }
+
+    pub fn add(&self, n: usize) {
+        let pending = self.pending.get() + n;
+        self.pending.set(pending);
+        if self.flush_every != 0 && pending >= self.flush_every {
+            self.flush();
+        }
+    }
+
+    /// 还没有合并到全局计数中的数量
+    pub fn pending(&self) -> usize {
+        self.pending.get()
+    }
+
+    /// 立即合并到全局计数
+    pub fn flush(&self) {
+        let pending = self.pending.replace(0);
+        if pending != 0 {
+            self.target.fetch_add(pending, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for LocalBuffer {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// ScopedContext 每个线程的覆盖栈
+pub type Overrides<T> = RefCell<Vec<Arc<T>>>;
+
+/// # ScopedContext 可以在作用域内覆盖的全局值
+///
+/// ```ignore
+/// thread_local! {
+///     static LEVEL_OVERRIDES: Overrides<Level> = const { RefCell::new(Vec::new()) };
+/// }
+/// static LEVEL: ScopedContext<Level> = ScopedContext::new(&LEVEL_OVERRIDES, || Level::Info);
+///
+/// LEVEL.set(Level::Debug, || assert_eq!(*LEVEL.current(), Level::Debug));
+/// ```
+pub struct ScopedContext<T: 'static> {
+    overrides: &'static LocalKey<Overrides<T>>,
+    default: fn() -> T,
+    global: OnceLock<Arc<T>>,
+}
+
+/// 离开作用域时弹出覆盖的值, f 发生 panic 时同样会弹出
+struct PopGuard<T: 'static> {
+    overrides: &'static LocalKey<Overrides<T>>,
+}
+
+impl<T> Drop for PopGuard<T> {
+    fn drop(&mut self) {
+        self.overrides.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+#[allow(unused)]
+impl<T> ScopedContext<T> {
+    /// default 在第一次需要默认值时调用, 所有线程共享同一个默认值
+    pub const fn new(overrides: &'static LocalKey<Overrides<T>>, default: fn() -> T) -> Self {
+        ScopedContext {
+            overrides,
+            default,
+            global: OnceLock::new(),
+        }
+    }
+
+    /// 当前线程中生效的值: 最内层的覆盖, 没有覆盖时为默认值
+    pub fn current(&self) -> Arc<T> {
+        self.overrides
+            .with(|stack| stack.borrow().last().cloned())
+            .unwrap_or_else(|| Arc::clone(self.global.get_or_init(|| Arc::new((self.default)()))))
+    }
+
+    /// 在 f 执行期间把当前线程中的值替换为 value, 可以嵌套
+    pub fn set<R>(&self, value: T, f: impl FnOnce() -> R) -> R {
+        self.set_arc(Arc::new(value), f)
+    }
+
+    /// 与 set 相同, 用于把 current 得到的值传递给新线程
+    pub fn set_arc<R>(&self, value: Arc<T>, f: impl FnOnce() -> R) -> R {
+        self.overrides.with(|stack| stack.borrow_mut().push(value));
+        let _guard = PopGuard {
+            overrides: self.overrides,
+        };
+        f()
+    }
+
+    /// 当前线程中覆盖的层数
+    pub fn depth(&self) -> usize {
+        self.overrides.with(|stack| stack.borrow().len())
+    }
+}
@@ -2,10 +2,44 @@
 // Rc 与 Arc 实现 1vN 所有权机制
 // Rc: 引用计数 (Reference counting)
 // Arc: 原子引用计数 (Atomic Reference counting)
-fn main() {
-    example01();
-    example02();
-    example03(); // todo
+//
+// MyRc / Weak 的实现见 my_rc.rs, 下面的示例同时在 std::rc::Rc 和 MyRc 上运行同一段代码
+//
+// 使用 Miri 检查 MyRc 的未定义行为 (example06 故意泄漏循环引用, 需要忽略泄漏):
+// `$(rustup which --toolchain nightly miri) --sysroot $(cargo +nightly miri setup --print-sysroot) --edition 2021 -Zmiri-ignore-leaks ac_and_arc.rs`
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod my_rc;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+        ("example06", example06),
+    ])
+}
+
+/// 把同一段代码分别用 std::rc::{Rc, Weak} 和 my_rc::{MyRc, Weak} 运行一次, 断言两次的结果相同
+macro_rules! side_by_side {
+    ($body:block) => {{
+        let expected = {
+            #[allow(unused_imports)]
+            use std::rc::{Rc, Weak};
+            $body
+        };
+        let actual = {
+            #[allow(unused_imports)]
+            use crate::my_rc::{MyRc as Rc, Weak};
+            $body
+        };
+        assert_eq!(expected, actual);
+        actual
+    }};
 }
 
 /// # example01 所有权被转移导致的错误示例
@@ -34,7 +68,155 @@ fn example02() {
 }
 
 /// # example03 使用 Rc::clone
+/// Rc::clone 只增加引用计数, 不会复制背后的数据; 每个 Rc 离开作用域时计数 - 1, 降为 0 时数据被释放
 #[allow(unused)]
 fn example03() {
-    todo!();
+    let counts = side_by_side!({
+        let mut counts = Vec::new();
+        let a = Rc::new(String::from("hello world!"));
+        counts.push(Rc::strong_count(&a));
+        {
+            // 推荐使用 Rc::clone(&a) 而不是 a.clone(), 一眼就能看出只是增加计数
+            let b = Rc::clone(&a);
+            let c = a.clone();
+            counts.push(Rc::strong_count(&a));
+            // 三者指向同一份数据
+            assert!(Rc::ptr_eq(&b, &c));
+            assert_eq!(b.as_ptr(), a.as_ptr());
+        }
+        // b 和 c 离开作用域, 计数 - 2
+        counts.push(Rc::strong_count(&a));
+        counts
+    });
+    assert_eq!(counts, [1, 3, 1]);
+}
+
+/// # example04 Weak 弱引用
+/// Weak 不阻止数据被释放, 通过 upgrade 访问时需要检查数据是否还在
+#[allow(unused)]
+fn example04() {
+    side_by_side!({
+        let mut log = Vec::new();
+        let strong = Rc::new(5);
+        let weak = Rc::downgrade(&strong);
+        let weak2 = Weak::clone(&weak);
+        log.push((Rc::strong_count(&strong), Rc::weak_count(&strong)));
+        log.push((weak.strong_count(), weak.weak_count()));
+
+        let upgraded = weak.upgrade();
+        log.push((
+            Rc::strong_count(&strong),
+            upgraded.as_deref().copied().unwrap_or(0),
+        ));
+        drop(upgraded);
+        drop(strong);
+        // 数据已释放, upgrade 返回 None, 计数都为 0
+        log.push((weak.strong_count(), weak.weak_count()));
+        assert!(weak2.upgrade().is_none());
+
+        // 不指向任何数据的 Weak
+        let empty: Weak<i32> = Weak::new();
+        assert!(empty.upgrade().is_none());
+        log
+    });
+}
+
+/// # example05 get_mut / make_mut / try_unwrap
+#[allow(unused)]
+fn example05() {
+    side_by_side!({
+        let mut log = Vec::new();
+
+        // get_mut: 没有其他 Rc 和 Weak 时才能修改
+        let mut a = Rc::new(String::from("a"));
+        Rc::get_mut(&mut a).unwrap().push('1');
+        let b = Rc::clone(&a);
+        log.push(format!("{:?}", Rc::get_mut(&mut a)));
+        drop(b);
+        let weak = Rc::downgrade(&a);
+        log.push(format!("{:?}", Rc::get_mut(&mut a)));
+        drop(weak);
+
+        // make_mut: 有其他 Rc 时先复制 (写时复制)
+        let mut shared = Rc::clone(&a);
+        Rc::make_mut(&mut shared).push('2');
+        log.push(format!("{a} {shared} {}", Rc::ptr_eq(&a, &shared)));
+
+        // make_mut: 只有 Weak 时把数据移走, Weak 失效
+        let weak = Rc::downgrade(&shared);
+        Rc::make_mut(&mut shared).push('3');
+        log.push(format!("{shared} {:?}", weak.upgrade()));
+
+        // try_unwrap: 只有一个 Rc 时取出数据
+        let c = Rc::clone(&a);
+        let a = Rc::try_unwrap(a).unwrap_err();
+        drop(c);
+        log.push(Rc::try_unwrap(a).unwrap());
+        log
+    });
+}
+
+/// # example06 循环引用
+/// 互相持有 Rc 的两个节点计数永远不会降为 0, 造成内存泄漏; 把其中一个方向改为 Weak 就能正常释放
+///
+/// 运行时检查这类泄漏见 rc_tracker.rs 与 rc_leaks.rs
+/// 能够回收循环引用的标记-清除垃圾回收见 gc.rs 与 gc_demo.rs
+#[allow(unused)]
+fn example06() {
+    use std::cell::{Cell, RefCell};
+
+    let dropped = side_by_side!({
+        struct Node<'a> {
+            name: &'static str,
+            next: RefCell<Option<Rc<Node<'a>>>>,
+            prev: RefCell<Weak<Node<'a>>>,
+            dropped: &'a Cell<usize>,
+        }
+        impl Drop for Node<'_> {
+            fn drop(&mut self) {
+                self.dropped.set(self.dropped.get() + 1);
+            }
+        }
+        let node = |name, dropped| {
+            Rc::new(Node {
+                name,
+                next: RefCell::new(None),
+                prev: RefCell::new(Weak::new()),
+                dropped,
+            })
+        };
+
+        // a -> b -> a, 两个方向都是 Rc
+        let leaked = Cell::new(0);
+        let a = node("a", &leaked);
+        let b = node("b", &leaked);
+        *a.next.borrow_mut() = Some(Rc::clone(&b));
+        *b.next.borrow_mut() = Some(Rc::clone(&a));
+        assert_eq!(Rc::strong_count(&a), 2);
+        drop((a, b));
+
+        // a -> b 是 Rc, b -> a 是 Weak
+        let freed = Cell::new(0);
+        let a = node("a", &freed);
+        let b = node("b", &freed);
+        *a.next.borrow_mut() = Some(Rc::clone(&b));
+        *b.prev.borrow_mut() = Rc::downgrade(&a);
+        let prev = b.prev.borrow().upgrade().map(|prev| prev.name);
+        assert_eq!(prev, Some("a"));
+        assert_eq!((Rc::strong_count(&a), Rc::weak_count(&a)), (1, 1));
+        drop((a, b));
+
+        // 父节点最后 drop: 析构 a 的过程中释放了 b, b 中指向 a 的 Weak 在 a 析构期间被 drop
+        let nested = Cell::new(0);
+        let parent = node("parent", &nested);
+        let child = node("child", &nested);
+        *parent.next.borrow_mut() = Some(Rc::clone(&child));
+        *child.prev.borrow_mut() = Rc::downgrade(&parent);
+        drop(child);
+        drop(parent);
+
+        (leaked.get(), freed.get(), nested.get())
+    });
+    // 循环引用的两个节点都没有被释放
+    assert_eq!(dropped, (0, 2, 2));
 }
\ No newline at end of file
@@ -0,0 +1,274 @@
+/// Arena 分配器
+///
+/// box.rs example02 中每个 Button / Select 都单独 Box::new 一次, 各自分配, 各自释放;
+/// arena 一次申请一大块内存, 之后的分配只是移动一下指针, 所有的值在 arena drop 时一起释放
+///
+/// - `TypedArena<T>`: 只能存放同一种类型, 按块存放在 Vec 中
+/// - `Bump`: 可以存放任意类型, 值按对齐要求依次放在原始内存块中
+///
+/// 两者的 alloc 都只需要 `&self`, 返回的 `&mut T` 的生命周期与 arena 的借用相同,
+/// 所以可以同时持有多个分配出来的引用, 也可以直接转换为 `&dyn Trait`:
+///
+/// ```ignore
+/// let bump = Bump::new();
+/// let widgets: Vec<&dyn Drawer> = vec![bump.alloc(Button { bid: 1 }), bump.alloc(Select { sid: 2 })];
+/// ```
+///
+/// 引用存在期间 arena 无法被 drop 或移动, 由借用检查保证
+#[allow(unused)]
+struct Description;
+
+use std::alloc::{self, Layout};
+use std::cell::{Cell, RefCell};
+use std::mem;
+use std::ptr::{self, NonNull};
+
+/// 第一个块的大小, 之后的块依次翻倍
+const FIRST_CHUNK_BYTES: usize = 4096;
+
+/// # TypedArena 同一种类型的 arena
+///
+/// 每个块是一个容量固定的 Vec, 放满之后申请一个新的 (容量翻倍) 块, 已有的块永远不会扩容,
+/// 所以其中的元素不会被移动, 返回的引用一直有效
+///
+/// 没有手动实现 Drop, 元素由 Vec 析构, 按分配的顺序;
+/// 元素之间互相引用时 (`T` 中包含 `&'a T`), 编译器的 drop 检查会要求 arena 比引用活得更久
+pub struct TypedArena<T> {
+    chunks: RefCell<Vec<Vec<T>>>,
+}
+
+#[allow(unused)]
+impl<T> TypedArena<T> {
+    pub fn new() -> TypedArena<T> {
+        TypedArena::with_capacity(FIRST_CHUNK_BYTES / mem::size_of::<T>().max(1))
+    }
+
+    /// 第一个块可以存放 capacity 个值
+    pub fn with_capacity(capacity: usize) -> TypedArena<T> {
+        TypedArena {
+            chunks: RefCell::new(vec![Vec::with_capacity(capacity.max(1))]),
+        }
+    }
+
+    // 每次调用都返回一块新的分配, 不会与其他引用重叠
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: T) -> &mut T {
+        let mut chunks = self.chunks.borrow_mut();
+        let last = chunks.last_mut().unwrap();
+        if last.len() == last.capacity() {
+            let capacity = last.capacity().checked_mul(2).expect("arena 容量溢出");
+            chunks.push(Vec::with_capacity(capacity));
+        }
+        let chunk = chunks.last_mut().unwrap();
+        debug_assert!(chunk.len() < chunk.capacity());
+        chunk.push(value);
+        // SAFETY: push 没有超过容量, 不会重新分配, 元素的地址在 arena drop 之前不变;
+        // 之后只会向块的末尾追加, 不会再通过 Vec 访问这个元素, 返回的可变引用是唯一的
+        unsafe { &mut *chunk.as_mut_ptr().add(chunk.len() - 1) }
+    }
+
+    /// 已经分配的值的个数
+    pub fn len(&self) -> usize {
+        self.chunks.borrow().iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 按分配的顺序取出所有的值
+    pub fn into_vec(self) -> Vec<T> {
+        self.chunks.into_inner().into_iter().flatten().collect()
+    }
+}
+
+impl<T> Default for TypedArena<T> {
+    fn default() -> TypedArena<T> {
+        TypedArena::new()
+    }
+}
+
+/// 需要析构的值: 地址和对应类型的 drop_in_place
+struct DropEntry {
+    ptr: NonNull<u8>,
+    drop: unsafe fn(NonNull<u8>),
+}
+
+unsafe fn drop_as<T>(ptr: NonNull<u8>) {
+    ptr::drop_in_place(ptr.cast::<T>().as_ptr());
+}
+
+/// 离开作用域时析构剩余的值, 某个值的 drop 发生 panic 时同样会继续析构后面的值
+struct DropRemaining<'a>(std::slice::IterMut<'a, DropEntry>);
+
+impl Drop for DropRemaining<'_> {
+    fn drop(&mut self) {
+        while let Some(entry) = self.0.next() {
+            // 这次 drop 发生 panic 时, 展开过程中由 rest 继续析构剩余的值
+            let mut rest = DropRemaining(mem::take(&mut self.0));
+            // SAFETY: 每个 entry 只会被取出一次
+            unsafe { (entry.drop)(entry.ptr) };
+            self.0 = mem::take(&mut rest.0);
+        }
+    }
+}
+
+/// # Bump 可以存放任意类型的 arena
+///
+/// 值依次放在原始内存块中, 块不够时申请一个新的 (大小翻倍) 块, 旧的块不会释放也不会移动;
+/// 需要析构的值记录在 drops 中, arena drop 时按分配的顺序析构, 再释放所有的块
+///
+/// ! `alloc` 要求 `T: 'static`: Bump 不知道值的类型, 无法借助 drop 检查;
+/// 如果允许值引用 arena 中的其他值, 先析构的值可能在后析构的值的 Drop 中被读到.
+/// 借用了外部数据而且不需要析构的值使用 `alloc_copy`
+pub struct Bump {
+    /// 每个块的起始地址和布局
+    chunks: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    /// 当前块中下一个可用的地址和块的结束地址
+    next: Cell<usize>,
+    end: Cell<usize>,
+    drops: RefCell<Vec<DropEntry>>,
+    /// 所有块的总大小
+    allocated_bytes: Cell<usize>,
+}
+
+#[allow(unused)]
+impl Bump {
+    pub fn new() -> Bump {
+        Bump {
+            chunks: RefCell::new(Vec::new()),
+            next: Cell::new(0),
+            end: Cell::new(0),
+            drops: RefCell::new(Vec::new()),
+            allocated_bytes: Cell::new(0),
+        }
+    }
+
+    /// 把 value 移动到 arena 中, arena drop 时析构
+    // 每次调用都返回一块新的分配, 不会与其他引用重叠
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: 'static>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+        // SAFETY: ptr 满足 T 的大小和对齐, 没有被其他分配使用
+        unsafe { ptr.as_ptr().write(value) };
+        if mem::needs_drop::<T>() {
+            self.drops.borrow_mut().push(DropEntry {
+                ptr: ptr.cast(),
+                drop: drop_as::<T>,
+            });
+        }
+        // SAFETY: 内存在 arena drop 之前一直有效, 每次分配的地址都不同
+        unsafe { &mut *ptr.as_ptr() }
+    }
+
+    /// Copy 类型没有 Drop, 可以包含任意生命周期的引用
+    // 每次调用都返回一块新的分配, 不会与其他引用重叠
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_copy<T: Copy>(&self, value: T) -> &mut T {
+        let ptr = self.alloc_layout(Layout::new::<T>()).cast::<T>();
+        unsafe {
+            ptr.as_ptr().write(value);
+            &mut *ptr.as_ptr()
+        }
+    }
+
+    /// 复制一个切片, 比如字符串
+    // 每次调用都返回一块新的分配, 不会与其他引用重叠
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, slice: &[T]) -> &mut [T] {
+        let layout = Layout::for_value(slice);
+        let ptr = self.alloc_layout(layout).cast::<T>();
+        unsafe {
+            ptr::copy_nonoverlapping(slice.as_ptr(), ptr.as_ptr(), slice.len());
+            std::slice::from_raw_parts_mut(ptr.as_ptr(), slice.len())
+        }
+    }
+
+    // 每次调用都返回一块新的分配, 不会与其他引用重叠
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, s: &str) -> &mut str {
+        let bytes = self.alloc_slice_copy(s.as_bytes());
+        // SAFETY: 从 &str 复制而来, 是合法的 UTF-8
+        unsafe { std::str::from_utf8_unchecked_mut(bytes) }
+    }
+
+    /// 所有块的总大小 (字节)
+    pub fn allocated_bytes(&self) -> usize {
+        self.allocated_bytes.get()
+    }
+
+    /// 块的个数
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    /// 分配一段满足 layout 的内存
+    fn alloc_layout(&self, layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // 零大小的类型不占用内存, 任意对齐的非空地址都可以
+            return NonNull::new(layout.align() as *mut u8).unwrap();
+        }
+        if let Some(ptr) = self.try_alloc_in_current(layout) {
+            return ptr;
+        }
+        self.grow(layout);
+        self.try_alloc_in_current(layout)
+            .expect("新的块足够放下这次分配")
+    }
+
+    fn try_alloc_in_current(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let chunks = self.chunks.borrow();
+        let &(base, _) = chunks.last()?;
+        // 通过地址计算对齐, 再转换回块内的偏移, 使得到的指针保留块的来源 (provenance)
+        let next = self.next.get();
+        let start = next.checked_add(layout.align() - 1)? & !(layout.align() - 1);
+        let end = start.checked_add(layout.size())?;
+        if end > self.end.get() {
+            return None;
+        }
+        self.next.set(end);
+        let offset = start - base.as_ptr() as usize;
+        // SAFETY: [start, end) 位于当前块中
+        Some(unsafe { NonNull::new_unchecked(base.as_ptr().add(offset)) })
+    }
+
+    /// 申请一个新的块, 至少能放下 layout
+    fn grow(&self, layout: Layout) {
+        let mut chunks = self.chunks.borrow_mut();
+        let last_size = chunks
+            .last()
+            .map_or(FIRST_CHUNK_BYTES / 2, |(_, l)| l.size());
+        let size = (last_size * 2).max(layout.size() + layout.align());
+        let chunk_layout = Layout::from_size_align(size, layout.align().max(16)).expect("块太大");
+        // SAFETY: size 不为 0
+        let base = unsafe { alloc::alloc(chunk_layout) };
+        let base = NonNull::new(base).unwrap_or_else(|| alloc::handle_alloc_error(chunk_layout));
+        chunks.push((base, chunk_layout));
+        self.next.set(base.as_ptr() as usize);
+        self.end.set(base.as_ptr() as usize + size);
+        self.allocated_bytes.set(self.allocated_bytes.get() + size);
+    }
+}
+
+impl Default for Bump {
+    fn default() -> Bump {
+        Bump::new()
+    }
+}
+
+impl Drop for Bump {
+    fn drop(&mut self) {
+        // 先释放内存块: 放在守卫中, 析构值时发生 panic 也会执行
+        struct FreeChunks<'a>(&'a mut Vec<(NonNull<u8>, Layout)>);
+        impl Drop for FreeChunks<'_> {
+            fn drop(&mut self) {
+                for &(base, layout) in self.0.iter() {
+                    unsafe { alloc::dealloc(base.as_ptr(), layout) };
+                }
+            }
+        }
+        let _free = FreeChunks(self.chunks.get_mut());
+        // 局部变量按相反的顺序析构: 先析构所有的值, 再释放内存块
+        let _drops = DropRemaining(self.drops.get_mut().iter_mut());
+    }
+}
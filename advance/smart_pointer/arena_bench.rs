@@ -0,0 +1,158 @@
+/// Arena 与逐个 Box 分配的性能对比
+///
+/// 创建大量 Button / Select 组件, 遍历调用 draw, 再全部释放:
+/// - `Vec<Box<dyn Drawer>>`: 每个组件分配一次, 释放一次
+/// - `Bump`: 组件依次放在几个大块中, 只有块需要分配和释放
+/// - `TypedArena`: 每种组件一个 arena, 同类组件在内存中连续存放
+///
+/// 使用 `Instant` 计时, 结果和机器, 分配器有关, 只用于比较相对快慢;
+/// 请使用 `rustc -O` 编译后运行
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod arena;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use arena::{Bump, TypedArena};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[("example01", example01), ("example02", example02)])
+}
+
+/// 与 box.rs example02 相同的组件, draw 返回一个值而不是打印, 避免测量到 IO
+trait Drawer {
+    fn draw(&self) -> u64;
+}
+
+struct Button {
+    bid: u32,
+}
+
+impl Drawer for Button {
+    fn draw(&self) -> u64 {
+        self.bid as u64
+    }
+}
+
+struct Select {
+    sid: i32,
+    // 让两种组件的大小不同
+    options: [u16; 8],
+}
+
+impl Drawer for Select {
+    fn draw(&self) -> u64 {
+        self.sid.unsigned_abs() as u64 + self.options[0] as u64
+    }
+}
+
+fn select(i: usize) -> Select {
+    Select {
+        sid: -(i as i32),
+        options: [i as u16; 8],
+    }
+}
+
+/// 所有组件 draw 的和, 用于确认三种方式的结果相同
+fn expected(widgets: usize) -> u64 {
+    (0..widgets)
+        .map(|i| {
+            if i % 3 == 0 {
+                select(i).draw()
+            } else {
+                i as u64
+            }
+        })
+        .sum()
+}
+
+fn with_box(widgets: usize) -> u64 {
+    let components: Vec<Box<dyn Drawer>> = (0..widgets)
+        .map(|i| -> Box<dyn Drawer> {
+            if i % 3 == 0 {
+                Box::new(select(i))
+            } else {
+                Box::new(Button { bid: i as u32 })
+            }
+        })
+        .collect();
+    components.iter().map(|c| c.draw()).sum()
+}
+
+fn with_bump(widgets: usize) -> u64 {
+    let bump = Bump::new();
+    let components: Vec<&dyn Drawer> = (0..widgets)
+        .map(|i| -> &dyn Drawer {
+            if i % 3 == 0 {
+                bump.alloc(select(i))
+            } else {
+                bump.alloc(Button { bid: i as u32 })
+            }
+        })
+        .collect();
+    components.iter().map(|c| c.draw()).sum()
+}
+
+fn with_typed_arena(widgets: usize) -> u64 {
+    let buttons = TypedArena::new();
+    let selects = TypedArena::new();
+    let components: Vec<&dyn Drawer> = (0..widgets)
+        .map(|i| -> &dyn Drawer {
+            if i % 3 == 0 {
+                selects.alloc(select(i))
+            } else {
+                buttons.alloc(Button { bid: i as u32 })
+            }
+        })
+        .collect();
+    components.iter().map(|c| c.draw()).sum()
+}
+
+/// 运行 rounds 次, 返回总耗时
+fn measure(rounds: usize, widgets: usize, f: fn(usize) -> u64) -> Duration {
+    let expected = expected(widgets);
+    let start = Instant::now();
+    for _ in 0..rounds {
+        assert_eq!(f(black_box(widgets)), expected);
+    }
+    start.elapsed()
+}
+
+/// 依次运行三种方式并打印结果
+fn compare(rounds: usize, widgets: usize) -> [(&'static str, Duration); 3] {
+    let results = [
+        ("Box", measure(rounds, widgets, with_box)),
+        ("Bump", measure(rounds, widgets, with_bump)),
+        ("TypedArena", measure(rounds, widgets, with_typed_arena)),
+    ];
+    println!("{widgets} 个组件, {rounds} 轮");
+    let baseline = results[0].1.as_secs_f64();
+    for (name, elapsed) in &results {
+        let per_widget = elapsed.as_nanos() as f64 / (rounds * widgets) as f64;
+        println!(
+            "  {name:<10} {:>10.2?} {:>8.2} ns/组件 {:>6.2}x",
+            elapsed,
+            per_widget,
+            baseline / elapsed.as_secs_f64()
+        );
+    }
+    results
+}
+
+/// # example01 一千个组件
+#[allow(unused)]
+fn example01() {
+    compare(1_000, 1_000);
+}
+
+/// # example02 十万个组件
+/// 组件越多, Box 版本的分配和释放次数越多, 而 arena 只多申请几个块
+#[allow(unused)]
+fn example02() {
+    compare(20, 100_000);
+}
@@ -0,0 +1,234 @@
+/// Arena 分配器
+///
+/// TypedArena / Bump 的实现见 arena.rs, 与逐个 Box 分配的性能对比见 arena_bench.rs
+///
+/// 内存检查: `rustc +nightly -Zsanitizer=address arena_demo.rs`
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod arena;
+mod drop_tracer;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use arena::{Bump, TypedArena};
+use drop_tracer::DropLog;
+use std::cell::Cell;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 与 box.rs example02 相同的组件
+trait Drawer {
+    fn draw(&self) -> String;
+}
+
+struct Button {
+    bid: u32,
+}
+
+impl Drawer for Button {
+    fn draw(&self) -> String {
+        format!("这是屏幕上第 {} 个按钮", self.bid)
+    }
+}
+
+struct Select {
+    sid: i32,
+}
+
+impl Drawer for Select {
+    fn draw(&self) -> String {
+        format!("这个选择框贼难用 {}", self.sid)
+    }
+}
+
+/// # example01 用 arena 存放特征对象
+/// box.rs example02 中的 `Vec<Box<dyn Drawer>>` 换成 `Vec<&dyn Drawer>`, 组件都放在 arena 中
+#[allow(unused)]
+fn example01() {
+    // Bump 可以存放不同的类型
+    let bump = Bump::new();
+    let components: Vec<&dyn Drawer> = vec![
+        bump.alloc(Button { bid: 1 }),
+        bump.alloc(Select { sid: 2 }),
+        bump.alloc(Button { bid: 3 }),
+    ];
+    let drawn: Vec<String> = components.iter().map(|c| c.draw()).collect();
+    println!("{drawn:?}");
+    assert_eq!(drawn[1], "这个选择框贼难用 2");
+    // 三个组件放在同一个块中
+    assert_eq!(bump.chunk_count(), 1);
+
+    // TypedArena 每种类型一个, 引用同样可以转换为 &dyn Drawer
+    let buttons = TypedArena::new();
+    let selects = TypedArena::new();
+    let mut components: Vec<&dyn Drawer> = Vec::new();
+    for i in 0..10 {
+        if i % 2 == 0 {
+            components.push(buttons.alloc(Button { bid: i }));
+        } else {
+            components.push(selects.alloc(Select { sid: -(i as i32) }));
+        }
+    }
+    assert_eq!((buttons.len(), selects.len()), (5, 5));
+    assert_eq!(components[9].draw(), "这个选择框贼难用 -9");
+
+    // 分配得到的是 &mut, 可以修改; 多次分配的引用可以同时存在
+    let a = buttons.alloc(Button { bid: 100 });
+    let b = buttons.alloc(Button { bid: 200 });
+    a.bid += b.bid;
+    assert_eq!(a.draw(), "这是屏幕上第 300 个按钮");
+
+    // 引用借用了 arena, 引用存在期间无法 drop arena
+    // drop(bump); // ! cannot move out of `bump` because it is borrowed
+    // println!("{}", components[0].draw());
+}
+
+/// # example02 arena drop 时析构所有的值
+/// 按分配的顺序析构, 只有需要析构的值会被记录
+#[allow(unused)]
+fn example02() {
+    let log = DropLog::new();
+    {
+        let bump = Bump::new();
+        bump.alloc(log.tracer("first"));
+        // 不需要析构的值不会被记录
+        bump.alloc(42u64);
+        let boxed = bump.alloc(Box::new(log.tracer("boxed")));
+        bump.alloc(vec![log.tracer("v[0]"), log.tracer("v[1]")]);
+        log.record("arena in use");
+        assert_eq!(boxed.label(), "boxed");
+    }
+    assert_eq!(
+        log.take(),
+        ["arena in use", "first", "boxed", "v[0]", "v[1]"]
+    );
+
+    // TypedArena 同样按分配的顺序, 跨越多个块
+    let arena = TypedArena::with_capacity(1);
+    for i in 0..5 {
+        arena.alloc(log.tracer(format!("t{i}")));
+    }
+    drop(arena);
+    assert_eq!(log.take(), ["t0", "t1", "t2", "t3", "t4"]);
+
+    // 不想析构时可以取出所有的值
+    let arena = TypedArena::new();
+    arena.alloc(log.tracer("kept"));
+    let values = arena.into_vec();
+    assert!(log.labels().is_empty());
+    drop(values);
+    assert_eq!(log.take(), ["kept"]);
+}
+
+/// # example03 互相引用的值
+/// 所有的值的生命周期相同, 可以用普通的引用组成环, 不需要 Rc / Weak (对比 rc_leaks.rs)
+#[allow(unused)]
+fn example03() {
+    struct Node<'a> {
+        name: &'a str,
+        next: Cell<Option<&'a Node<'a>>>,
+    }
+
+    let names = Bump::new();
+    let nodes = TypedArena::new();
+    let new_node = |i: usize| -> &Node {
+        // 字符串也复制到 arena 中
+        let name = names.alloc_str(&format!("n{i}"));
+        nodes.alloc(Node {
+            name,
+            next: Cell::new(None),
+        })
+    };
+
+    // n0 -> n1 -> ... -> n99 -> n0
+    let ring: Vec<&Node> = (0..100).map(new_node).collect();
+    for (i, node) in ring.iter().enumerate() {
+        node.next.set(Some(ring[(i + 1) % ring.len()]));
+    }
+
+    let mut current = ring[98];
+    for _ in 0..3 {
+        current = current.next.get().unwrap();
+    }
+    assert_eq!(current.name, "n1");
+    // 不存在引用计数, 不会泄漏, arena drop 时一起释放
+    assert_eq!(nodes.len(), 100);
+
+    // 如果 Node 实现了 Drop 并在其中访问 next, drop 检查会拒绝这段代码:
+    // 析构 nodes 时, next 指向的值可能已经被析构
+}
+
+/// # example04 对齐, 零大小类型与大对象
+#[allow(unused)]
+fn example04() {
+    #[repr(align(64))]
+    struct CacheLine([u8; 64]);
+
+    let bump = Bump::new();
+    let byte = bump.alloc(1u8);
+    let line = bump.alloc(CacheLine([7; 64]));
+    let word = bump.alloc(2u64);
+    assert_eq!(line as *const CacheLine as usize % 64, 0);
+    assert_eq!(word as *const u64 as usize % 8, 0);
+    assert_eq!((*byte, line.0[63], *word), (1, 7, 2));
+
+    // 零大小的类型不占用内存
+    let before = bump.allocated_bytes();
+    for _ in 0..1000 {
+        bump.alloc(());
+    }
+    assert_eq!(bump.allocated_bytes(), before);
+
+    // 超过当前块的对象会申请一个足够大的新块
+    let big = bump.alloc([1u64; 4096]);
+    assert_eq!(big.iter().sum::<u64>(), 4096);
+    assert_eq!(bump.chunk_count(), 2);
+    // 之前的引用依然有效
+    assert_eq!(line.0[0], 7);
+
+    // 借用了外部数据的 Copy 值可以放入 Bump, 需要析构的则不行 (要求 'static)
+    let text = String::from("外部数据");
+    let borrowed = bump.alloc_copy(text.as_str());
+    assert_eq!(*borrowed, "外部数据");
+    // bump.alloc(vec![text.as_str()]); // ! `text` does not live long enough
+    println!(
+        "{} 个块, 共 {} 字节",
+        bump.chunk_count(),
+        bump.allocated_bytes()
+    );
+}
+
+/// # example05 析构时 panic
+/// 一个值的 drop 发生 panic, 剩余的值依然会被析构, 内存块也会被释放
+#[allow(unused)]
+fn example05() {
+    let log = DropLog::new();
+    let result = panic_boundary::catch_panic(|| {
+        let bump = Bump::new();
+        bump.alloc(log.tracer("a"));
+        bump.alloc(log.tracer("b").panicking());
+        bump.alloc(log.tracer("c"));
+    });
+    assert!(result.is_err());
+    assert_eq!(log.take(), ["a", "b", "c"]);
+
+    // 放入 arena 之后再 panic, 值同样会被析构
+    let result = panic_boundary::catch_panic(|| {
+        let bump = Bump::new();
+        bump.alloc(log.tracer("allocated"));
+        panic!("使用 arena 时 panic");
+    });
+    assert!(result.is_err());
+    assert_eq!(log.take(), ["allocated"]);
+}
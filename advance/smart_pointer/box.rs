@@ -1,8 +1,14 @@
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
 
 /// 进阶之 Box 堆对象分配
-fn main() {
-    example01();
-    example02();
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+    ])
 }
 
 /// # example01 使用 Box<T> 将数据存储在堆上
@@ -44,7 +50,8 @@ fn example02() {
         }
     }
 
-    // 使用 vec 存放多个特征对象
+    // 使用 vec 存放多个特征对象, 每个元素单独分配一次堆内存
+    // 把组件放在 arena 中统一分配的版本见 arena_demo.rs example01
     let elements: Vec<Box<dyn Drawer>> = vec![
         Box::new(Select { sid: 101 }),
         Box::new(Button { bid: 1 }),
@@ -1,9 +1,16 @@
 
 // 进阶之 Deref 解引用
-fn main() {
-    example01();
-    example02();
-    example03();
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+    ])
 }
 
 /// # example01 通过 * 获取引用背后的值
@@ -47,6 +54,7 @@ fn example03() {
 }
 
 /// # example04 改进自定义的智能指针
+/// 这里的 GoodBox 只是把值包了一层, 值依然在栈上; 真正把值放到堆上的完整实现见 good_box.rs
 #[allow(unused)]
 fn example04() {
     // 自定义自己的智能指针
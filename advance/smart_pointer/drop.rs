@@ -1,8 +1,19 @@
 
 // 进阶之 Drop 释放资源
-fn main() {
-    example01();
-    example02();
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod drop_tracer;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use drop_tracer::DropLog;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+    ])
 }
 
 /// # example01 手动回收内存
@@ -27,6 +38,7 @@ fn example01() {
     // 其原理是移走目标值的所有权
     // 源码: `pub fn drop<T>(_x: T) {}`
     drop(foo);
+    // 利用 Drop 在离开作用域时执行清理 (删除临时文件, 释放锁, 回滚) 见 scope_guard.rs
 }
 
 /// # example02 Copy 和 Drop 是互斥的!
@@ -36,4 +48,20 @@ fn example02() {
     // ! Copy 特征是在栈上按位复制操作的, 而不是在堆上
     // ! 所以不能为实现了 Copy 特征的类型实现 Drop 特征
     // ! 所以实现了 Drop 特征调用 drop 是没有意义的
-}
\ No newline at end of file
+}
+
+/// # example03 观察 drop 的顺序
+/// 用 DropLog 记录析构的顺序, 而不是在 drop 中 println!; 更多示例见 drop_order.rs
+#[allow(unused)]
+fn example03() {
+    let log = DropLog::new();
+    {
+        let a = log.tracer("a");
+        let b = log.tracer("b");
+        // 手动 drop 的值立即析构
+        let c = log.tracer("c");
+        drop(c);
+    }
+    // 其余的按声明的相反顺序析构
+    assert_eq!(log.labels(), ["c", "b", "a"]);
+}
@@ -0,0 +1,214 @@
+/// 析构顺序
+///
+/// 每个示例都通过 DropLog 断言准确的析构顺序, DropTracer 的实现见 drop_tracer.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod drop_tracer;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use drop_tracer::{DropEvent, DropLog, DropTracer};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// # example01 结构体与局部变量
+/// 与 mixed.rs 的 drop_example01 相同:
+/// 1. 局部变量按声明的相反顺序析构
+/// 2. 结构体先调用自己的 Drop, 再按字段声明的顺序 (而不是初始化的顺序) 析构字段
+#[allow(unused)]
+fn example01() {
+    struct HasTwoDrops {
+        log: DropLog,
+        one: DropTracer,
+        two: DropTracer,
+    }
+    impl Drop for HasTwoDrops {
+        fn drop(&mut self) {
+            self.log.record("HasTwoDrops");
+        }
+    }
+
+    let log = DropLog::new();
+    {
+        let _x = HasTwoDrops {
+            two: log.tracer("HasDrop2"),
+            one: log.tracer("HasDrop1"),
+            log: log.clone(),
+        };
+        let _foo = log.tracer("Foo");
+        log.record("Running!");
+    }
+    print!("{log}");
+    assert_eq!(
+        log.labels(),
+        ["Running!", "Foo", "HasTwoDrops", "HasDrop1", "HasDrop2"]
+    );
+}
+
+/// # example02 元组, 数组, Vec, Box 与嵌套
+/// 容器中的元素都按从前往后的顺序析构
+#[allow(unused)]
+fn example02() {
+    let log = DropLog::new();
+
+    let tuple = (log.tracer("t.0"), log.tracer("t.1"), log.tracer("t.2"));
+    drop(tuple);
+    assert_eq!(log.take(), ["t.0", "t.1", "t.2"]);
+
+    let array = [log.tracer("a[0]"), log.tracer("a[1]")];
+    let vec = vec![log.tracer("v[0]"), log.tracer("v[1]")];
+    let boxed = Box::new(log.tracer("box"));
+    drop((array, vec, boxed));
+    assert_eq!(log.take(), ["a[0]", "a[1]", "v[0]", "v[1]", "box"]);
+
+    // 被其他值拥有的值在拥有者之后析构, 深度 + 1
+    let tree = log.tracer_with(
+        "root",
+        vec![
+            log.tracer_with("left", vec![log.tracer("left.leaf")]),
+            log.tracer("right"),
+        ],
+    );
+    drop(tree);
+    print!("{log}");
+    let depth = |label: &str, depth| DropEvent {
+        label: label.to_string(),
+        depth,
+    };
+    assert_eq!(
+        log.events(),
+        [
+            depth("root", 0),
+            depth("left", 1),
+            depth("left.leaf", 2),
+            depth("right", 1)
+        ]
+    );
+}
+
+/// # example03 枚举, Option, 赋值与临时值
+#[allow(unused)]
+fn example03() {
+    #[allow(dead_code)]
+    enum Shape {
+        Point,
+        Line(DropTracer, DropTracer),
+        Named { name: DropTracer },
+    }
+
+    let log = DropLog::new();
+
+    // 只析构当前变体中的字段
+    drop(Shape::Line(log.tracer("start"), log.tracer("end")));
+    drop(Shape::Point);
+    drop(Shape::Named {
+        name: log.tracer("name"),
+    });
+    assert_eq!(log.take(), ["start", "end", "name"]);
+
+    // 重新赋值时旧的值立即析构
+    let mut slot = Some(log.tracer("first"));
+    slot = Some(log.tracer("second"));
+    assert_eq!(log.take(), ["first"]);
+    // take 把值移出, Option 中不再有值
+    let taken = slot.take();
+    slot = None;
+    assert!(log.labels().is_empty());
+    drop(taken);
+    assert_eq!(log.take(), ["second"]);
+
+    // `let _ = ` 不绑定, 值立即析构; `let _x = ` 绑定, 到作用域结束才析构
+    {
+        let _ = log.tracer("underscore");
+        let _x = log.tracer("_x");
+        log.record("scope end");
+    }
+    assert_eq!(log.take(), ["underscore", "scope end", "_x"]);
+
+    // 临时值在语句结束时析构
+    let len = log.tracer("temporary").label().len();
+    log.record("next statement");
+    assert_eq!(log.take(), ["temporary", "next statement"]);
+}
+
+/// # example04 被移走的字段与闭包
+/// 值被移走之后由新的拥有者负责析构, 原来的位置不会再析构一次
+#[allow(unused)]
+fn example04() {
+    struct Pair {
+        left: DropTracer,
+        right: DropTracer,
+    }
+
+    let log = DropLog::new();
+    {
+        let pair = Pair {
+            left: log.tracer("left"),
+            right: log.tracer("right"),
+        };
+        // 移走一个字段 (Pair 没有实现 Drop, 才允许部分移动)
+        let left = pair.left;
+        drop(left);
+        log.record("moved left dropped");
+        // pair 中剩下的 right 在作用域结束时析构
+    }
+    assert_eq!(log.take(), ["left", "moved left dropped", "right"]);
+
+    // move 闭包拥有捕获的值, 闭包析构时才析构
+    let captured = log.tracer("captured");
+    let closure = move || captured.label().len();
+    closure();
+    log.record("closure called");
+    drop(closure);
+    assert_eq!(log.take(), ["closure called", "captured"]);
+
+    // 函数参数在函数返回时析构, 顺序与局部变量相同: 后声明的先析构
+    fn consume(_a: DropTracer, _b: DropTracer) {}
+    consume(log.tracer("arg a"), log.tracer("arg b"));
+    assert_eq!(log.take(), ["arg b", "arg a"]);
+}
+
+/// # example05 drop 中的 panic 与 mem::forget
+#[allow(unused)]
+fn example05() {
+    let log = DropLog::new();
+
+    // 一个元素的 drop 发生 panic, 后面的元素依然会被析构
+    let vec = vec![
+        log.tracer("v[0]"),
+        log.tracer("v[1]").panicking(),
+        log.tracer("v[2]"),
+    ];
+    let result = panic_boundary::catch_panic(|| drop(vec));
+    assert!(result.is_err());
+    assert_eq!(log.take(), ["v[0]", "v[1]", "v[2]"]);
+
+    // 子节点 panic 时, 其他子节点同样会被析构, 深度也会恢复
+    let tree = log.tracer_with(
+        "parent",
+        vec![log.tracer("child a").panicking(), log.tracer("child b")],
+    );
+    assert!(panic_boundary::catch_panic(|| drop(tree)).is_err());
+    log.record("after");
+    assert_eq!(log.events().last().unwrap().depth, 0);
+    assert_eq!(log.take(), ["parent", "child a", "child b", "after"]);
+
+    // mem::forget 不会调用 drop, 值拥有的资源也不会释放
+    std::mem::forget(log.tracer("forgotten"));
+    // ManuallyDrop 同样不会自动析构, 需要时手动调用
+    let mut manual = std::mem::ManuallyDrop::new(log.tracer("manual"));
+    log.record("before manual drop");
+    unsafe { std::mem::ManuallyDrop::drop(&mut manual) };
+    assert_eq!(log.take(), ["before manual drop", "manual"]);
+}
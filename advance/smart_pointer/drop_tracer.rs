@@ -0,0 +1,148 @@
+/// Drop 顺序记录
+///
+/// drop.rs 与 mixed.rs 中通过 Drop 里的 println! 观察析构顺序, 只能用眼睛看;
+/// 这里把每次析构记录到一个共享的日志中, 示例可以直接断言顺序
+///
+/// - `DropLog`: 日志, 克隆得到的是同一份日志
+/// - `DropTracer`: drop 时把自己的标签写入日志, 可以带子节点 (在自己之后, 以更深一层的深度析构)
+/// - 自己实现的 Drop 中也可以调用 `log.record(label)` 写入日志
+///
+/// 深度表示记录时有多少个 DropTracer 的 drop 正在执行, 即析构的嵌套层数
+#[allow(unused)]
+struct Description;
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+
+/// 一次析构
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DropEvent {
+    pub label: String,
+    pub depth: usize,
+}
+
+/// # DropLog 共享的析构日志
+#[derive(Debug, Clone, Default)]
+pub struct DropLog {
+    events: Rc<RefCell<Vec<DropEvent>>>,
+    depth: Rc<Cell<usize>>,
+}
+
+#[allow(unused)]
+impl DropLog {
+    pub fn new() -> DropLog {
+        DropLog::default()
+    }
+
+    /// 创建一个 drop 时写入这份日志的 DropTracer
+    pub fn tracer(&self, label: impl Into<String>) -> DropTracer {
+        DropTracer {
+            label: label.into(),
+            log: self.clone(),
+            children: Vec::new(),
+            panic_on_drop: false,
+        }
+    }
+
+    /// 带子节点的 DropTracer, drop 时先记录自己, 再按顺序析构子节点
+    pub fn tracer_with(&self, label: impl Into<String>, children: Vec<DropTracer>) -> DropTracer {
+        DropTracer {
+            label: label.into(),
+            log: self.clone(),
+            children,
+            panic_on_drop: false,
+        }
+    }
+
+    /// 以当前深度写入一条记录
+    pub fn record(&self, label: impl Into<String>) {
+        self.events.borrow_mut().push(DropEvent {
+            label: label.into(),
+            depth: self.depth.get(),
+        });
+    }
+
+    /// 所有的记录
+    pub fn events(&self) -> Vec<DropEvent> {
+        self.events.borrow().clone()
+    }
+
+    /// 按顺序排列的标签
+    pub fn labels(&self) -> Vec<String> {
+        self.events
+            .borrow()
+            .iter()
+            .map(|e| e.label.clone())
+            .collect()
+    }
+
+    /// 取出所有的标签并清空日志
+    pub fn take(&self) -> Vec<String> {
+        let labels = self.labels();
+        self.events.borrow_mut().clear();
+        labels
+    }
+}
+
+impl fmt::Display for DropLog {
+    /// 每条记录一行, 按深度缩进
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in self.events.borrow().iter() {
+            writeln!(f, "{}{}", "  ".repeat(event.depth), event.label)?;
+        }
+        Ok(())
+    }
+}
+
+/// # DropTracer drop 时写入日志
+pub struct DropTracer {
+    label: String,
+    log: DropLog,
+    children: Vec<DropTracer>,
+    panic_on_drop: bool,
+}
+
+#[allow(unused)]
+impl DropTracer {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// drop 时写入日志后 panic, 用于观察析构期间 panic 时其他值是否还会被析构
+    pub fn panicking(mut self) -> DropTracer {
+        self.panic_on_drop = true;
+        self
+    }
+}
+
+impl fmt::Debug for DropTracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DropTracer({})", self.label)
+    }
+}
+
+/// 离开作用域时恢复深度, 子节点的 drop 发生 panic 时同样会恢复
+struct DepthGuard<'a>(&'a Cell<usize>);
+
+impl Drop for DepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() - 1);
+    }
+}
+
+impl Drop for DropTracer {
+    fn drop(&mut self) {
+        self.log.record(self.label.as_str());
+        {
+            let depth = &self.log.depth;
+            depth.set(depth.get() + 1);
+            let _guard = DepthGuard(depth);
+            // 子节点在自己的 drop 中析构, 所以深度 + 1
+            self.children.clear();
+        }
+        if self.panic_on_drop {
+            panic!("{} 的 drop 发生 panic", self.label);
+        }
+    }
+}
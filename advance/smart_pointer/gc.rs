@@ -0,0 +1,413 @@
+/// 标记-清除垃圾回收 Gc<T>
+///
+/// Rc 无法回收循环引用 (见 ac_and_arc.rs example06 与 rc_leaks.rs), 追踪式的垃圾回收可以:
+/// 不再计数, 而是从根出发找出所有还能访问到的对象, 其余的全部释放
+///
+/// - `Heap`: 保存所有对象, 单线程
+/// - `Gc<T>`: 指向堆中对象的句柄, 可以复制, 可以保存在其他对象中形成任意的图 (包括环)
+/// - `Root<T>`: 根, 只要 Root (或它的克隆) 还存在, 对象以及从它出发能访问到的对象都不会被回收
+/// - `Trace`: 列出对象中的 Gc, 用 `derive_trace!` 为结构体生成
+/// - `collect()`: 标记从根出发能访问到的对象, 释放其余的对象, 每个对象的 Drop 只会执行一次
+///
+/// 与真正的 Gc 不同, 访问对象需要通过 `heap.get(gc)`, 返回的引用借用了 heap,
+/// 于是在引用存在期间无法调用 `heap.collect()` (需要 &mut), 不会访问到已经释放的对象;
+/// 句柄带有代数 (generation), 对象被回收后再访问会 panic, 而不是读到复用了同一位置的其他对象
+///
+/// 只有显式调用 collect 才会回收, alloc 不会触发回收
+#[allow(unused)]
+struct Description;
+
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::{Rc, Weak};
+
+/// # Trace 列出对象直接持有的 Gc
+pub trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+/// # Tracer 标记阶段收集对象的边
+pub struct Tracer {
+    pending: Vec<usize>,
+}
+
+#[allow(unused)]
+impl Tracer {
+    pub fn edge<T>(&mut self, gc: &Gc<T>) {
+        self.pending.push(gc.index);
+    }
+}
+
+/// 为结构体实现 Trace, 依次 trace 每个字段
+///
+/// ```ignore
+/// derive_trace! {
+///     #[derive(Debug)]
+///     struct Node {
+///         name: String,
+///         next: Option<Gc<Node>>,
+///     }
+/// }
+/// ```
+///
+/// 每个字段都需要实现 Trace; 不包含 Gc 又没有实现 Trace 的字段用 `Untraced<T>` 包装
+#[allow(unused_macros)]
+macro_rules! derive_trace {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $ty),*
+        }
+
+        impl $crate::gc::Trace for $name {
+            #[allow(unused_variables)]
+            fn trace(&self, tracer: &mut $crate::gc::Tracer) {
+                $($crate::gc::Trace::trace(&self.$field, tracer);)*
+            }
+        }
+    };
+}
+#[allow(unused_imports)]
+pub(crate) use derive_trace;
+
+/// # Untraced 不参与 trace 的字段
+///
+/// ! 其中不能包含 Gc, 否则 Gc 指向的对象可能在仍被使用时被回收 (之后访问会 panic)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Untraced<T>(pub T);
+
+impl<T> Trace for Untraced<T> {
+    fn trace(&self, _tracer: &mut Tracer) {}
+}
+
+impl<T> std::ops::Deref for Untraced<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T> Trace for Gc<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.edge(self);
+    }
+}
+
+impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(value) = self {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for Vec<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace + ?Sized> Trace for Box<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        (**self).trace(tracer);
+    }
+}
+
+/// 不包含 Gc 的类型
+macro_rules! impl_trace_leaf {
+    ($($ty:ty),*) => {
+        $(
+            impl Trace for $ty {
+                fn trace(&self, _tracer: &mut Tracer) {}
+            }
+        )*
+    };
+}
+impl_trace_leaf!(
+    bool,
+    char,
+    i32,
+    i64,
+    u32,
+    u64,
+    usize,
+    f64,
+    String,
+    &'static str
+);
+
+/// # Gc 堆中对象的句柄
+pub struct Gc<T> {
+    index: usize,
+    generation: u32,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for Gc<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Gc<T> {}
+
+impl<T> PartialEq for Gc<T> {
+    /// 是否指向同一个对象
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for Gc<T> {}
+
+impl<T> fmt::Debug for Gc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Gc({}#{})", self.index, self.generation)
+    }
+}
+
+/// # Root 根
+///
+/// 克隆 Root 得到同一个根, 所有克隆都 drop 后对象才不再是根
+pub struct Root<T> {
+    gc: Gc<T>,
+    _token: Rc<()>,
+}
+
+impl<T> Clone for Root<T> {
+    fn clone(&self) -> Self {
+        Root {
+            gc: self.gc,
+            _token: Rc::clone(&self._token),
+        }
+    }
+}
+
+#[allow(unused)]
+impl<T> Root<T> {
+    pub fn gc(&self) -> Gc<T> {
+        self.gc
+    }
+}
+
+impl<T> fmt::Debug for Root<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Root({:?})", self.gc)
+    }
+}
+
+/// 堆中保存的对象, 通过 Any 还原为具体类型
+trait Object: Trace {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Trace + 'static> Object for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+struct Slot {
+    /// 每次回收后加 1, 旧的句柄因此失效
+    generation: u32,
+    object: Option<Box<dyn Object>>,
+    marked: bool,
+}
+
+/// # Stats 分配统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// 累计分配的对象数
+    pub allocated: usize,
+    /// 累计回收的对象数
+    pub freed: usize,
+    /// 当前存活的对象数
+    pub live: usize,
+    /// 当前存活对象占用的字节数 (不含 Box 之外的堆内存, 如 String 的内容)
+    pub live_bytes: usize,
+    /// 执行 collect 的次数
+    pub collections: usize,
+}
+
+/// 一次 collect 的结果
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Collected {
+    /// 标记为可访问的对象数
+    pub marked: usize,
+    /// 回收的对象数
+    pub freed: usize,
+    /// 回收的字节数
+    pub freed_bytes: usize,
+}
+
+/// # Heap 垃圾回收堆
+#[derive(Default)]
+pub struct Heap {
+    slots: Vec<Slot>,
+    /// 空闲的位置, 分配时优先复用
+    free: Vec<usize>,
+    /// 每个根的位置, Weak 失效说明这个根的所有 Root 都已经 drop
+    roots: Vec<(usize, Weak<()>)>,
+    stats: Stats,
+}
+
+#[allow(unused)]
+impl Heap {
+    pub fn new() -> Heap {
+        Heap::default()
+    }
+
+    /// 分配一个对象
+    ///
+    /// ! 返回的 Gc 不是根, 下一次 collect 之前需要用 root 保护起来, 或者保存到能从根访问到的对象中
+    pub fn alloc<T: Trace + 'static>(&mut self, value: T) -> Gc<T> {
+        let object: Box<dyn Object> = Box::new(value);
+        self.stats.allocated += 1;
+        self.stats.live += 1;
+        self.stats.live_bytes += std::mem::size_of_val(&*object);
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index].object = Some(object);
+                index
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    object: Some(object),
+                    marked: false,
+                });
+                self.slots.len() - 1
+            }
+        };
+        Gc {
+            index,
+            generation: self.slots[index].generation,
+            _type: PhantomData,
+        }
+    }
+
+    /// 分配一个对象并作为根
+    pub fn alloc_root<T: Trace + 'static>(&mut self, value: T) -> Root<T> {
+        let gc = self.alloc(value);
+        self.root(gc)
+    }
+
+    /// 把对象作为根
+    pub fn root<T>(&mut self, gc: Gc<T>) -> Root<T> {
+        assert!(self.is_alive(gc), "Gc 已经被回收: {gc:?}");
+        let token = Rc::new(());
+        self.roots.push((gc.index, Rc::downgrade(&token)));
+        Root { gc, _token: token }
+    }
+
+    pub fn is_alive<T>(&self, gc: Gc<T>) -> bool {
+        self.slots
+            .get(gc.index)
+            .is_some_and(|slot| slot.generation == gc.generation && slot.object.is_some())
+    }
+
+    pub fn try_get<T: 'static>(&self, gc: Gc<T>) -> Option<&T> {
+        if !self.is_alive(gc) {
+            return None;
+        }
+        let object = self.slots[gc.index].object.as_deref()?;
+        // 同一代的句柄一定由 alloc::<T> 返回, 类型一定匹配
+        object.as_any().downcast_ref::<T>()
+    }
+
+    /// 访问对象, 对象已经被回收时 panic
+    pub fn get<T: 'static>(&self, gc: Gc<T>) -> &T {
+        self.try_get(gc)
+            .unwrap_or_else(|| panic!("Gc 已经被回收: {gc:?}"))
+    }
+
+    /// 修改对象, 对象已经被回收时 panic
+    pub fn get_mut<T: 'static>(&mut self, gc: Gc<T>) -> &mut T {
+        if !self.is_alive(gc) {
+            panic!("Gc 已经被回收: {gc:?}");
+        }
+        self.slots[gc.index]
+            .object
+            .as_deref_mut()
+            .and_then(|object| object.as_any_mut().downcast_mut::<T>())
+            .unwrap()
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    /// 标记-清除
+    pub fn collect(&mut self) -> Collected {
+        self.stats.collections += 1;
+
+        // 标记: 从仍然存在的根出发, 用栈代替递归, 很长的链表也不会栈溢出
+        self.roots.retain(|(_, token)| token.strong_count() > 0);
+        let mut tracer = Tracer {
+            pending: self.roots.iter().map(|(index, _)| *index).collect(),
+        };
+        let mut marked = 0;
+        while let Some(index) = tracer.pending.pop() {
+            let slot = &mut self.slots[index];
+            if slot.marked {
+                continue;
+            }
+            let Some(object) = &slot.object else {
+                continue;
+            };
+            slot.marked = true;
+            marked += 1;
+            object.trace(&mut tracer);
+        }
+
+        // 清除: 先把未标记的对象全部取出, 再统一 drop
+        let mut garbage = Vec::new();
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if std::mem::take(&mut slot.marked) {
+                continue;
+            }
+            if let Some(object) = slot.object.take() {
+                slot.generation += 1;
+                self.free.push(index);
+                garbage.push(object);
+            }
+        }
+        let freed_bytes = garbage
+            .iter()
+            .map(|object| std::mem::size_of_val(&**object))
+            .sum();
+        let freed = garbage.len();
+        self.stats.freed += freed;
+        self.stats.live -= freed;
+        self.stats.live_bytes -= freed_bytes;
+        // 对象的 Drop 拿不到 Heap, 不会访问其他对象, drop 的顺序无关紧要
+        drop(garbage);
+
+        Collected {
+            marked,
+            freed,
+            freed_bytes,
+        }
+    }
+}
+
+impl fmt::Debug for Heap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heap")
+            .field("slots", &self.slots.len())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
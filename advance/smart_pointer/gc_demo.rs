@@ -0,0 +1,224 @@
+/// 标记-清除垃圾回收
+///
+/// Gc / Heap 的实现见 gc.rs
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod gc;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use gc::{derive_trace, Gc, Heap, Untraced};
+use std::cell::Cell;
+use std::rc::Rc;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// drop 时给计数加 1
+struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+derive_trace! {
+    /// 可以指向任意多个其他节点的图节点
+    struct Node {
+        name: String,
+        edges: Vec<Gc<Node>>,
+        // DropCounter 不包含 Gc, 不需要 trace
+        counter: Untraced<DropCounter>,
+    }
+}
+
+fn node(heap: &mut Heap, name: &str, drops: &Rc<Cell<usize>>) -> Gc<Node> {
+    heap.alloc(Node {
+        name: name.to_string(),
+        edges: Vec::new(),
+        counter: Untraced(DropCounter(Rc::clone(drops))),
+    })
+}
+
+/// # example01 根与回收
+/// 没有被根 (直接或间接) 引用的对象会在 collect 时被回收
+#[allow(unused)]
+fn example01() {
+    let drops = Rc::new(Cell::new(0));
+    let mut heap = Heap::new();
+
+    let kept = node(&mut heap, "kept", &drops);
+    let root = heap.root(kept);
+    let temp = node(&mut heap, "temp", &drops);
+    assert_eq!(heap.stats().live, 2);
+
+    let collected = heap.collect();
+    assert_eq!((collected.marked, collected.freed), (1, 1));
+    assert_eq!(drops.get(), 1);
+    assert_eq!(heap.get(root.gc()).name, "kept");
+    // 已经回收的对象无法再访问
+    assert!(heap.try_get(temp).is_none());
+
+    // 根的所有克隆都 drop 之后对象才会被回收
+    let another = root.clone();
+    drop(root);
+    heap.collect();
+    assert_eq!(drops.get(), 1);
+    drop(another);
+    heap.collect();
+    assert_eq!(drops.get(), 2);
+
+    let stats = heap.stats();
+    println!("{stats:?}");
+    assert_eq!((stats.allocated, stats.freed, stats.live), (2, 2, 0));
+    assert_eq!(stats.live_bytes, 0);
+}
+
+/// # example02 回收循环引用
+/// 同样的 a <-> b 用 Rc 会泄漏 (见 ac_and_arc.rs example06), Gc 可以回收
+#[allow(unused)]
+fn example02() {
+    let drops = Rc::new(Cell::new(0));
+    let mut heap = Heap::new();
+
+    let a = node(&mut heap, "a", &drops);
+    let b = node(&mut heap, "b", &drops);
+    heap.get_mut(a).edges.push(b);
+    heap.get_mut(b).edges.push(a);
+    // 自己指向自己
+    heap.get_mut(b).edges.push(b);
+
+    let root = heap.root(a);
+    assert_eq!(heap.collect().freed, 0);
+    let next = heap.get(root.gc()).edges[0];
+    assert_eq!(heap.get(next).name, "b");
+
+    drop(root);
+    let collected = heap.collect();
+    assert_eq!(collected.freed, 2);
+    // 每个对象的 Drop 只执行一次
+    assert_eq!(drops.get(), 2);
+    assert_eq!(heap.collect().freed, 0);
+    assert_eq!(drops.get(), 2);
+}
+
+/// # example03 父子双向指针的树
+/// 用 Rc 时需要 Weak 指向父节点, Gc 可以直接双向引用
+#[allow(unused)]
+fn example03() {
+    derive_trace! {
+        struct TreeNode {
+            name: &'static str,
+            parent: Option<Gc<TreeNode>>,
+            children: Vec<Gc<TreeNode>>,
+        }
+    }
+
+    fn add(heap: &mut Heap, name: &'static str, parent: Option<Gc<TreeNode>>) -> Gc<TreeNode> {
+        let node = heap.alloc(TreeNode {
+            name,
+            parent,
+            children: Vec::new(),
+        });
+        if let Some(parent) = parent {
+            heap.get_mut(parent).children.push(node);
+        }
+        node
+    }
+
+    let mut heap = Heap::new();
+    let root = heap.alloc_root(TreeNode {
+        name: "root",
+        parent: None,
+        children: Vec::new(),
+    });
+    let a = add(&mut heap, "a", Some(root.gc()));
+    add(&mut heap, "b", Some(root.gc()));
+    let c = add(&mut heap, "c", Some(a));
+
+    // 只有根节点是根, 整棵树都可以访问
+    assert_eq!(heap.collect().marked, 4);
+    let grandparent = heap.get(c).parent.and_then(|p| heap.get(p).parent);
+    assert_eq!(grandparent.map(|g| heap.get(g).name), Some("root"));
+
+    // 把 a 从树上摘下来, a 和 c 互相引用, 但已经无法从根访问
+    heap.get_mut(root.gc()).children.retain(|&child| child != a);
+    assert_eq!(heap.collect().freed, 2);
+    assert!(!heap.is_alive(c));
+
+    drop(root);
+    assert_eq!(heap.collect().freed, 2);
+    assert_eq!(heap.stats().live, 0);
+}
+
+/// # example04 大量对象组成的环
+/// 10000 个节点组成一个环, 每个节点再随机指向其他节点; 标记使用栈而不是递归, 不会栈溢出
+#[allow(unused)]
+fn example04() {
+    const N: usize = 10_000;
+    let drops = Rc::new(Cell::new(0));
+    {
+        let mut heap = Heap::new();
+        let nodes: Vec<Gc<Node>> = (0..N)
+            .map(|i| node(&mut heap, &format!("n{i}"), &drops))
+            .collect();
+        let mut seed = 42u64;
+        for i in 0..N {
+            let next = nodes[(i + 1) % N];
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let random = nodes[(seed >> 33) as usize % N];
+            heap.get_mut(nodes[i]).edges.extend([next, random]);
+        }
+
+        // 环中任意一个节点是根, 所有节点都不会被回收
+        let root = heap.root(nodes[N / 2]);
+        assert_eq!(heap.collect().marked, N);
+        drop(root);
+        let collected = heap.collect();
+        println!("{collected:?}");
+        assert_eq!(collected.freed, N);
+        assert_eq!(drops.get(), N);
+
+        // 复用回收后的位置, 旧的句柄失效
+        let reused = node(&mut heap, "reused", &drops);
+        assert!(!nodes.contains(&reused));
+        assert!(nodes.iter().all(|&old| !heap.is_alive(old)));
+        assert_eq!(heap.stats().live, 1);
+    }
+    // Heap drop 时析构剩余的对象, 同样只执行一次
+    assert_eq!(drops.get(), N + 1);
+}
+
+/// # example05 访问已经回收的对象
+/// 得到的是 panic, 而不是读到已经释放或者被复用的内存
+#[allow(unused)]
+fn example05() {
+    let drops = Rc::new(Cell::new(0));
+    let mut heap = Heap::new();
+    let old = node(&mut heap, "old", &drops);
+    heap.collect();
+    let new = node(&mut heap, "new", &drops);
+    // new 复用了 old 的位置, 但代数不同
+    assert_ne!(old, new);
+    assert!(heap.try_get(old).is_none());
+
+    let result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| heap.get(old).name.len()));
+    assert!(result.is_err());
+
+    // 引用借用了 heap, 引用存在期间无法 collect
+    // let name = &heap.get(new).name;
+    // heap.collect(); // ! cannot borrow `heap` as mutable because it is also borrowed as immutable
+    // println!("{name}");
+}
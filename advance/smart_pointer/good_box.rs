@@ -0,0 +1,270 @@
+/// 完整的 GoodBox
+///
+/// deref.rs 中的 `GoodBox<T>(T)` 只是把值包了一层, 值依然保存在栈上;
+/// 这里的 GoodBox 和 `Box` 一样通过全局分配器把值放到堆上, 自身只保存一个指针
+///
+/// - 分配: `std::alloc::alloc` 按 `Layout::new::<T>()` 分配, 零大小类型不分配
+/// - 释放: Drop 中先 `drop_in_place` 析构值, 再按 `Layout::for_value` 释放内存
+/// - `T: ?Sized`: 可以保存 `str`, `[T]` 和 `dyn Trait`
+///
+/// `Box<T>` 到 `Box<dyn Trait>` 的自动转换依赖不稳定的 `CoerceUnsized`,
+/// 这里使用 `unsize_box!` 宏通过裸指针的转换完成 (裸指针的 unsized 转换是稳定的)
+#[allow(unused)]
+struct Description;
+
+use std::alloc::{self, Layout};
+use std::borrow::{Borrow, BorrowMut};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+
+/// # GoodBox 在堆上保存值的智能指针
+pub struct GoodBox<T: ?Sized> {
+    ptr: NonNull<T>,
+    // 告诉编译器 GoodBox 拥有一个 T, drop 时会析构 T
+    _owns: PhantomData<T>,
+}
+
+// 和 Box 一样, 是否能跨线程只取决于 T
+unsafe impl<T: ?Sized + Send> Send for GoodBox<T> {}
+unsafe impl<T: ?Sized + Sync> Sync for GoodBox<T> {}
+
+/// 分配一块能容纳 layout 的内存, 大小为 0 时不分配
+fn allocate(layout: Layout) -> NonNull<u8> {
+    if layout.size() == 0 {
+        // 对齐的悬空指针, 对零大小类型的读写都是合法的
+        return unsafe { NonNull::new_unchecked(ptr::without_provenance_mut(layout.align())) };
+    }
+    let raw = unsafe { alloc::alloc(layout) };
+    NonNull::new(raw).unwrap_or_else(|| alloc::handle_alloc_error(layout))
+}
+
+#[allow(unused)]
+impl<T> GoodBox<T> {
+    /// 把 value 移动到堆上
+    pub fn new(value: T) -> GoodBox<T> {
+        let ptr = allocate(Layout::new::<T>()).cast::<T>();
+        unsafe { ptr.as_ptr().write(value) };
+        GoodBox {
+            ptr,
+            _owns: PhantomData,
+        }
+    }
+
+    /// 取出堆上的值并释放内存
+    pub fn into_inner(boxed: GoodBox<T>) -> T {
+        let boxed = ManuallyDrop::new(boxed);
+        unsafe {
+            let value = boxed.ptr.as_ptr().read();
+            // 值已经被移出, 只释放内存, 不再析构
+            let layout = Layout::new::<T>();
+            if layout.size() != 0 {
+                alloc::dealloc(boxed.ptr.as_ptr().cast(), layout);
+            }
+            value
+        }
+    }
+}
+
+#[allow(unused)]
+impl<T: ?Sized> GoodBox<T> {
+    /// 放弃所有权, 返回一个活到程序结束的引用, 值和内存都不会被释放
+    pub fn leak<'a>(boxed: GoodBox<T>) -> &'a mut T {
+        let boxed = ManuallyDrop::new(boxed);
+        unsafe { &mut *boxed.ptr.as_ptr() }
+    }
+
+    /// 放弃所有权, 返回裸指针, 之后需要用 from_raw 重新接管, 否则会泄漏
+    pub fn into_raw(boxed: GoodBox<T>) -> *mut T {
+        ManuallyDrop::new(boxed).ptr.as_ptr()
+    }
+
+    /// 从裸指针重新接管所有权
+    ///
+    /// # Safety
+    /// ptr 必须来自 `GoodBox::into_raw` 或 `Box::into_raw` (两者使用相同的全局分配器和布局),
+    /// 并且只能接管一次
+    pub unsafe fn from_raw(ptr: *mut T) -> GoodBox<T> {
+        GoodBox {
+            ptr: NonNull::new_unchecked(ptr),
+            _owns: PhantomData,
+        }
+    }
+
+    /// 从 Box 转换, 不需要重新分配
+    pub fn from_box(boxed: Box<T>) -> GoodBox<T> {
+        unsafe { GoodBox::from_raw(Box::into_raw(boxed)) }
+    }
+
+    /// 转换为 Box, 不需要重新分配
+    pub fn into_box(boxed: GoodBox<T>) -> Box<T> {
+        unsafe { Box::from_raw(GoodBox::into_raw(boxed)) }
+    }
+
+    pub fn as_ptr(boxed: &GoodBox<T>) -> *const T {
+        boxed.ptr.as_ptr()
+    }
+}
+
+/// 把 `GoodBox<T>` 转换为 `GoodBox<dyn Trait>` 或 `GoodBox<[T]>`
+///
+/// ```ignore
+/// let shape: GoodBox<dyn Shape> = unsize_box!(GoodBox::new(Circle), dyn Shape);
+/// ```
+///
+/// 通过类型标注触发裸指针的隐式转换, 只允许合法的 unsized 转换 (不能像 `as` 那样转换成任意类型)
+#[allow(unused_macros)]
+macro_rules! unsize_box {
+    ($boxed:expr, $target:ty) => {{
+        // 先得到具体类型的裸指针, 否则类型标注会被用来推断 into_raw 的泛型参数
+        let raw = $crate::good_box::GoodBox::into_raw($boxed);
+        let ptr: *mut $target = raw;
+        // ptr 刚刚由 into_raw 得到, 只接管一次
+        unsafe { $crate::good_box::GoodBox::<$target>::from_raw(ptr) }
+    }};
+}
+#[allow(unused_imports)]
+pub(crate) use unsize_box;
+
+impl<T: ?Sized> Drop for GoodBox<T> {
+    fn drop(&mut self) {
+        unsafe {
+            // 先计算布局, 析构之后就不能再访问值了
+            let layout = Layout::for_value(self.ptr.as_ref());
+            ptr::drop_in_place(self.ptr.as_ptr());
+            if layout.size() != 0 {
+                alloc::dealloc(self.ptr.as_ptr().cast(), layout);
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for GoodBox<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for GoodBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: Clone> Clone for GoodBox<T> {
+    /// 深拷贝: 在堆上分配一份新的值
+    fn clone(&self) -> Self {
+        GoodBox::new((**self).clone())
+    }
+}
+
+impl<T: Default> Default for GoodBox<T> {
+    fn default() -> Self {
+        GoodBox::new(T::default())
+    }
+}
+
+impl<T> From<T> for GoodBox<T> {
+    fn from(value: T) -> Self {
+        GoodBox::new(value)
+    }
+}
+
+impl<T: ?Sized> From<Box<T>> for GoodBox<T> {
+    fn from(boxed: Box<T>) -> Self {
+        GoodBox::from_box(boxed)
+    }
+}
+
+impl From<&str> for GoodBox<str> {
+    fn from(s: &str) -> Self {
+        let bytes: GoodBox<[u8]> = GoodBox::from(s.as_bytes());
+        // str 和 [u8] 的布局相同, 内容来自合法的 str
+        unsafe { GoodBox::from_raw(GoodBox::into_raw(bytes) as *mut str) }
+    }
+}
+
+impl<T: Copy> From<&[T]> for GoodBox<[T]> {
+    /// T: Copy 保证逐个复制时不会 panic, 也就不需要处理复制了一半的情况
+    fn from(slice: &[T]) -> Self {
+        let layout = Layout::for_value(slice);
+        let data = allocate(layout).cast::<T>();
+        unsafe {
+            ptr::copy_nonoverlapping(slice.as_ptr(), data.as_ptr(), slice.len());
+            GoodBox::from_raw(ptr::slice_from_raw_parts_mut(data.as_ptr(), slice.len()))
+        }
+    }
+}
+
+impl<T: ?Sized> AsRef<T> for GoodBox<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> AsMut<T> for GoodBox<T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+impl<T: ?Sized> Borrow<T> for GoodBox<T> {
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized> BorrowMut<T> for GoodBox<T> {
+    fn borrow_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+/// Debug 和 Display 打印的是值而不是指针, 需要地址时使用 `{:p}`
+impl<T: ?Sized + fmt::Debug> fmt::Debug for GoodBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for GoodBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// `{:p}` 打印堆上的地址
+impl<T: ?Sized> fmt::Pointer for GoodBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Pointer::fmt(&self.ptr, f)
+    }
+}
+
+impl<T: ?Sized + PartialEq> PartialEq for GoodBox<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: ?Sized + Eq> Eq for GoodBox<T> {}
+
+impl<T: ?Sized + PartialOrd> PartialOrd for GoodBox<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<T: ?Sized + Ord> Ord for GoodBox<T> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<T: ?Sized + std::hash::Hash> std::hash::Hash for GoodBox<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
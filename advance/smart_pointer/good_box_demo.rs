@@ -0,0 +1,230 @@
+/// 完整的 GoodBox
+///
+/// GoodBox 的实现见 good_box.rs
+///
+/// 这些示例不应该有任何未定义行为, 可以使用 AddressSanitizer 检查内存错误:
+/// `rustc +nightly -Zsanitizer=address --edition 2021 good_box_demo.rs`
+/// (example03 中的 `GoodBox::leak` 是故意泄漏的, 运行时设置 `ASAN_OPTIONS=detect_leaks=0`)
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod good_box;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use good_box::{unsize_box, GoodBox};
+use std::cell::Cell;
+use std::rc::Rc;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+        ("example06", example06),
+    ])
+}
+
+/// drop 时给计数加 1
+struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// # example01 值保存在堆上
+#[allow(unused)]
+fn example01() {
+    let on_stack = [0u8; 1024];
+    let on_heap = GoodBox::new([0u8; 1024]);
+    // GoodBox 本身只有一个指针大小
+    assert_eq!(std::mem::size_of_val(&on_stack), 1024);
+    assert_eq!(
+        std::mem::size_of_val(&on_heap),
+        std::mem::size_of::<usize>()
+    );
+    // 和 Box 一样, Option<GoodBox<T>> 不占用额外空间
+    assert_eq!(
+        std::mem::size_of::<Option<GoodBox<u32>>>(),
+        std::mem::size_of::<usize>()
+    );
+    println!("stack {:p}, heap {:p}", &on_stack, on_heap);
+
+    // 移动 GoodBox 只复制指针, 值的地址不变
+    let address = GoodBox::as_ptr(&on_heap);
+    let moved = on_heap;
+    assert_eq!(GoodBox::as_ptr(&moved), address);
+}
+
+/// # example02 Deref / DerefMut / Drop
+#[allow(unused)]
+fn example02() {
+    let mut s = GoodBox::new(String::from("hello"));
+    // DerefMut: 调用 String 的方法
+    s.push_str(", rust!");
+    // 隐式 Deref 转换: &GoodBox<String> -> &String -> &str
+    fn got_str(s: &str) -> usize {
+        s.len()
+    }
+    assert_eq!(got_str(&s), 12);
+
+    let mut n = GoodBox::new(1);
+    *n += 1;
+    assert_eq!(*n, 2);
+
+    // 值只被 drop 一次
+    let drops = Rc::new(Cell::new(0));
+    let boxed = GoodBox::new(DropCounter(Rc::clone(&drops)));
+    drop(boxed);
+    assert_eq!(drops.get(), 1);
+
+    // 零大小类型不分配内存, 但同样会被 drop
+    let zst = GoodBox::new(DropCounter(Rc::clone(&drops)));
+    let unit = GoodBox::new(());
+    drop((zst, unit));
+    assert_eq!(drops.get(), 2);
+}
+
+/// # example03 取出, 泄漏, 裸指针
+#[allow(unused)]
+fn example03() {
+    let drops = Rc::new(Cell::new(0));
+
+    // into_inner 移出值, 只释放内存, 值由调用方负责
+    let value = GoodBox::into_inner(GoodBox::new(DropCounter(Rc::clone(&drops))));
+    assert_eq!(drops.get(), 0);
+    drop(value);
+    assert_eq!(drops.get(), 1);
+
+    // into_raw / from_raw 往返
+    let raw = GoodBox::into_raw(GoodBox::new(DropCounter(Rc::clone(&drops))));
+    assert_eq!(drops.get(), 1);
+    let back = unsafe { GoodBox::from_raw(raw) };
+    drop(back);
+    assert_eq!(drops.get(), 2);
+
+    // 和 Box 互相转换, 不需要重新分配
+    let boxed = GoodBox::into_box(GoodBox::new(7));
+    let address: *const i32 = &*boxed;
+    let good = GoodBox::from_box(boxed);
+    assert_eq!(GoodBox::as_ptr(&good), address);
+
+    // leak 得到 'static 引用, 与 global_variable.rs 中的 Box::leak 相同
+    let config: &'static mut String = GoodBox::leak(GoodBox::new(String::from("config")));
+    config.push_str(" v2");
+    assert_eq!(config, "config v2");
+}
+
+/// # example04 Clone / PartialEq / Ord / Hash / Borrow
+#[allow(unused)]
+fn example04() {
+    use std::collections::{BTreeSet, HashMap};
+
+    let a = GoodBox::new(vec![1, 2, 3]);
+    let mut b = a.clone();
+    // 深拷贝: 两块不同的内存
+    assert_ne!(GoodBox::as_ptr(&a), GoodBox::as_ptr(&b));
+    b.push(4);
+    assert_eq!(*a, [1, 2, 3]);
+    assert!(a < b);
+
+    // Borrow<str>: 用 &str 查找以 GoodBox<str> 为键的 HashMap
+    let mut ages: HashMap<GoodBox<str>, u32> = HashMap::new();
+    ages.insert(GoodBox::from("Sunface"), 18);
+    assert_eq!(ages.get("Sunface"), Some(&18));
+
+    let set: BTreeSet<GoodBox<i32>> = [3, 1, 2].into_iter().map(GoodBox::new).collect();
+    let sorted: Vec<i32> = set.iter().map(|b| **b).collect();
+    assert_eq!(sorted, [1, 2, 3]);
+
+    // Debug / Display 打印值本身
+    let s: GoodBox<str> = "hello".into();
+    assert_eq!(
+        format!("{s} {s:?} {:?}", GoodBox::new(Some(1))),
+        "hello \"hello\" Some(1)"
+    );
+    assert_eq!(GoodBox::<u8>::default(), GoodBox::from(0));
+}
+
+/// # example05 unsized 类型: str, 切片, trait 对象
+#[allow(unused)]
+fn example05() {
+    trait Draw {
+        fn draw(&self) -> String;
+    }
+    struct Button {
+        label: String,
+    }
+    impl Draw for Button {
+        fn draw(&self) -> String {
+            format!("[{}]", self.label)
+        }
+    }
+    struct Select(Vec<&'static str>);
+    impl Draw for Select {
+        fn draw(&self) -> String {
+            self.0.join("|")
+        }
+    }
+
+    let s: GoodBox<str> = GoodBox::from("你好");
+    assert_eq!(s.len(), 6);
+    let slice: GoodBox<[u16]> = GoodBox::from(&[1u16, 2, 3][..]);
+    assert_eq!(slice.iter().sum::<u16>(), 6);
+    let empty: GoodBox<[u64]> = GoodBox::from(&[][..]);
+    assert!(empty.is_empty());
+
+    // 数组转换为切片
+    let array: GoodBox<[i32]> = unsize_box!(GoodBox::new([1, 2, 3]), [i32]);
+    assert_eq!(array.len(), 3);
+
+    // 具体类型转换为 trait 对象, drop 时通过虚表调用具体类型的析构
+    let widgets: Vec<GoodBox<dyn Draw>> = vec![
+        unsize_box!(
+            GoodBox::new(Button {
+                label: String::from("OK")
+            }),
+            dyn Draw
+        ),
+        unsize_box!(GoodBox::new(Select(vec!["a", "b"])), dyn Draw),
+        // 也可以从 Box<dyn Draw> 转换
+        GoodBox::from(Box::new(Select(vec!["c"])) as Box<dyn Draw>),
+    ];
+    let drawn: Vec<String> = widgets.iter().map(|w| w.draw()).collect();
+    assert_eq!(drawn, ["[OK]", "a|b", "c"]);
+}
+
+/// # example06 GoodBox<dyn Trait> 的 drop
+#[allow(unused)]
+fn example06() {
+    use std::any::Any;
+
+    let drops = Rc::new(Cell::new(0));
+    let items: Vec<GoodBox<dyn Any>> = vec![
+        unsize_box!(GoodBox::new(DropCounter(Rc::clone(&drops))), dyn Any),
+        unsize_box!(GoodBox::new(1u8), dyn Any),
+        unsize_box!(
+            GoodBox::new([
+                DropCounter(Rc::clone(&drops)),
+                DropCounter(Rc::clone(&drops))
+            ]),
+            dyn Any
+        ),
+    ];
+    assert!(items[1].is::<u8>());
+    drop(items);
+    assert_eq!(drops.get(), 3);
+
+    // 跨线程传递
+    let boxed = GoodBox::new(vec![1, 2, 3]);
+    let sum = std::thread::spawn(move || boxed.iter().sum::<i32>())
+        .join()
+        .unwrap();
+    assert_eq!(sum, 6);
+}
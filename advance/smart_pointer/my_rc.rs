@@ -0,0 +1,283 @@
+/// 手写的 Rc<T> 与 Weak<T>
+///
+/// 和 `std::rc::Rc` 的实现思路相同:
+/// - 值和两个计数放在同一块堆内存 `RcBox` 中, MyRc 和 Weak 都只保存指向它的指针
+/// - strong: MyRc 的数量, 降为 0 时析构值
+/// - weak: Weak 的数量 + 1, 所有 MyRc 共同持有这额外的 1, 降为 0 时释放内存
+///   (这样最后一个 MyRc 析构值的过程中, 即使值里的 Weak 被 drop 也不会提前释放内存)
+///
+/// 计数使用 `Cell`, 不是原子操作, 所以 MyRc 既不是 Send 也不是 Sync (裸指针 NonNull 保证了这一点)
+///
+/// 计数只通过字段的裸指针访问 (见 WeakInner): 析构值的过程中会 drop 值里的 Weak,
+/// 这时如果创建覆盖整个 RcBox 的引用, 就会与析构时对 value 的 `&mut` 重叠
+#[allow(unused)]
+struct Description;
+
+use std::cell::Cell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+
+struct RcBox<T> {
+    strong: Cell<usize>,
+    weak: Cell<usize>,
+    // 值的析构由 strong 控制, 释放内存时不再析构
+    value: ManuallyDrop<T>,
+}
+
+/// 两个计数的引用, 不覆盖 value
+struct WeakInner<'a> {
+    strong: &'a Cell<usize>,
+    weak: &'a Cell<usize>,
+}
+
+/// # Safety
+/// ptr 指向的 RcBox 还没有被释放, 返回的引用不能在内存释放之后使用
+unsafe fn counts<'a, T>(ptr: NonNull<RcBox<T>>) -> WeakInner<'a> {
+    let ptr = ptr.as_ptr();
+    WeakInner {
+        strong: &*ptr::addr_of!((*ptr).strong),
+        weak: &*ptr::addr_of!((*ptr).weak),
+    }
+}
+
+/// 计数溢出时和 std 一样直接终止进程 (例如 mem::forget 了大量的克隆),
+/// 否则计数回绕后会提前释放仍在使用的内存
+fn increment(count: &Cell<usize>) {
+    match count.get().checked_add(1) {
+        Some(n) => count.set(n),
+        None => std::process::abort(),
+    }
+}
+
+/// 减少 weak 计数, 降为 0 时释放内存
+///
+/// # Safety
+/// ptr 指向的 RcBox 还没有被释放, 调用后不能再使用 ptr
+unsafe fn release_weak<T>(ptr: NonNull<RcBox<T>>) {
+    let weak = counts(ptr).weak;
+    weak.set(weak.get() - 1);
+    if weak.get() == 0 {
+        // value 是 ManuallyDrop, 这里只释放内存
+        drop(Box::from_raw(ptr.as_ptr()));
+    }
+}
+
+/// # MyRc 单线程的引用计数指针
+pub struct MyRc<T> {
+    ptr: NonNull<RcBox<T>>,
+    _owns: PhantomData<RcBox<T>>,
+}
+
+/// # Weak 不拥有值的弱引用, 需要 upgrade 成 MyRc 才能访问值
+pub struct Weak<T> {
+    // Weak::new 创建的 Weak 不指向任何 RcBox
+    ptr: Option<NonNull<RcBox<T>>>,
+}
+
+#[allow(unused)]
+impl<T> MyRc<T> {
+    pub fn new(value: T) -> MyRc<T> {
+        let inner = Box::new(RcBox {
+            strong: Cell::new(1),
+            weak: Cell::new(1),
+            value: ManuallyDrop::new(value),
+        });
+        MyRc {
+            ptr: NonNull::from(Box::leak(inner)),
+            _owns: PhantomData,
+        }
+    }
+
+    fn counts(&self) -> WeakInner<'_> {
+        // 只要还有 MyRc, RcBox 就不会被释放
+        unsafe { counts(self.ptr) }
+    }
+
+    pub fn strong_count(this: &MyRc<T>) -> usize {
+        this.counts().strong.get()
+    }
+
+    /// Weak 的数量, 不包括所有 MyRc 共同持有的 1
+    pub fn weak_count(this: &MyRc<T>) -> usize {
+        this.counts().weak.get() - 1
+    }
+
+    pub fn downgrade(this: &MyRc<T>) -> Weak<T> {
+        increment(this.counts().weak);
+        Weak {
+            ptr: Some(this.ptr),
+        }
+    }
+
+    /// 两个 MyRc 是否指向同一个值
+    pub fn ptr_eq(this: &MyRc<T>, other: &MyRc<T>) -> bool {
+        this.ptr == other.ptr
+    }
+
+    /// 没有其他 MyRc 和 Weak 时才能得到可变引用
+    pub fn get_mut(this: &mut MyRc<T>) -> Option<&mut T> {
+        let counts = this.counts();
+        if counts.strong.get() == 1 && counts.weak.get() == 1 {
+            Some(unsafe { &mut (*this.ptr.as_ptr()).value })
+        } else {
+            None
+        }
+    }
+
+    /// 只有一个 MyRc 时取出值, 否则原样返回
+    ///
+    /// 取出后剩下的 Weak 都无法再 upgrade
+    pub fn try_unwrap(this: MyRc<T>) -> Result<T, MyRc<T>> {
+        if MyRc::strong_count(&this) != 1 {
+            return Err(this);
+        }
+        let this = ManuallyDrop::new(this);
+        unsafe {
+            let value =
+                ManuallyDrop::into_inner(ptr::read(ptr::addr_of!((*this.ptr.as_ptr()).value)));
+            this.counts().strong.set(0);
+            release_weak(this.ptr);
+            Ok(value)
+        }
+    }
+}
+
+#[allow(unused)]
+impl<T: Clone> MyRc<T> {
+    /// 写时复制: 得到可变引用, 必要时先把值复制一份
+    ///
+    /// - 有其他 MyRc: 复制值, this 指向新的副本, 其他 MyRc 不受影响
+    /// - 只有 Weak: 把值移动到新的 RcBox, 原来的 Weak 都无法再 upgrade
+    pub fn make_mut(this: &mut MyRc<T>) -> &mut T {
+        let counts = this.counts();
+        if counts.strong.get() != 1 {
+            *this = MyRc::new(T::clone(this));
+        } else if counts.weak.get() != 1 {
+            unsafe {
+                let value =
+                    ManuallyDrop::into_inner(ptr::read(ptr::addr_of!((*this.ptr.as_ptr()).value)));
+                counts.strong.set(0);
+                // 还有 Weak, 这里不会释放内存
+                release_weak(this.ptr);
+                // 原来的 MyRc 已经放弃了它的计数, 不能再 drop
+                ptr::write(this, MyRc::new(value));
+            }
+        }
+        unsafe { &mut (*this.ptr.as_ptr()).value }
+    }
+}
+
+impl<T> Clone for MyRc<T> {
+    /// 只增加计数, 不复制值
+    fn clone(&self) -> Self {
+        increment(self.counts().strong);
+        MyRc {
+            ptr: self.ptr,
+            _owns: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for MyRc<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // 持有 MyRc 期间 value 不会被析构
+        unsafe { &(*self.ptr.as_ptr()).value }
+    }
+}
+
+impl<T> Drop for MyRc<T> {
+    fn drop(&mut self) {
+        let strong = self.counts().strong;
+        strong.set(strong.get() - 1);
+        if strong.get() == 0 {
+            unsafe {
+                // 先析构值, 值里面的 Weak 会在这里被 drop, 但 weak 至少还有所有 MyRc 共同持有的 1
+                ManuallyDrop::drop(&mut (*self.ptr.as_ptr()).value);
+                release_weak(self.ptr);
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MyRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for MyRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[allow(unused)]
+impl<T> Weak<T> {
+    /// 不指向任何值的 Weak, upgrade 总是返回 None
+    pub fn new() -> Weak<T> {
+        Weak { ptr: None }
+    }
+
+    fn inner(&self) -> Option<WeakInner<'_>> {
+        // 只要还有 Weak, RcBox 就不会被释放; value 可能已经 (或正在) 被析构, 只能访问计数
+        self.ptr.map(|ptr| unsafe { counts(ptr) })
+    }
+
+    /// 值还没有被析构时得到一个新的 MyRc
+    pub fn upgrade(&self) -> Option<MyRc<T>> {
+        let inner = self.inner()?;
+        if inner.strong.get() == 0 {
+            return None;
+        }
+        increment(inner.strong);
+        Some(MyRc {
+            ptr: self.ptr?,
+            _owns: PhantomData,
+        })
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner().map_or(0, |inner| inner.strong.get())
+    }
+
+    /// 值已经被析构时为 0, 与 std 相同
+    pub fn weak_count(&self) -> usize {
+        match self.inner() {
+            Some(inner) if inner.strong.get() > 0 => inner.weak.get() - 1,
+            _ => 0,
+        }
+    }
+}
+
+impl<T> Default for Weak<T> {
+    fn default() -> Self {
+        Weak::new()
+    }
+}
+
+impl<T> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        if let Some(inner) = self.inner() {
+            increment(inner.weak);
+        }
+        Weak { ptr: self.ptr }
+    }
+}
+
+impl<T> Drop for Weak<T> {
+    fn drop(&mut self) {
+        if let Some(ptr) = self.ptr {
+            unsafe { release_weak(ptr) };
+        }
+    }
+}
+
+impl<T> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
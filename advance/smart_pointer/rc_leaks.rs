@@ -0,0 +1,235 @@
+/// Rc 循环引用导致的内存泄漏
+///
+/// 检测的实现见 rc_tracker.rs; 登记只在 debug 构建中进行, 需要不加 -O 编译运行才能看到报告,
+/// -O 编译时报告总是空的, 示例只检查这一点
+///
+/// 登记表是线程局部的, 每个示例在单独的线程中运行, 互不影响
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+mod rc_tracker;
+
+use rc_tracker::{report, track, LeakCheck, LeakReport, Trace, Tracer};
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+    ])
+}
+
+/// 是否需要检查报告的内容
+///
+/// release 构建 (-O) 中不登记, 报告总是空的: 断言这一点并跳过后面的检查
+fn tracked(report: &LeakReport) -> bool {
+    if cfg!(debug_assertions) {
+        return true;
+    }
+    assert_eq!((report.live, report.leaked.len()), (0, 0));
+    false
+}
+
+/// 在新线程中运行, 使用一张新的登记表; f 中的 panic 传回当前线程
+fn in_thread<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    std::thread::scope(|s| {
+        s.spawn(f)
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    })
+}
+
+/// 子节点通过 Weak 指向父节点
+struct TreeNode {
+    name: &'static str,
+    parent: RefCell<Weak<TreeNode>>,
+    children: RefCell<Vec<Rc<TreeNode>>>,
+}
+
+impl Trace for TreeNode {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.parent.trace(tracer);
+        self.children.trace(tracer);
+    }
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+/// 子节点通过 Rc 指向父节点
+struct LeakyNode {
+    name: &'static str,
+    parent: RefCell<Option<Rc<LeakyNode>>>,
+    children: RefCell<Vec<Rc<LeakyNode>>>,
+}
+
+impl Trace for LeakyNode {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.parent.trace(tracer);
+        self.children.trace(tracer);
+    }
+    fn label(&self) -> String {
+        self.name.to_string()
+    }
+}
+
+/// 创建 TreeNode 并挂到 parent 下面
+fn tree_node(name: &'static str, parent: Option<&Rc<TreeNode>>) -> Rc<TreeNode> {
+    let node = track(TreeNode {
+        name,
+        parent: RefCell::new(parent.map(Rc::downgrade).unwrap_or_default()),
+        children: RefCell::new(Vec::new()),
+    });
+    if let Some(parent) = parent {
+        parent.children.borrow_mut().push(Rc::clone(&node));
+    }
+    node
+}
+
+/// 创建 LeakyNode 并挂到 parent 下面
+fn leaky_node(name: &'static str, parent: Option<&Rc<LeakyNode>>) -> Rc<LeakyNode> {
+    let node = track(LeakyNode {
+        name,
+        parent: RefCell::new(parent.cloned()),
+        children: RefCell::new(Vec::new()),
+    });
+    if let Some(parent) = parent {
+        parent.children.borrow_mut().push(Rc::clone(&node));
+    }
+    node
+}
+
+/// # example01 父指针使用 Weak: 没有环, 根节点 drop 后整棵树被释放
+/// ```text
+/// root
+/// ├── a
+/// │   └── c
+/// └── b
+/// ```
+#[allow(unused)]
+fn example01() {
+    in_thread(|| {
+        let root = tree_node("root", None);
+        let a = tree_node("a", Some(&root));
+        tree_node("b", Some(&root));
+        let c = tree_node("c", Some(&a));
+        drop(a);
+
+        // c 可以通过 Weak 找到祖先
+        let grandparent = c
+            .parent
+            .borrow()
+            .upgrade()
+            .unwrap()
+            .parent
+            .borrow()
+            .upgrade();
+        assert_eq!(grandparent.map(|g| g.name), Some("root"));
+
+        let before = report();
+        println!("{before}");
+        if tracked(&before) {
+            assert_eq!((before.live, before.leaked.len()), (4, 0));
+        }
+
+        // c 还被外部持有, 所以还活着, 但它的父节点已经释放, Weak 无法 upgrade
+        drop(root);
+        assert!(c.parent.borrow().upgrade().is_none());
+        let after = report();
+        println!("{after}");
+        if tracked(&after) {
+            assert_eq!((after.live, after.leaked.len()), (1, 0));
+        }
+    });
+}
+
+/// # example02 父指针使用 Rc: 父子互相持有, 根节点 drop 后整棵树泄漏
+#[allow(unused)]
+fn example02() {
+    in_thread(|| {
+        let root = leaky_node("root", None);
+        let a = leaky_node("a", Some(&root));
+        leaky_node("b", Some(&root));
+        leaky_node("c", Some(&a));
+        drop(a);
+
+        // 根节点还在栈上, 树可以访问, 还不算泄漏
+        assert!(report().is_clean());
+        assert_eq!(Rc::strong_count(&root), 3);
+
+        drop(root);
+        let leaks = report();
+        print!("{leaks}");
+        if tracked(&leaks) {
+            assert_eq!(leaks.live, 4);
+            assert_eq!(leaks.leaked.len(), 4);
+            // 所有节点通过父子关系互相可达, 组成一个环
+            assert_eq!(leaks.cycles.len(), 1);
+            assert_eq!(leaks.cycles[0].len(), 4);
+        }
+    });
+}
+
+/// # example03 被环持有的值, 以及手动打破环
+/// ```text
+/// x <-> y -> z
+/// ```
+/// z 自己不在环中, 但只被环中的 y 持有, 同样会泄漏
+#[allow(unused)]
+fn example03() {
+    in_thread(|| {
+        let x = leaky_node("x", None);
+        let y = leaky_node("y", Some(&x));
+        let z = leaky_node("z", None);
+        y.children.borrow_mut().push(Rc::clone(&z));
+        let x_weak = Rc::downgrade(&x);
+        drop((x, y));
+
+        // z 还被栈上的变量持有, 只有 x 和 y 泄漏
+        let leaks = report();
+        print!("{leaks}");
+        if tracked(&leaks) {
+            assert_eq!(leaks.leaked.len(), 2);
+            assert_eq!(leaks.cycles.len(), 1);
+        }
+
+        drop(z);
+        let leaks = report();
+        print!("{leaks}");
+        if tracked(&leaks) {
+            assert_eq!(leaks.leaked.len(), 3);
+            assert_eq!(leaks.cycles.len(), 1);
+            assert_eq!(leaks.cycles[0].len(), 2);
+        }
+
+        // 通过 Weak 找到环中的节点, 断开一条边后整个环被释放
+        let x = x_weak.upgrade().unwrap();
+        x.children.borrow_mut().clear();
+        drop(x);
+        assert!(x_weak.upgrade().is_none());
+        let leaks = report();
+        println!("{leaks}");
+        assert_eq!((leaks.live, leaks.leaked.len()), (0, 0));
+    });
+}
+
+/// # example04 线程结束时打印报告
+/// 线程中泄漏的环会在 LeakCheck drop 时打印到 stderr
+#[allow(unused)]
+fn example04() {
+    in_thread(|| {
+        let _check = LeakCheck;
+        let a = leaky_node("a", None);
+        let b = leaky_node("b", Some(&a));
+        // 自己指向自己也是环
+        let c = leaky_node("c", None);
+        c.children.borrow_mut().push(Rc::clone(&c));
+    });
+}
@@ -0,0 +1,385 @@
+/// Rc 循环引用检测
+///
+/// Rc 只在计数降为 0 时释放, 互相持有 Rc 的一组值计数永远不会降为 0, 也就永远不会被释放 (见 ac_and_arc.rs example06);
+/// 编译器和运行时都不会提示, 这里在 std Rc 之上加一层调试用的记录:
+///
+/// - `track(value)` 代替 `Rc::new(value)`, 把新的 Rc 以 Weak 的形式登记下来 (不影响计数)
+/// - 值实现 `Trace`, 列出自己直接持有的 Rc (即图中的边)
+/// - `report()` 找出所有只被其他登记过的值持有, 从外部已经无法访问的值 (与 CPython 的循环垃圾回收相同的思路):
+///   1. 每个值的 strong_count 减去图中指向它的边数, 剩下的就是来自外部 (栈上变量, 全局变量等) 的引用
+///   2. 从有外部引用的值出发沿着边标记, 标记不到的就是泄漏的值
+///   3. 把泄漏的值按强连通分量分组, 每个大小大于 1 (或者指向自己) 的分量就是一个环
+///
+/// 登记只在 debug 构建 (`debug_assertions`) 中进行, release 构建中 track 就是 Rc::new, report 总是为空
+///
+/// 因为 Rc 不能跨线程, 登记表是线程局部的, 每个线程只能检查自己创建的值
+#[allow(unused)]
+struct Description;
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::rc::{Rc, Weak};
+
+/// # Trace 列出值直接持有的 Rc
+///
+/// ```ignore
+/// impl Trace for Node {
+///     fn trace(&self, tracer: &mut Tracer) {
+///         self.children.trace(tracer); // RefCell<Vec<Rc<Node>>>
+///         self.parent.trace(tracer);   // RefCell<Weak<Node>>, Weak 不是边
+///     }
+/// }
+/// ```
+pub trait Trace {
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// 报告中显示的名字, 默认为类型名
+    fn label(&self) -> String {
+        short_type_name(std::any::type_name::<Self>())
+    }
+}
+
+/// 去掉类型名中的路径, `ac::Node<alloc::string::String>` -> `Node<String>`
+fn short_type_name(name: &str) -> String {
+    let mut short = String::new();
+    for part in name.split_inclusive(['<', '>', ',', ' ']) {
+        short.push_str(part.rsplit("::").next().unwrap_or(part));
+    }
+    short
+}
+
+/// # Tracer 收集一个值的所有边
+pub struct Tracer {
+    edges: Vec<usize>,
+}
+
+#[allow(unused)]
+impl Tracer {
+    /// 记录一条指向 rc 的边
+    pub fn edge<T: ?Sized>(&mut self, rc: &Rc<T>) {
+        self.edges.push(address(rc));
+    }
+}
+
+fn address<T: ?Sized>(rc: &Rc<T>) -> usize {
+    Rc::as_ptr(rc).cast::<()>() as usize
+}
+
+impl<T: ?Sized> Trace for Rc<T> {
+    /// 只记录边, 不递归: 被指向的值自己也登记了, 会单独被 trace
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.edge(self);
+    }
+}
+
+impl<T: ?Sized> Trace for Weak<T> {
+    /// Weak 不阻止释放, 不是边
+    fn trace(&self, _tracer: &mut Tracer) {}
+}
+
+impl<T: Trace + ?Sized> Trace for RefCell<T> {
+    /// 正在被可变借用时无法读取, 跳过: 少算边只会让值看起来有外部引用, 不会误报泄漏
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(value) = self.try_borrow() {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(value) = self {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for Vec<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        for value in self {
+            value.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace + ?Sized> Trace for Box<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        (**self).trace(tracer);
+    }
+}
+
+/// 不持有 Rc 的类型
+macro_rules! impl_trace_leaf {
+    ($($ty:ty),*) => {
+        $(
+            impl Trace for $ty {
+                fn trace(&self, _tracer: &mut Tracer) {}
+            }
+        )*
+    };
+}
+impl_trace_leaf!(
+    bool,
+    char,
+    i32,
+    i64,
+    u32,
+    u64,
+    usize,
+    f64,
+    String,
+    &'static str
+);
+
+thread_local! {
+    /// 当前线程登记过的值, 已经释放的会在 report 时清理
+    static TRACKED: RefCell<Vec<Weak<dyn Trace>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// 创建 Rc 并登记, 用法与 Rc::new 相同
+#[allow(unused)]
+pub fn track<T: Trace + 'static>(value: T) -> Rc<T> {
+    let rc = Rc::new(value);
+    if cfg!(debug_assertions) {
+        let weak: Weak<dyn Trace> = Rc::downgrade(&rc) as Weak<dyn Trace>;
+        TRACKED.with(|tracked| tracked.borrow_mut().push(weak));
+    }
+    rc
+}
+
+/// 一个泄漏的值
+#[derive(Debug, Clone)]
+pub struct Leaked {
+    pub address: usize,
+    pub label: String,
+    /// 指向的其他泄漏的值
+    pub edges: Vec<usize>,
+}
+
+/// # LeakReport 检查的结果
+#[derive(Debug, Default)]
+pub struct LeakReport {
+    /// 仍然存活的登记过的值
+    pub live: usize,
+    /// 已经无法从外部访问的值, 按地址排序
+    pub leaked: Vec<Leaked>,
+    /// 泄漏的值中的环, 每个环是一组地址
+    pub cycles: Vec<Vec<usize>>,
+}
+
+#[allow(unused)]
+impl LeakReport {
+    pub fn is_clean(&self) -> bool {
+        self.leaked.is_empty()
+    }
+
+    fn label(&self, address: usize) -> &str {
+        self.leaked
+            .iter()
+            .find(|leaked| leaked.address == address)
+            .map_or("?", |leaked| leaked.label.as_str())
+    }
+}
+
+impl fmt::Display for LeakReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "{} 个值存活, 没有泄漏", self.live);
+        }
+        writeln!(
+            f,
+            "{} 个值存活, 其中 {} 个已经无法访问, 组成 {} 个环:",
+            self.live,
+            self.leaked.len(),
+            self.cycles.len()
+        )?;
+        for (i, cycle) in self.cycles.iter().enumerate() {
+            let labels: Vec<&str> = cycle.iter().map(|&address| self.label(address)).collect();
+            writeln!(
+                f,
+                "  环 {} ({} 个值): {}",
+                i + 1,
+                cycle.len(),
+                labels.join(", ")
+            )?;
+        }
+        let in_cycle: BTreeSet<usize> = self.cycles.iter().flatten().copied().collect();
+        for leaked in self
+            .leaked
+            .iter()
+            .filter(|l| !in_cycle.contains(&l.address))
+        {
+            writeln!(f, "  被环持有: {}", leaked.label)?;
+        }
+        Ok(())
+    }
+}
+
+/// 检查当前线程中登记过的值
+#[allow(unused)]
+pub fn report() -> LeakReport {
+    // 先取出所有存活的值, 不在借用登记表期间调用 trace (trace 中可能再调用 track)
+    let live: Vec<Rc<dyn Trace>> = TRACKED.with(|tracked| {
+        let mut tracked = tracked.borrow_mut();
+        tracked.retain(|weak| weak.strong_count() > 0);
+        tracked.iter().filter_map(Weak::upgrade).collect()
+    });
+
+    struct Node {
+        label: String,
+        edges: Vec<usize>,
+        external: usize,
+    }
+    let mut nodes: BTreeMap<usize, Node> = BTreeMap::new();
+    for rc in &live {
+        // 注意 Rc 本身也实现了 Trace, 这里要调用的是值的 trace
+        let value: &dyn Trace = &**rc;
+        let mut tracer = Tracer { edges: Vec::new() };
+        value.trace(&mut tracer);
+        nodes.insert(
+            address(rc),
+            Node {
+                label: value.label(),
+                edges: tracer.edges,
+                // 减去 live 中的这一个
+                external: Rc::strong_count(rc) - 1,
+            },
+        );
+    }
+
+    // 1. 减去来自图中的引用, 指向未登记的 Rc 的边忽略
+    let edges: Vec<usize> = nodes.values().flat_map(|n| n.edges.clone()).collect();
+    for target in edges {
+        if let Some(node) = nodes.get_mut(&target) {
+            node.external = node.external.saturating_sub(1);
+        }
+    }
+
+    // 2. 从有外部引用的值出发标记
+    let mut reachable: BTreeSet<usize> = BTreeSet::new();
+    let mut stack: Vec<usize> = nodes
+        .iter()
+        .filter(|(_, node)| node.external > 0)
+        .map(|(&address, _)| address)
+        .collect();
+    while let Some(address) = stack.pop() {
+        if !reachable.insert(address) {
+            continue;
+        }
+        if let Some(node) = nodes.get(&address) {
+            stack.extend(node.edges.iter().filter(|e| nodes.contains_key(e)));
+        }
+    }
+
+    let leaked: Vec<Leaked> = nodes
+        .iter()
+        .filter(|(address, _)| !reachable.contains(address))
+        .map(|(&address, node)| Leaked {
+            address,
+            label: node.label.clone(),
+            edges: node
+                .edges
+                .iter()
+                .copied()
+                .filter(|e| nodes.contains_key(e) && !reachable.contains(e))
+                .collect(),
+        })
+        .collect();
+
+    // 3. 找环
+    let cycles = strongly_connected(&leaked)
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1 || {
+                let only = component[0];
+                leaked
+                    .iter()
+                    .any(|l| l.address == only && l.edges.contains(&only))
+            }
+        })
+        .collect();
+
+    LeakReport {
+        live: live.len(),
+        leaked,
+        cycles,
+    }
+}
+
+/// Tarjan 算法求强连通分量
+fn strongly_connected(graph: &[Leaked]) -> Vec<Vec<usize>> {
+    struct State<'a> {
+        graph: BTreeMap<usize, &'a [usize]>,
+        index: BTreeMap<usize, usize>,
+        low: BTreeMap<usize, usize>,
+        stack: Vec<usize>,
+        on_stack: BTreeSet<usize>,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn visit(state: &mut State, v: usize) {
+        let i = state.index.len();
+        state.index.insert(v, i);
+        state.low.insert(v, i);
+        state.stack.push(v);
+        state.on_stack.insert(v);
+        for &w in state.graph[&v] {
+            if !state.index.contains_key(&w) {
+                visit(state, w);
+                let low = state.low[&v].min(state.low[&w]);
+                state.low.insert(v, low);
+            } else if state.on_stack.contains(&w) {
+                let low = state.low[&v].min(state.index[&w]);
+                state.low.insert(v, low);
+            }
+        }
+        if state.low[&v] == state.index[&v] {
+            let mut component = Vec::new();
+            loop {
+                let w = state.stack.pop().unwrap();
+                state.on_stack.remove(&w);
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            state.components.push(component);
+        }
+    }
+
+    let mut state = State {
+        graph: graph
+            .iter()
+            .map(|l| (l.address, l.edges.as_slice()))
+            .collect(),
+        index: BTreeMap::new(),
+        low: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for leaked in graph {
+        if !state.index.contains_key(&leaked.address) {
+            visit(&mut state, leaked.address);
+        }
+    }
+    state.components
+}
+
+/// # LeakCheck 离开作用域时打印当前线程的泄漏报告
+///
+/// 放在 main 或线程函数的开头: `let _check = LeakCheck;`
+pub struct LeakCheck;
+
+impl Drop for LeakCheck {
+    fn drop(&mut self) {
+        let report = report();
+        if report.is_clean() {
+            println!("[rc_tracker] {report}");
+        } else {
+            eprint!("[rc_tracker] {report}");
+        }
+    }
+}
@@ -0,0 +1,146 @@
+/// 作用域守卫
+///
+/// Drop 最常见的用途: 把 "离开作用域时一定要做的事" 交给编译器, 无论是正常返回, 提前 return, `?` 还是 panic
+///
+/// - `guard(value, f)`: 离开作用域时调用 `f(value)`, 期间可以通过 Deref 访问 value
+/// - `guard_on_success` / `guard_on_unwind`: 只在正常离开 / 只在 panic 展开时调用
+/// - `defuse()`: 取消, 取回 value, f 不会被调用
+/// - `defer!` / `defer_on_success!` / `defer_on_unwind!`: 不需要 value 时的简写
+///
+/// 是否在 panic 中通过 `std::thread::panicking()` 判断:
+/// 在其他值 panic 展开期间的 drop 中创建并离开作用域的守卫, 也会被视为 panic 展开
+#[allow(unused)]
+struct Description;
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// 什么时候调用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Always,
+    /// 只在正常离开作用域时
+    OnSuccess,
+    /// 只在 panic 展开时
+    OnUnwind,
+}
+
+impl Strategy {
+    fn should_run(self) -> bool {
+        match self {
+            Strategy::Always => true,
+            Strategy::OnSuccess => !std::thread::panicking(),
+            Strategy::OnUnwind => std::thread::panicking(),
+        }
+    }
+}
+
+/// # ScopeGuard 离开作用域时调用 f(value)
+#[must_use = "守卫被立即 drop, f 会马上执行; 需要绑定到变量, 比如 `let _guard = guard(...)`"]
+pub struct ScopeGuard<T, F: FnOnce(T)> {
+    // defuse 之后为 None
+    inner: Option<(T, F)>,
+    strategy: Strategy,
+}
+
+/// 离开作用域时调用 f(value)
+#[allow(unused)]
+pub fn guard<T, F: FnOnce(T)>(value: T, f: F) -> ScopeGuard<T, F> {
+    ScopeGuard::with_strategy(value, f, Strategy::Always)
+}
+
+/// 只在正常离开作用域时调用 f(value)
+#[allow(unused)]
+pub fn guard_on_success<T, F: FnOnce(T)>(value: T, f: F) -> ScopeGuard<T, F> {
+    ScopeGuard::with_strategy(value, f, Strategy::OnSuccess)
+}
+
+/// 只在 panic 展开时调用 f(value), 适合回滚
+#[allow(unused)]
+pub fn guard_on_unwind<T, F: FnOnce(T)>(value: T, f: F) -> ScopeGuard<T, F> {
+    ScopeGuard::with_strategy(value, f, Strategy::OnUnwind)
+}
+
+#[allow(unused)]
+impl<T, F: FnOnce(T)> ScopeGuard<T, F> {
+    pub fn with_strategy(value: T, f: F, strategy: Strategy) -> ScopeGuard<T, F> {
+        ScopeGuard {
+            inner: Some((value, f)),
+            strategy,
+        }
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// 取消守卫, 取回 value, f 不会被调用
+    pub fn defuse(mut self) -> T {
+        let (value, _f) = self.inner.take().expect("守卫只能取消一次");
+        value
+    }
+}
+
+impl<T, F: FnOnce(T)> Deref for ScopeGuard<T, F> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // inner 只会在 defuse 和 drop 中被取走, 之后无法再访问守卫
+        &self.inner.as_ref().unwrap().0
+    }
+}
+
+impl<T, F: FnOnce(T)> DerefMut for ScopeGuard<T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.as_mut().unwrap().0
+    }
+}
+
+impl<T, F: FnOnce(T)> Drop for ScopeGuard<T, F> {
+    fn drop(&mut self) {
+        if let Some((value, f)) = self.inner.take() {
+            if self.strategy.should_run() {
+                f(value);
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug, F: FnOnce(T)> fmt::Debug for ScopeGuard<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopeGuard")
+            .field("value", &self.inner.as_ref().map(|(value, _)| value))
+            .field("strategy", &self.strategy)
+            .finish()
+    }
+}
+
+/// 离开作用域时执行代码块, 多个 defer! 按相反的顺序执行
+///
+/// ```ignore
+/// defer! { println!("离开作用域"); }
+/// ```
+#[allow(unused_macros)]
+macro_rules! defer {
+    ($($body:tt)*) => {
+        let _guard = $crate::scope_guard::guard((), |()| { $($body)* });
+    };
+}
+
+/// 只在正常离开作用域时执行代码块
+#[allow(unused_macros)]
+macro_rules! defer_on_success {
+    ($($body:tt)*) => {
+        let _guard = $crate::scope_guard::guard_on_success((), |()| { $($body)* });
+    };
+}
+
+/// 只在 panic 展开时执行代码块
+#[allow(unused_macros)]
+macro_rules! defer_on_unwind {
+    ($($body:tt)*) => {
+        let _guard = $crate::scope_guard::guard_on_unwind((), |()| { $($body)* });
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use {defer, defer_on_success, defer_on_unwind};
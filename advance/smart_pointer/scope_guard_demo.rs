@@ -0,0 +1,240 @@
+/// 作用域守卫
+///
+/// ScopeGuard / defer! 的实现见 scope_guard.rs, 执行顺序通过 drop_tracer.rs 中的 DropLog 断言
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod drop_tracer;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+mod scope_guard;
+
+use drop_tracer::DropLog;
+use panic_boundary::catch_panic;
+use scope_guard::{
+    defer, defer_on_success, defer_on_unwind, guard, guard_on_success, guard_on_unwind,
+};
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// # example01 defer!
+/// 离开作用域时执行, 多个 defer! 按相反的顺序执行 (与局部变量的析构顺序相同)
+#[allow(unused)]
+fn example01() {
+    let log = DropLog::new();
+
+    fn early_return(log: &DropLog, stop: bool) -> Option<()> {
+        defer! { log.record("first defer"); }
+        defer! { log.record("second defer"); }
+        if stop {
+            return None;
+        }
+        log.record("body end");
+        Some(())
+    }
+
+    early_return(&log, false);
+    assert_eq!(log.take(), ["body end", "second defer", "first defer"]);
+    // 提前 return 时同样会执行
+    early_return(&log, true);
+    assert_eq!(log.take(), ["second defer", "first defer"]);
+
+    // guard 持有一个值, 期间可以通过 Deref 访问和修改
+    {
+        let mut buffer = guard(Vec::new(), |buffer: Vec<&str>| {
+            log.record(format!("flush {}", buffer.join(",")))
+        });
+        buffer.push("a");
+        buffer.push("b");
+    }
+    assert_eq!(log.take(), ["flush a,b"]);
+}
+
+/// # example02 on_success / on_unwind
+#[allow(unused)]
+fn example02() {
+    let log = DropLog::new();
+
+    let run = |fail: bool| {
+        defer! { log.record("always"); }
+        defer_on_success! { log.record("success"); }
+        defer_on_unwind! { log.record("unwind"); }
+        if fail {
+            panic!("任务失败");
+        }
+    };
+
+    assert!(catch_panic(|| run(false)).is_ok());
+    assert_eq!(log.take(), ["success", "always"]);
+    assert!(catch_panic(|| run(true)).is_err());
+    assert_eq!(log.take(), ["unwind", "always"]);
+
+    // guard_on_unwind 也可以持有值
+    let result = catch_panic(|| {
+        let _rollback = guard_on_unwind("订单 42", |order| log.record(format!("回滚 {order}")));
+        let _notify = guard_on_success("订单 42", |order| log.record(format!("通知 {order}")));
+        panic!("扣款失败");
+    });
+    assert!(result.is_err());
+    assert_eq!(log.take(), ["回滚 订单 42"]);
+}
+
+/// # example03 defuse 取消守卫
+/// 先注册回滚, 所有步骤都成功后取消回滚 (提交)
+#[allow(unused)]
+fn example03() {
+    let log = DropLog::new();
+
+    fn transfer(log: &DropLog, amount: u32, balance: &mut u32) -> Result<(), String> {
+        let before = *balance;
+        // 守卫持有 balance 的可变引用, 期间通过守卫修改
+        let mut account = guard(balance, |balance: &mut u32| {
+            *balance = before;
+            log.record("rollback");
+        });
+        **account -= amount.min(**account);
+        if amount > before {
+            // 提前返回, 守卫被 drop, 执行回滚
+            return Err(format!("余额不足: {before} < {amount}"));
+        }
+        // 取消回滚
+        let balance = account.defuse();
+        log.record(format!("commit {balance}"));
+        Ok(())
+    }
+
+    let mut balance = 100;
+    assert!(transfer(&log, 30, &mut balance).is_ok());
+    assert_eq!(balance, 70);
+    assert!(transfer(&log, 500, &mut balance).is_err());
+    assert_eq!(balance, 70);
+    assert_eq!(log.take(), ["commit 70", "rollback"]);
+}
+
+/// # example04 临时文件
+/// 无论处理成功, 返回错误还是 panic, 临时文件都会被删除; 需要保留时 defuse
+#[allow(unused)]
+fn example04() {
+    use std::path::{Path, PathBuf};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("scope_guard_{}_{name}", std::process::id()))
+    }
+
+    fn temp_file(name: &str, content: &str) -> scope_guard::ScopeGuard<PathBuf, fn(PathBuf)> {
+        let path = temp_path(name);
+        std::fs::write(&path, content).unwrap();
+        guard(path, |path| {
+            // 文件可能已经被删除, 忽略错误
+            let _ = std::fs::remove_file(path);
+        })
+    }
+
+    fn process(path: &Path) -> Result<usize, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        content
+            .trim()
+            .parse::<usize>()
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    // 正常处理
+    let file = temp_file("ok", "42");
+    let path = file.clone();
+    assert_eq!(process(&file), Ok(42));
+    drop(file);
+    assert!(!path.exists());
+
+    // 返回错误
+    let path = {
+        let file = temp_file("err", "not a number");
+        assert!(process(&file).is_err());
+        file.clone()
+    };
+    assert!(!path.exists());
+
+    // panic
+    let path = temp_path("panic");
+    let result = catch_panic(|| {
+        let file = temp_file("panic", "");
+        assert!(file.exists());
+        panic!("处理临时文件时 panic");
+    });
+    assert!(result.is_err());
+    assert!(!path.exists());
+
+    // 保留文件: defuse 之后由调用方负责删除
+    let kept = temp_file("kept", "1").defuse();
+    assert!(kept.exists());
+    std::fs::remove_file(&kept).unwrap();
+}
+
+/// # example05 释放锁
+/// 一个最简单的自旋锁, 解锁交给守卫, panic 时同样会解锁, 不会导致其他线程永远等待
+#[allow(unused)]
+fn example05() {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    struct SpinLock {
+        locked: AtomicBool,
+    }
+
+    impl SpinLock {
+        fn lock(&self) -> scope_guard::ScopeGuard<&SpinLock, fn(&SpinLock)> {
+            while self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                std::hint::spin_loop();
+            }
+            guard(self, |lock| lock.locked.store(false, Ordering::Release))
+        }
+    }
+
+    static LOCK: SpinLock = SpinLock {
+        locked: AtomicBool::new(false),
+    };
+    static ENTERED: AtomicUsize = AtomicUsize::new(0);
+
+    // 持有锁时 panic
+    let result = catch_panic(|| {
+        let _lock = LOCK.lock();
+        panic!("持有锁时 panic");
+    });
+    assert!(result.is_err());
+    assert!(!LOCK.locked.load(Ordering::Relaxed));
+
+    // 其他线程依然可以获取锁
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let _lock = LOCK.lock();
+                    // 非原子的 "读-改-写", 只有在锁的保护下才正确
+                    let n = ENTERED.load(Ordering::Relaxed);
+                    ENTERED.store(n + 1, Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    assert_eq!(ENTERED.load(Ordering::Relaxed), 4000);
+
+    // 与 std::sync::Mutex 对比: 解锁同样通过 Drop, 但持有锁时 panic 会让 Mutex 中毒
+    let mutex = std::sync::Mutex::new(0);
+    let _ = catch_panic(|| {
+        let _guard = mutex.lock().unwrap();
+        panic!("持有 Mutex 时 panic");
+    });
+    assert!(mutex.is_poisoned());
+}
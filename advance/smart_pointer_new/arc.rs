@@ -6,12 +6,21 @@
 #[allow(unused)]
 struct Description;
 
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+mod my_arc;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use my_arc::{unsize_arc, MyArc};
 use std::sync::Arc;
 
-fn main() {
-    // code_example01();
-    // code_example02();
-    // code_example03();
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("code_example01", code_example01),
+        ("code_example02", code_example02),
+        ("code_example03", code_example03),
+    ])
 }
 
 /// `Arc<T>` 主要是与 clone() 配合使用  
@@ -99,7 +108,64 @@ fn code_example02() {
 /// `Arc<dyn Trait>`  
 /// 可以將 `Box<dyn Trait>` 改成 `Arc<dyn Trait>`
 /// ```
+///
+/// 与 `Box<dyn Trait>` 不同, 同一个 trait 对象可以被多个线程共享; 因为要跨线程, trait 需要 Send + Sync
+///
+/// 手写的 MyArc 见 my_arc.rs, 更多示例见 my_arc_demo.rs
 #[allow(unused)]
 fn code_example03() {
-    ()
+    trait Shape: Send + Sync {
+        fn area(&self) -> f64;
+        fn name(&self) -> String;
+    }
+    struct Circle {
+        r: f64,
+    }
+    struct Square {
+        side: f64,
+    }
+    impl Shape for Circle {
+        fn area(&self) -> f64 {
+            3.0 * self.r * self.r
+        }
+        fn name(&self) -> String {
+            format!("circle({})", self.r)
+        }
+    }
+    impl Shape for Square {
+        fn area(&self) -> f64 {
+            self.side * self.side
+        }
+        fn name(&self) -> String {
+            format!("square({})", self.side)
+        }
+    }
+
+    // Arc<Circle> 自动转换为 Arc<dyn Shape>
+    let shapes: Vec<Arc<dyn Shape>> = vec![
+        Arc::new(Circle { r: 1.0 }),
+        Arc::new(Square { side: 2.0 }),
+    ];
+    let shared = Arc::clone(&shapes[0]);
+    let total = std::thread::spawn(move || shared.area()).join().unwrap() + shapes[1].area();
+    assert_eq!(total, 7.0);
+    assert_eq!(Arc::strong_count(&shapes[0]), 1);
+
+    // MyArc 不能自动转换 (依赖不稳定的 CoerceUnsized), 通过 unsize_arc! 转换
+    let shapes: Vec<MyArc<dyn Shape>> = vec![
+        unsize_arc!(MyArc::new(Circle { r: 1.0 }), dyn Shape),
+        unsize_arc!(MyArc::new(Square { side: 2.0 }), dyn Shape),
+    ];
+    let names: Vec<String> = std::thread::scope(|s| {
+        let handles: Vec<_> = shapes
+            .iter()
+            .map(|shape| {
+                let shape = MyArc::clone(shape);
+                s.spawn(move || shape.name())
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(names, ["circle(1)", "square(2)"]);
+    assert_eq!(MyArc::strong_count(&shapes[1]), 1);
 }
\ No newline at end of file
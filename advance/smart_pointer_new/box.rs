@@ -61,6 +61,7 @@ fn code_example03() {
     // Box 是一个类型整体, 可以将资源强行创建在堆上, 并获得所有权
     // 其生命周期可以被精确的掌控
     // ! 注意: 堆上的资源默认与整个程序存在的时间一样久
+    // 即使只是一个 u32 也会分配堆内存, 小的值内联存放的 SmallBox 见 small_box.rs
     fn foo() -> Box<u32> {
         let i = 100u32;
         Box::new(i)
@@ -102,6 +103,7 @@ fn code_example05() {
     let _valye = *boxed;
 
     // 对于 move 语义的类型来说就不一样了, 会发生所有权转义
+    // (SmallBox 通过 SmallBox::into_inner 取出值, 见 small_box_demo.rs example01)
     let boxed: Box<String> = Box::new(String::new());
     // 堆上的值再次回到栈上
     let _s = *boxed;
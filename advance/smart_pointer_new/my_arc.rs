@@ -0,0 +1,413 @@
+/// 手写的 Arc<T> 与 Weak<T>
+///
+/// 结构与 smart_pointer/my_rc.rs 中的 MyRc 相同 (strong / weak 两个计数, 所有 MyArc 共同持有 1 个 weak),
+/// 区别在于计数是原子的, 并且需要用 Ordering 保证析构值时能看到其他线程对值的所有访问:
+///
+/// - clone: `fetch_add(Relaxed)`, 已经持有一个 MyArc, 不需要同步任何数据
+/// - drop: `fetch_sub(Release)`, 把本线程对值的访问 "发布" 出去;
+///   最后一个 drop 的线程在析构前执行 `fence(Acquire)`, 与之前所有的 Release 同步,
+///   保证析构时其他线程对值的访问都已经完成 (否则可能一边析构, 另一边还在读)
+/// - upgrade: 计数为 0 时不能再增加, 使用 compare_exchange 循环
+/// - 计数超过 isize::MAX 时直接终止进程: clone 先加再检查, 在检查之前其他线程最多再加 "线程数" 次, 不会回绕
+///
+/// `T: ?Sized`, 通过 `unsize_arc!` 得到 `MyArc<dyn Trait>` (与 good_box.rs 中的 unsize_box! 相同)
+///
+/// 计数只通过字段的裸指针访问 (见 WeakInner), 不会创建覆盖 data 的 `&ArcInner<T>`:
+/// 一个线程 upgrade / drop Weak 的同时, 另一个线程可能正在析构 data
+#[allow(unused)]
+struct Description;
+
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+/// 计数的上限, 超过时终止进程
+const MAX_REFCOUNT: usize = isize::MAX as usize;
+
+/// weak 计数被 get_mut 暂时 "锁住" 时的值, 见 is_unique
+const WEAK_LOCKED: usize = usize::MAX;
+
+// repr(C): 字段按声明的顺序排列, data 的偏移只取决于 T 的对齐, unsize 依赖这一点
+#[repr(C)]
+struct ArcInner<T: ?Sized> {
+    strong: AtomicUsize,
+    weak: AtomicUsize,
+    /// 分配时的布局: 释放内存时 data 可能已经析构, 不能再通过 `Layout::for_value` 计算
+    layout: Layout,
+    data: T,
+}
+
+/// 两个计数的引用, 不覆盖 data
+struct WeakInner<'a> {
+    strong: &'a AtomicUsize,
+    weak: &'a AtomicUsize,
+}
+
+/// # Safety
+/// ptr 指向的 ArcInner 还没有被释放, 返回的引用不能在内存释放之后使用
+unsafe fn counts<'a, T: ?Sized>(ptr: NonNull<ArcInner<T>>) -> WeakInner<'a> {
+    let ptr = ptr.as_ptr();
+    WeakInner {
+        strong: &*ptr::addr_of!((*ptr).strong),
+        weak: &*ptr::addr_of!((*ptr).weak),
+    }
+}
+
+/// # MyArc 线程安全的引用计数指针
+pub struct MyArc<T: ?Sized> {
+    ptr: NonNull<ArcInner<T>>,
+    _owns: PhantomData<ArcInner<T>>,
+}
+
+/// # Weak 不拥有值的弱引用
+pub struct Weak<T: ?Sized> {
+    // Weak::new 创建的 Weak 不指向任何 ArcInner
+    ptr: Option<NonNull<ArcInner<T>>>,
+}
+
+// 和 Arc 一样: 多个线程可以同时通过 &T 访问值 (需要 Sync), 最后一个线程负责析构值 (需要 Send)
+unsafe impl<T: ?Sized + Send + Sync> Send for MyArc<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for MyArc<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Send for Weak<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for Weak<T> {}
+
+/// 减少 weak 计数, 降为 0 时释放内存
+///
+/// # Safety
+/// ptr 指向的 ArcInner 还没有被释放, 其中的值已经被析构, 调用后不能再使用 ptr
+unsafe fn release_weak<T: ?Sized>(ptr: NonNull<ArcInner<T>>) {
+    if counts(ptr).weak.fetch_sub(1, Ordering::Release) != 1 {
+        return;
+    }
+    // 与其他 Weak drop 时的 Release 同步, 之后没有任何线程在访问这块内存
+    atomic::fence(Ordering::Acquire);
+    let layout = ptr::addr_of!((*ptr.as_ptr()).layout).read();
+    alloc::dealloc(ptr.as_ptr().cast(), layout);
+}
+
+#[allow(unused)]
+impl<T> MyArc<T> {
+    pub fn new(data: T) -> MyArc<T> {
+        let inner = Box::new(ArcInner {
+            strong: AtomicUsize::new(1),
+            weak: AtomicUsize::new(1),
+            layout: Layout::new::<ArcInner<T>>(),
+            data,
+        });
+        MyArc {
+            ptr: NonNull::from(Box::leak(inner)),
+            _owns: PhantomData,
+        }
+    }
+
+    /// 只有一个 MyArc 时取出值, 否则原样返回
+    pub fn try_unwrap(this: MyArc<T>) -> Result<T, MyArc<T>> {
+        if this
+            .counts()
+            .strong
+            .compare_exchange(1, 0, Ordering::Relaxed, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(this);
+        }
+        // 与 drop 相同, 取出值之前先与其他 MyArc drop 时的 Release 同步
+        atomic::fence(Ordering::Acquire);
+        let this = std::mem::ManuallyDrop::new(this);
+        unsafe {
+            let data = ptr::read(ptr::addr_of!((*this.ptr.as_ptr()).data));
+            release_weak(this.ptr);
+            Ok(data)
+        }
+    }
+}
+
+#[allow(unused)]
+impl<T: ?Sized> MyArc<T> {
+    fn counts(&self) -> WeakInner<'_> {
+        // 只要还有 MyArc, ArcInner 就不会被释放
+        unsafe { counts(self.ptr) }
+    }
+
+    /// 其他线程可能同时在修改计数, 结果只能作为参考
+    pub fn strong_count(this: &MyArc<T>) -> usize {
+        this.counts().strong.load(Ordering::Relaxed)
+    }
+
+    /// Weak 的数量, 不包括所有 MyArc 共同持有的 1
+    pub fn weak_count(this: &MyArc<T>) -> usize {
+        match this.counts().weak.load(Ordering::Relaxed) {
+            // get_mut 正在检查, 说明此时没有 Weak
+            WEAK_LOCKED => 0,
+            weak => weak - 1,
+        }
+    }
+
+    pub fn downgrade(this: &MyArc<T>) -> Weak<T> {
+        let weak = this.counts().weak;
+        let mut current = weak.load(Ordering::Relaxed);
+        loop {
+            // 等待 is_unique 检查结束
+            if current == WEAK_LOCKED {
+                std::hint::spin_loop();
+                current = weak.load(Ordering::Relaxed);
+                continue;
+            }
+            if current > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire 与 is_unique 解锁时的 Release 同步
+            match weak.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Weak {
+                        ptr: Some(this.ptr),
+                    }
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    pub fn ptr_eq(this: &MyArc<T>, other: &MyArc<T>) -> bool {
+        ptr::addr_eq(this.ptr.as_ptr(), other.ptr.as_ptr())
+    }
+
+    /// 是否只有这一个 MyArc 且没有 Weak
+    ///
+    /// 不能先检查 weak 再检查 strong: 两次检查之间, 另一个 MyArc 可以先 downgrade 再 drop,
+    /// 于是两次检查都通过, 但那个 Weak 之后还能 upgrade. 所以检查 strong 期间先把 weak 锁住, 让 downgrade 等待
+    fn is_unique(&mut self) -> bool {
+        let inner = self.counts();
+        if inner
+            .weak
+            .compare_exchange(1, WEAK_LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        // Acquire 与其他 MyArc drop 时的 Release 同步, 之后修改值不会与它们对值的访问冲突
+        let unique = inner.strong.load(Ordering::Acquire) == 1;
+        inner.weak.store(1, Ordering::Release);
+        unique
+    }
+
+    /// 没有其他 MyArc 和 Weak 时才能得到可变引用
+    pub fn get_mut(this: &mut MyArc<T>) -> Option<&mut T> {
+        if this.is_unique() {
+            Some(unsafe { &mut (*this.ptr.as_ptr()).data })
+        } else {
+            None
+        }
+    }
+
+    /// 放弃所有权, 返回指向值的指针, 供 unsize 重新接管
+    fn into_raw(this: MyArc<T>) -> *const T {
+        let this = std::mem::ManuallyDrop::new(this);
+        unsafe { ptr::addr_of!((*this.ptr.as_ptr()).data) }
+    }
+
+    /// 把 MyArc<T> 转换为 MyArc<U>, U 通常是 dyn Trait 或切片, 一般通过 unsize_arc! 调用
+    ///
+    /// # Safety
+    /// f 必须返回同一个地址, 且 U 必须是 T 的 unsized 类型 (指向同一个值, 只是增加了虚表或长度)
+    pub unsafe fn unsize<U: ?Sized>(
+        this: MyArc<T>,
+        f: impl FnOnce(*const T) -> *const U,
+    ) -> MyArc<U> {
+        let inner = this.ptr.as_ptr();
+        let original = MyArc::into_raw(this);
+        let data = f(original);
+        debug_assert!(ptr::addr_eq(data, original));
+        // ArcInner 是 repr(C), 值的偏移只取决于对齐, T 和 U 的对齐相同, 所以偏移也相同;
+        // byte_sub 保留了 data 中的虚表或长度
+        let offset = (original as *const u8).offset_from(inner as *const u8) as usize;
+        let inner = data.byte_sub(offset) as *mut ArcInner<U>;
+        MyArc {
+            ptr: NonNull::new_unchecked(inner),
+            _owns: PhantomData,
+        }
+    }
+}
+
+/// 把 `MyArc<T>` 转换为 `MyArc<dyn Trait>` 或 `MyArc<[T]>`
+///
+/// ```ignore
+/// let shape: MyArc<dyn Shape> = unsize_arc!(MyArc::new(Circle), dyn Shape);
+/// ```
+///
+/// 通过类型标注触发裸指针的隐式转换, 只允许合法的 unsized 转换
+#[allow(unused_macros)]
+macro_rules! unsize_arc {
+    ($arc:expr, $target:ty) => {{
+        let arc = $arc;
+        // 闭包只做隐式转换, 地址不变
+        unsafe {
+            $crate::my_arc::MyArc::unsize(arc, |data| {
+                let data: *const $target = data;
+                data
+            })
+        }
+    }};
+}
+#[allow(unused_imports)]
+pub(crate) use unsize_arc;
+
+#[allow(unused)]
+impl<T: Clone> MyArc<T> {
+    /// 写时复制: 得到可变引用, 必要时先把值复制一份
+    ///
+    /// - 有其他 MyArc: 复制值, this 指向新的副本
+    /// - 只有 Weak: 把值移动到新的 ArcInner, 原来的 Weak 都无法再 upgrade
+    pub fn make_mut(this: &mut MyArc<T>) -> &mut T {
+        // 把 strong 从 1 改为 0, 成功说明没有其他 MyArc, 同时阻止 Weak 在这期间 upgrade
+        if this
+            .counts()
+            .strong
+            .compare_exchange(1, 0, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            *this = MyArc::new(T::clone(this));
+        } else if this.counts().weak.load(Ordering::Relaxed) != 1 {
+            unsafe {
+                let data = ptr::read(ptr::addr_of!((*this.ptr.as_ptr()).data));
+                // strong 已经是 0, 放弃所有 MyArc 共同持有的 weak
+                release_weak(this.ptr);
+                // 原来的 MyArc 已经放弃了它的计数, 不能再 drop
+                ptr::write(this, MyArc::new(data));
+            }
+        } else {
+            // 没有 Weak, 恢复 strong
+            this.counts().strong.store(1, Ordering::Release);
+        }
+        unsafe { &mut (*this.ptr.as_ptr()).data }
+    }
+}
+
+impl<T: ?Sized> Clone for MyArc<T> {
+    fn clone(&self) -> Self {
+        let old = self.counts().strong.fetch_add(1, Ordering::Relaxed);
+        if old > MAX_REFCOUNT {
+            std::process::abort();
+        }
+        MyArc {
+            ptr: self.ptr,
+            _owns: PhantomData,
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for MyArc<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // 持有 MyArc 期间 data 不会被析构
+        unsafe { &(*self.ptr.as_ptr()).data }
+    }
+}
+
+impl<T: ?Sized> Drop for MyArc<T> {
+    fn drop(&mut self) {
+        // Release: 本线程对值的访问都发生在这次减少之前
+        if self.counts().strong.fetch_sub(1, Ordering::Release) != 1 {
+            return;
+        }
+        // Acquire: 与其他线程减少计数时的 Release 同步, 它们对值的访问都发生在析构之前
+        atomic::fence(Ordering::Acquire);
+        unsafe {
+            ptr::drop_in_place(ptr::addr_of_mut!((*self.ptr.as_ptr()).data));
+            release_weak(self.ptr);
+        }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for MyArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display> fmt::Display for MyArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[allow(unused)]
+impl<T: ?Sized> Weak<T> {
+    /// 不指向任何值的 Weak, upgrade 总是返回 None
+    pub fn new() -> Weak<T> {
+        Weak { ptr: None }
+    }
+
+    fn inner(&self) -> Option<WeakInner<'_>> {
+        // 只要还有 Weak, ArcInner 就不会被释放; data 可能已经 (或正在) 被析构, 只能访问计数
+        self.ptr.map(|ptr| unsafe { counts(ptr) })
+    }
+
+    /// 值还没有被析构时得到一个新的 MyArc
+    pub fn upgrade(&self) -> Option<MyArc<T>> {
+        let inner = self.inner()?;
+        let mut current = inner.strong.load(Ordering::Relaxed);
+        loop {
+            // 计数为 0 时值已经 (或正在) 被析构, 不能再增加
+            if current == 0 {
+                return None;
+            }
+            if current > MAX_REFCOUNT {
+                std::process::abort();
+            }
+            // Acquire 与 make_mut 恢复 strong 时的 Release 同步
+            match inner.strong.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(MyArc {
+                        ptr: self.ptr?,
+                        _owns: PhantomData,
+                    })
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    pub fn strong_count(&self) -> usize {
+        self.inner()
+            .map_or(0, |inner| inner.strong.load(Ordering::Relaxed))
+    }
+}
+
+impl<T: ?Sized> Clone for Weak<T> {
+    fn clone(&self) -> Self {
+        if let Some(inner) = self.inner() {
+            // 已经持有一个 Weak, 计数不可能处于 WEAK_LOCKED
+            if inner.weak.fetch_add(1, Ordering::Relaxed) > MAX_REFCOUNT {
+                std::process::abort();
+            }
+        }
+        Weak { ptr: self.ptr }
+    }
+}
+
+impl<T: ?Sized> Drop for Weak<T> {
+    fn drop(&mut self) {
+        if let Some(ptr) = self.ptr {
+            unsafe { release_weak(ptr) };
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Weak<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(Weak)")
+    }
+}
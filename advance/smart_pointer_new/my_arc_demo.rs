@@ -0,0 +1,348 @@
+/// 手写的 Arc<T>
+///
+/// MyArc 的实现见 my_arc.rs
+///
+/// 使用 Miri 检查未定义行为 (数据竞争, 别名规则, 释放后使用), Miri 下压力测试会减少轮数:
+/// `rustup component add --toolchain nightly miri rust-src && cargo +nightly miri setup`
+/// `$(rustup which --toolchain nightly miri) --sysroot $(cargo +nightly miri setup --print-sysroot) --edition 2021 my_arc_demo.rs`
+///
+/// loom 在这里不可用 (没有 Cargo.toml), 用以下方式代替:
+/// - example05: 用 global_variable/memory_model.rs 中的模型检查器穷举 drop 的 Ordering
+/// - example04 / example06: 多线程压力测试, 也可以配合 AddressSanitizer 检查释放后使用与重复释放:
+///   `rustc +nightly -Zsanitizer=address --edition 2021 my_arc_demo.rs`
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[allow(unused)]
+#[path = "../global_variable/memory_model.rs"]
+mod memory_model;
+mod my_arc;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+use my_arc::{unsize_arc, MyArc, Weak};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+        ("example06", example06),
+    ])
+}
+
+/// drop 时给计数加 1
+struct DropCounter<'a>(&'a AtomicUsize);
+
+impl Drop for DropCounter<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// # example01 clone 只增加计数, 最后一个 MyArc drop 时析构值
+#[allow(unused)]
+fn example01() {
+    let drops = AtomicUsize::new(0);
+    let a = MyArc::new(DropCounter(&drops));
+    let clones: Vec<_> = (0..4).map(|_| MyArc::clone(&a)).collect();
+    assert_eq!(MyArc::strong_count(&a), 5);
+    assert!(clones.iter().all(|c| MyArc::ptr_eq(c, &a)));
+
+    std::thread::scope(|s| {
+        for c in clones {
+            s.spawn(move || drop(c));
+        }
+    });
+    assert_eq!(MyArc::strong_count(&a), 1);
+    assert_eq!(drops.load(Ordering::Relaxed), 0);
+    drop(a);
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+
+    // 可以保存 dyn Trait 和切片
+    let debug: MyArc<dyn std::fmt::Debug + Send + Sync> =
+        unsize_arc!(MyArc::new(vec![1, 2]), dyn std::fmt::Debug + Send + Sync);
+    assert_eq!(format!("{debug:?}"), "[1, 2]");
+    let slice: MyArc<[u8]> = unsize_arc!(MyArc::new(*b"abc"), [u8]);
+    assert_eq!(&*slice, b"abc");
+}
+
+/// # example02 Weak
+#[allow(unused)]
+fn example02() {
+    let drops = AtomicUsize::new(0);
+    let strong = MyArc::new(DropCounter(&drops));
+    let weak = MyArc::downgrade(&strong);
+    let weak2 = Weak::clone(&weak);
+    assert_eq!(MyArc::weak_count(&strong), 2);
+
+    let upgraded = weak.upgrade().unwrap();
+    assert_eq!(weak.strong_count(), 2);
+    drop((strong, upgraded));
+    // 值已经析构, 但内存还被 Weak 持有
+    assert_eq!(drops.load(Ordering::Relaxed), 1);
+    assert!(weak.upgrade().is_none());
+    assert_eq!(weak2.strong_count(), 0);
+
+    let empty: Weak<dyn std::fmt::Debug> = Weak::new();
+    assert!(empty.upgrade().is_none());
+}
+
+/// # example03 get_mut / make_mut / try_unwrap
+#[allow(unused)]
+fn example03() {
+    let mut a = MyArc::new(String::from("a"));
+    MyArc::get_mut(&mut a).unwrap().push('1');
+
+    // 有其他 MyArc 或 Weak 时不能直接修改
+    let b = MyArc::clone(&a);
+    assert!(MyArc::get_mut(&mut a).is_none());
+    drop(b);
+    let weak = MyArc::downgrade(&a);
+    assert!(MyArc::get_mut(&mut a).is_none());
+    drop(weak);
+
+    // 写时复制: 有其他 MyArc 时复制一份
+    let mut shared = MyArc::clone(&a);
+    MyArc::make_mut(&mut shared).push('2');
+    assert_eq!((a.as_str(), shared.as_str()), ("a1", "a12"));
+    assert!(!MyArc::ptr_eq(&a, &shared));
+
+    // 只有 Weak 时把值移走, Weak 失效
+    let weak = MyArc::downgrade(&shared);
+    MyArc::make_mut(&mut shared).push('3');
+    assert!(weak.upgrade().is_none());
+    assert_eq!(*shared, "a123");
+
+    // 只有自己时直接修改, 不复制
+    let before = MyArc::clone(&shared);
+    let address: *const String = &*before;
+    drop(before);
+    MyArc::make_mut(&mut shared).push('4');
+    assert!(std::ptr::eq(&*shared, address));
+
+    let c = MyArc::clone(&a);
+    let a = MyArc::try_unwrap(a).unwrap_err();
+    drop(c);
+    assert_eq!(MyArc::try_unwrap(a).unwrap(), "a1");
+}
+
+/// # example04 多线程压力测试
+#[allow(unused)]
+fn example04() {
+    const THREADS: usize = 8;
+    const ROUNDS: usize = if cfg!(miri) { 20 } else { 2_000 };
+
+    /// 析构后写入 DEAD, 之后如果还有线程读到它, 说明发生了释放后使用
+    struct Canary(AtomicUsize);
+    const ALIVE: usize = 0xA11CE;
+    const DEAD: usize = 0xDEAD;
+    impl Drop for Canary {
+        fn drop(&mut self) {
+            assert_eq!(self.0.swap(DEAD, Ordering::Relaxed), ALIVE, "重复析构");
+        }
+    }
+
+    // 1. 每一轮所有线程同时 clone 和 drop, 值只被析构一次, 且析构发生在所有访问之后
+    for _ in 0..ROUNDS / 10 {
+        let drops = AtomicUsize::new(0);
+        let barrier = Barrier::new(THREADS);
+        let arc = MyArc::new((Canary(AtomicUsize::new(ALIVE)), DropCounter(&drops)));
+        std::thread::scope(|s| {
+            for _ in 0..THREADS {
+                let arc = MyArc::clone(&arc);
+                let barrier = &barrier;
+                s.spawn(move || {
+                    barrier.wait();
+                    for _ in 0..10 {
+                        let c = MyArc::clone(&arc);
+                        assert_eq!(c.0 .0.load(Ordering::Relaxed), ALIVE);
+                    }
+                });
+            }
+            drop(arc);
+        });
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+
+    // 2. 最后一个 MyArc drop 的同时, 其他线程在 upgrade: 要么失败, 要么拿到还活着的值
+    let upgraded = AtomicUsize::new(0);
+    for _ in 0..ROUNDS {
+        let arc = MyArc::new(Canary(AtomicUsize::new(ALIVE)));
+        let weak = MyArc::downgrade(&arc);
+        let barrier = Barrier::new(3);
+        std::thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    barrier.wait();
+                    if let Some(arc) = weak.upgrade() {
+                        assert_eq!(arc.0.load(Ordering::Relaxed), ALIVE);
+                        upgraded.fetch_add(1, Ordering::Relaxed);
+                    }
+                });
+            }
+            barrier.wait();
+            drop(arc);
+        });
+        assert!(weak.upgrade().is_none());
+    }
+    println!("upgrade 成功 {} 次", upgraded.load(Ordering::Relaxed));
+
+    // 3. get_mut 与 downgrade 竞争: get_mut 成功时, 不能有 Weak 在之后 upgrade 成功
+    for _ in 0..ROUNDS {
+        let mut arc = MyArc::new(0);
+        let other = MyArc::clone(&arc);
+        let barrier = Barrier::new(2);
+        let weak = std::thread::scope(|s| {
+            let handle = s.spawn(|| {
+                let other = other;
+                barrier.wait();
+                MyArc::downgrade(&other)
+                // other 在这里 drop
+            });
+            barrier.wait();
+            let unique = MyArc::get_mut(&mut arc).is_some();
+            let weak = handle.join().unwrap();
+            (unique, weak)
+        });
+        let (unique, weak) = weak;
+        // downgrade 完成之前 other 一直存在, 所以 get_mut 不可能成功
+        assert!(!unique);
+        assert_eq!(*weak.upgrade().unwrap(), 0);
+    }
+}
+
+/// # example05 用模型检查验证 drop 的 Ordering
+///
+/// ```text
+/// 线程 0 (倒数第二个 MyArc)         线程 1 (最后一个 MyArc)
+/// data.store(1, Relaxed)            drops.fetch_add(1, last)  // 等线程 0 先 drop
+/// drops.fetch_add(1, decrement)     r0 = data.load(Relaxed)   // 析构时读取值
+/// ```
+///
+/// 计数器以 "已经 drop 的次数" 表示; 模型不包含 fence,
+/// `fetch_sub(Release)` + `fence(Acquire)` 用一次 AcqRel 的读改写表示 (对这次读改写读到的 Release 序列效果相同)
+///
+/// 析构时读到 r0 == 0 说明线程 0 对值的访问还没有完成, 就可能发生释放后使用
+#[allow(unused)]
+fn example05() {
+    use memory_model::{explore, Litmus, Op};
+
+    fn arc_drop(decrement: Ordering, last: Ordering) -> Litmus {
+        const DATA: usize = 0;
+        const DROPS: usize = 1;
+        Litmus {
+            name: "arc drop",
+            locations: 2,
+            threads: vec![
+                vec![
+                    Op::Store {
+                        loc: DATA,
+                        value: 1,
+                        ordering: Ordering::Relaxed,
+                    },
+                    Op::CasWait {
+                        loc: DROPS,
+                        expected: 0,
+                        new: 1,
+                        ordering: decrement,
+                    },
+                ],
+                vec![
+                    Op::CasWait {
+                        loc: DROPS,
+                        expected: 1,
+                        new: 2,
+                        ordering: last,
+                    },
+                    Op::Load {
+                        loc: DATA,
+                        reg: 0,
+                        ordering: Ordering::Relaxed,
+                    },
+                ],
+            ],
+        }
+    }
+
+    let cases = [
+        // 全部 Relaxed
+        (Ordering::Relaxed, Ordering::Relaxed, true),
+        // 只有 Release, 最后一个线程没有 Acquire
+        (Ordering::Release, Ordering::Release, true),
+        // 只有 Acquire, 前面的线程没有 Release
+        (Ordering::Relaxed, Ordering::AcqRel, true),
+        // MyArc 的做法
+        (Ordering::Release, Ordering::AcqRel, false),
+    ];
+    for (decrement, last, stale_allowed) in cases {
+        let stale: Vec<u64> = explore(&arc_drop(decrement, last))
+            .into_keys()
+            .map(|outcome| outcome.registers[1][0])
+            .collect();
+        println!("decrement {decrement:?}, last {last:?}: 析构时可能读到 {stale:?}");
+        assert_eq!(stale.contains(&0), stale_allowed);
+    }
+}
+
+/// # example06 值的析构与 Weak 同时访问同一个 ArcInner
+/// 值持有指向自己的 Weak: 析构值时 drop 这个 Weak, 此时 data 正在被析构;
+/// 其他线程在最后一个 MyArc drop 的同时 upgrade / clone / drop Weak, 也是同样的情况.
+/// 这两种情况下访问计数都不能创建覆盖 data 的引用, Miri 会报告这样的未定义行为
+#[allow(unused)]
+fn example06() {
+    const ROUNDS: usize = if cfg!(miri) { 20 } else { 2_000 };
+
+    struct Node {
+        name: String,
+        me: std::sync::Mutex<Weak<Node>>,
+    }
+
+    let node = MyArc::new(Node {
+        name: String::from("self"),
+        me: std::sync::Mutex::new(Weak::new()),
+    });
+    *node.me.lock().unwrap() = MyArc::downgrade(&node);
+    let outside = MyArc::downgrade(&node);
+    let upgraded = node.me.lock().unwrap().upgrade().unwrap();
+    assert_eq!(upgraded.name, "self");
+    assert_eq!(MyArc::weak_count(&node), 2);
+    drop((upgraded, node));
+    // 值中的 Weak 已经随值一起 drop, 只剩 outside
+    assert!(outside.upgrade().is_none());
+    drop(outside);
+
+    // 最后一个 MyArc drop 的同时, 其他线程使用 Weak; 值本身也持有一个指向自己的 Weak
+    for _ in 0..ROUNDS {
+        let node = MyArc::new(Node {
+            name: String::from("node"),
+            me: std::sync::Mutex::new(Weak::new()),
+        });
+        *node.me.lock().unwrap() = MyArc::downgrade(&node);
+        let weak = MyArc::downgrade(&node);
+        let barrier = Barrier::new(3);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                barrier.wait();
+                if let Some(node) = weak.upgrade() {
+                    assert_eq!(node.name, "node");
+                }
+            });
+            s.spawn(|| {
+                barrier.wait();
+                let clone = Weak::clone(&weak);
+                assert!(clone.strong_count() <= 1);
+            });
+            barrier.wait();
+            drop(node);
+        });
+        assert!(weak.upgrade().is_none());
+        assert_eq!(weak.strong_count(), 0);
+    }
+}
@@ -0,0 +1,192 @@
+/// 小对象优化: SmallBox<T, N>
+///
+/// box.rs code_example03 / code_example05 中, 即使只是一个 u32, Box 也一定会分配堆内存;
+/// SmallBox 在自身中预留 N 字节, 放得下的值直接内联存放, 放不下 (或对齐超过 16) 的值才分配到堆上
+///
+/// 和 Box 一样可以存放特征对象, 通过 small_box! 创建:
+///
+/// ```ignore
+/// let button: SmallBox<dyn Drawer, 16> = small_box!(Button { bid: 1 }, dyn Drawer);
+/// ```
+///
+/// 内联的值会随着 SmallBox 一起移动, 所以不能像 Box 那样保存一个固定的指针:
+/// 每次访问时由 cast 把当前的地址转换为 `*mut T`, 转换过程中附加上 dyn Trait 的虚表或切片的长度
+///
+/// 代价是 SmallBox 本身更大: N 字节的空间 (16 字节对齐), 加上堆指针和 cast 两个字
+#[allow(unused)]
+struct Description;
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+
+/// 内联存放值的空间, 对齐要求超过 16 的值放到堆上
+///
+/// 放在 UnsafeCell 中: T 可能包含 Cell / RefCell / 原子类型, 会通过 &SmallBox 修改内联的值;
+/// 否则编译器会认为 &SmallBox 指向的内存不可变, 优化后读到旧的值
+#[repr(C, align(16))]
+struct Inline<const N: usize>(UnsafeCell<[MaybeUninit<u8>; N]>);
+
+/// # SmallBox 不超过 N 字节的值内联存放的 Box
+pub struct SmallBox<T: ?Sized, const N: usize> {
+    inline: Inline<N>,
+    /// 值在堆上时指向堆内存, 内联时为空指针
+    heap: *mut u8,
+    /// 把值的地址转换为 *mut T
+    cast: fn(*mut u8) -> *mut T,
+    // 表示拥有一个 T
+    _marker: PhantomData<T>,
+}
+
+#[allow(unused)]
+impl<T, const N: usize> SmallBox<T, N> {
+    pub fn new(value: T) -> SmallBox<T, N> {
+        // SAFETY: 闭包原样返回指针
+        unsafe { SmallBox::new_unsize(value, |p| p) }
+    }
+
+    /// 取出值
+    pub fn into_inner(this: SmallBox<T, N>) -> T {
+        let mut this = ManuallyDrop::new(this);
+        let data = this.as_mut_ptr();
+        if this.heap.is_null() {
+            // SAFETY: 值只会被读取一次, this 不会再 drop
+            unsafe { ptr::read(data) }
+        } else {
+            // SAFETY: heap 来自 new_unsize 中的 Box::into_raw
+            *unsafe { Box::from_raw(data) }
+        }
+    }
+}
+
+#[allow(unused)]
+impl<T: ?Sized, const N: usize> SmallBox<T, N> {
+    /// 存放一个 U 类型的值, 通过 coerce 把 *mut U 转换为 *mut T, 一般通过 small_box! 调用
+    ///
+    /// # Safety
+    /// coerce 必须原样返回传入的地址, 只允许 U 到 T 的 unsized 转换 (增加虚表或长度)
+    pub unsafe fn new_unsize<U>(value: U, coerce: fn(*mut U) -> *mut T) -> SmallBox<T, N> {
+        // SAFETY: U 是 Sized, *mut U 与 *mut u8 都是瘦指针, 两种函数指针的调用方式 (ABI) 相同
+        let cast = mem::transmute::<fn(*mut U) -> *mut T, fn(*mut u8) -> *mut T>(coerce);
+        let mut this = SmallBox {
+            inline: Inline(UnsafeCell::new([MaybeUninit::uninit(); N])),
+            heap: ptr::null_mut(),
+            cast,
+            _marker: PhantomData,
+        };
+        if SmallBox::<T, N>::fits::<U>() {
+            this.inline.0.get().cast::<U>().write(value);
+        } else {
+            this.heap = Box::into_raw(Box::new(value)).cast();
+        }
+        this
+    }
+
+    /// U 类型的值是否可以内联存放
+    pub const fn fits<U>() -> bool {
+        mem::size_of::<U>() <= N && mem::align_of::<U>() <= mem::align_of::<Inline<N>>()
+    }
+
+    /// 值是否分配在堆上
+    pub fn is_heap(this: &SmallBox<T, N>) -> bool {
+        !this.heap.is_null()
+    }
+
+    /// 值的地址, 内联时通过 UnsafeCell::get 得到, 允许通过共享引用修改
+    fn data(&self) -> *mut u8 {
+        if self.heap.is_null() {
+            self.inline.0.get().cast()
+        } else {
+            self.heap
+        }
+    }
+
+    fn as_ptr(&self) -> *const T {
+        (self.cast)(self.data())
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        (self.cast)(self.data())
+    }
+}
+
+// SmallBox 拥有 T, 与 Box<T> 相同
+unsafe impl<T: ?Sized + Send, const N: usize> Send for SmallBox<T, N> {}
+unsafe impl<T: ?Sized + Sync, const N: usize> Sync for SmallBox<T, N> {}
+
+impl<T: ?Sized, const N: usize> Deref for SmallBox<T, N> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // SAFETY: 值在 SmallBox drop 之前一直有效
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T: ?Sized, const N: usize> DerefMut for SmallBox<T, N> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.as_mut_ptr() }
+    }
+}
+
+impl<T: ?Sized, const N: usize> Drop for SmallBox<T, N> {
+    fn drop(&mut self) {
+        let data = self.as_mut_ptr();
+        if self.heap.is_null() {
+            // SAFETY: 内联的值只需要析构, 空间随 SmallBox 一起释放
+            unsafe { ptr::drop_in_place(data) };
+        } else {
+            // SAFETY: heap 来自 Box::into_raw, 由 Box 析构值并按 T 的布局释放内存
+            drop(unsafe { Box::from_raw(data) });
+        }
+    }
+}
+
+impl<T: Clone, const N: usize> Clone for SmallBox<T, N> {
+    fn clone(&self) -> SmallBox<T, N> {
+        SmallBox::new((**self).clone())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, const N: usize> fmt::Debug for SmallBox<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Display, const N: usize> fmt::Display for SmallBox<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+/// 创建 `SmallBox<dyn Trait, N>` 或 `SmallBox<[T], N>`
+///
+/// ```ignore
+/// let shape: SmallBox<dyn Shape, 16> = small_box!(Circle { r: 1.0 }, dyn Shape);
+/// // 目标类型可以从上下文推断时可以省略
+/// let shapes: Vec<SmallBox<dyn Shape, 16>> = vec![small_box!(Circle { r: 1.0 })];
+/// ```
+///
+/// 通过类型标注触发裸指针的隐式转换, 只允许合法的 unsized 转换
+#[allow(unused_macros)]
+macro_rules! small_box {
+    ($value:expr, $target:ty) => {{
+        let value = $value;
+        // 闭包只做隐式转换, 地址不变
+        unsafe {
+            $crate::small_box::SmallBox::new_unsize(value, |data| {
+                let data: *mut $target = data;
+                data
+            })
+        }
+    }};
+    ($value:expr) => {{
+        let value = $value;
+        unsafe { $crate::small_box::SmallBox::new_unsize(value, |data| data) }
+    }};
+}
+#[allow(unused_imports)]
+pub(crate) use small_box;
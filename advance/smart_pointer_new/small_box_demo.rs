@@ -0,0 +1,262 @@
+/// 小对象优化: SmallBox<T, N>
+///
+/// SmallBox 的实现见 small_box.rs, 与 Box<dyn Drawer> 的性能对比见 small_box_bench.rs
+///
+/// 可以使用 AddressSanitizer 检查内存错误:
+/// `rustc +nightly -Zsanitizer=address --edition 2021 small_box_demo.rs`
+///
+/// AddressSanitizer 只能发现越界和释放后使用, 别名规则之类的未定义行为 (例如 example03 中通过共享引用修改内联的 Cell)
+/// 需要使用 Miri 检查, Stacked Borrows 和 Tree Borrows 都应该通过:
+/// `rustup component add --toolchain nightly miri rust-src && cargo +nightly miri setup`
+/// `$(rustup which --toolchain nightly miri) --sysroot $(cargo +nightly miri setup --print-sysroot) --edition 2021 small_box_demo.rs`
+/// (再加上 `-Zmiri-tree-borrows` 运行一次)
+#[allow(unused)]
+struct Description;
+
+#[path = "../../errors/app_error.rs"]
+mod app_error;
+#[path = "../../errors/panic_boundary.rs"]
+mod panic_boundary;
+mod small_box;
+
+use small_box::{small_box, SmallBox};
+use std::cell::Cell;
+use std::fmt::Display;
+use std::rc::Rc;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("example01", example01),
+        ("example02", example02),
+        ("example03", example03),
+        ("example04", example04),
+        ("example05", example05),
+    ])
+}
+
+/// 与 box.rs 中相同的组件
+trait Drawer {
+    fn draw(&self) -> String;
+}
+
+struct Button {
+    bid: u32,
+}
+
+impl Drawer for Button {
+    fn draw(&self) -> String {
+        format!("这是屏幕上第 {} 个按钮", self.bid)
+    }
+}
+
+struct Select {
+    sid: i32,
+    options: Vec<String>,
+}
+
+impl Drawer for Select {
+    fn draw(&self) -> String {
+        format!("这个选择框贼难用 {} {:?}", self.sid, self.options)
+    }
+}
+
+/// 一个比较大的组件, 放不进 16 字节
+struct Table {
+    cells: [u64; 8],
+}
+
+impl Drawer for Table {
+    fn draw(&self) -> String {
+        format!("表格 {:?}", self.cells)
+    }
+}
+
+/// 每次 draw 都修改内部的计数
+struct Counter(std::cell::RefCell<u32>);
+
+impl Drawer for Counter {
+    fn draw(&self) -> String {
+        *self.0.borrow_mut() += 1;
+        format!("第 {} 次绘制", self.0.borrow())
+    }
+}
+
+/// drop 时给计数加 1
+struct DropCounter(Rc<Cell<usize>>);
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+/// # example01 小的值内联存放
+/// 对比 code_example03: Box<u32> 一定会分配堆内存
+#[allow(unused)]
+fn example01() {
+    let small: SmallBox<u32, 8> = SmallBox::new(100);
+    assert!(!SmallBox::is_heap(&small));
+    assert_eq!(*small, 100);
+
+    // 放不下的值分配到堆上, 用法完全相同
+    let large: SmallBox<[u64; 4], 8> = SmallBox::new([1, 2, 3, 4]);
+    assert!(SmallBox::is_heap(&large));
+    assert_eq!(large.iter().sum::<u64>(), 10);
+
+    // 对齐要求超过 16 的值同样放到堆上
+    #[repr(align(32))]
+    struct Aligned(u8);
+    let aligned: SmallBox<Aligned, 64> = SmallBox::new(Aligned(1));
+    assert!(SmallBox::is_heap(&aligned));
+    assert_eq!(&*aligned as *const Aligned as usize % 32, 0);
+
+    // 与 code_example05 一样可以把值取出来
+    let mut boxed: SmallBox<String, 32> = SmallBox::new(String::from("hjkl1"));
+    boxed.push_str("!!!");
+    assert_eq!(SmallBox::into_inner(boxed), "hjkl1!!!");
+
+    println!(
+        "Box<u32>: {} 字节, SmallBox<u32, 8>: {} 字节",
+        std::mem::size_of::<Box<u32>>(),
+        std::mem::size_of::<SmallBox<u32, 8>>()
+    );
+}
+
+/// # example02 特征对象
+/// box.rs example02 中的 Vec<Box<dyn Drawer>> 换成 Vec<SmallBox<dyn Drawer, 32>>
+#[allow(unused)]
+fn example02() {
+    let components: Vec<SmallBox<dyn Drawer, 32>> = vec![
+        small_box!(Button { bid: 1 }),
+        small_box!(Select {
+            sid: 2,
+            options: vec!["a".to_string()],
+        }),
+        small_box!(Table { cells: [7; 8] }),
+    ];
+    // Button 和 Select 内联存放, Table 有 64 字节, 放到堆上
+    let on_heap: Vec<bool> = components.iter().map(SmallBox::is_heap).collect();
+    assert_eq!(on_heap, [false, false, true]);
+    for component in &components {
+        println!("{}", component.draw());
+    }
+    assert_eq!(components[0].draw(), "这是屏幕上第 1 个按钮");
+
+    // 其他的特征对象和切片
+    let text: SmallBox<dyn Display, 16> = small_box!(42u8, dyn Display);
+    assert_eq!(text.to_string(), "42");
+    let slice: SmallBox<[u16], 16> = small_box!([1u16, 2, 3], [u16]);
+    assert_eq!(slice.len(), 3);
+    let closure: SmallBox<dyn Fn(i32) -> i32, 16> = small_box!(|x| x + 1, dyn Fn(i32) -> i32);
+    assert_eq!(closure(1), 2);
+}
+
+/// # example03 移动之后依然可以访问
+/// 内联的值随 SmallBox 一起移动, 每次访问时根据当前的地址重新计算指针
+#[allow(unused)]
+fn example03() {
+    fn make(bid: u32) -> SmallBox<dyn Drawer, 16> {
+        let button = small_box!(Button { bid }, dyn Drawer);
+        // 返回时移动
+        button
+    }
+
+    let first = make(1);
+    let address = &*first as *const dyn Drawer as *const u8;
+    // 移动到 Vec 中, 再移动到 Box 中
+    let mut moved = vec![first];
+    moved.push(make(2));
+    let boxed = Box::new(moved.remove(0));
+    assert_ne!(&**boxed as *const dyn Drawer as *const u8, address);
+    assert_eq!(boxed.draw(), "这是屏幕上第 1 个按钮");
+
+    // 通过 DerefMut 修改内联的值
+    let mut counter: SmallBox<u32, 4> = SmallBox::new(0);
+    for _ in 0..10 {
+        *counter += 1;
+    }
+    // 修改之后再移动
+    let counter = [counter];
+    assert_eq!(*counter[0], 10);
+
+    // 通过共享引用修改内联的 Cell, 使用 -O 编译时同样需要读到新的值
+    fn bump(b: &SmallBox<Cell<u32>, 16>) {
+        b.set(b.get() + 1);
+    }
+    let cell: SmallBox<Cell<u32>, 16> = SmallBox::new(Cell::new(41));
+    assert!(!SmallBox::is_heap(&cell));
+    bump(std::hint::black_box(&cell));
+    assert_eq!(cell.get(), 42);
+    // 特征对象背后的 RefCell 也一样
+    let shared: SmallBox<dyn Drawer, 32> = small_box!(Counter(std::cell::RefCell::new(0)));
+    assert!(!SmallBox::is_heap(&shared));
+    for _ in 0..3 {
+        shared.draw();
+    }
+    assert_eq!(shared.draw(), "第 4 次绘制");
+}
+
+/// # example04 析构
+/// 内联和堆上的值都只析构一次; 堆上的值按照实际类型的布局释放内存
+#[allow(unused)]
+fn example04() {
+    let drops = Rc::new(Cell::new(0));
+    {
+        let inline: SmallBox<DropCounter, 8> = SmallBox::new(DropCounter(Rc::clone(&drops)));
+        let heap: SmallBox<(DropCounter, [u8; 64]), 8> =
+            SmallBox::new((DropCounter(Rc::clone(&drops)), [0; 64]));
+        assert!(!SmallBox::is_heap(&inline) && SmallBox::is_heap(&heap));
+        // 特征对象同样会调用实际类型的 drop
+        let any: SmallBox<dyn std::any::Any, 8> =
+            small_box!(DropCounter(Rc::clone(&drops)), dyn std::any::Any);
+        assert!(any.is::<DropCounter>());
+    }
+    assert_eq!(drops.get(), 3);
+
+    // into_inner 之后由调用方析构, SmallBox 不会再析构一次
+    let boxed: SmallBox<DropCounter, 8> = SmallBox::new(DropCounter(Rc::clone(&drops)));
+    let value = SmallBox::into_inner(boxed);
+    assert_eq!(drops.get(), 3);
+    drop(value);
+    assert_eq!(drops.get(), 4);
+    let boxed: SmallBox<(DropCounter, [u8; 64]), 8> =
+        SmallBox::new((DropCounter(Rc::clone(&drops)), [0; 64]));
+    let value = SmallBox::into_inner(boxed);
+    drop(value);
+    assert_eq!(drops.get(), 5);
+
+    // N 为 0 时只有零大小的值内联存放
+    let button: SmallBox<dyn Drawer, 0> = small_box!(Button { bid: 0 });
+    assert!(SmallBox::is_heap(&button));
+    let empty: SmallBox<(), 0> = SmallBox::new(());
+    assert!(!SmallBox::is_heap(&empty));
+}
+
+/// # example05 值的大小决定存放位置
+/// 同一个 Vec<SmallBox<dyn Drawer, N>> 中, 不同的 N 内联存放的组件不同
+#[allow(unused)]
+fn example05() {
+    fn count_inline<const N: usize>() -> usize {
+        let components: Vec<SmallBox<dyn Drawer, N>> = vec![
+            small_box!(Button { bid: 1 }),
+            small_box!(Select {
+                sid: 2,
+                options: Vec::new(),
+            }),
+            small_box!(Table { cells: [0; 8] }),
+        ];
+        components.iter().filter(|c| !SmallBox::is_heap(c)).count()
+    }
+
+    println!(
+        "Button: {} 字节, Select: {} 字节, Table: {} 字节",
+        std::mem::size_of::<Button>(),
+        std::mem::size_of::<Select>(),
+        std::mem::size_of::<Table>()
+    );
+    assert_eq!(count_inline::<0>(), 0);
+    assert_eq!(count_inline::<4>(), 1);
+    assert_eq!(count_inline::<32>(), 2);
+    assert_eq!(count_inline::<64>(), 3);
+    assert!(SmallBox::<dyn Drawer, 64>::fits::<Table>());
+}
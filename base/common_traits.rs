@@ -1,20 +1,29 @@
 // 常用的 Traits
 // Commonly used Traits
 
-fn main() {
-    // default_example();
-    // display_example();
-    // to_string_example()
-    // partial_eq_and_eq_example();
-    // add_example()
-    // clone_example();
-    // copy_example();
-    // to_owned_example();
-    // deref_example();
-    // drop_example();
-    // from_and_into_example();
-    // from_str_example();
-    as_ref_example();
+#[path = "../errors/app_error.rs"]
+mod app_error;
+#[path = "../errors/panic_boundary.rs"]
+mod panic_boundary;
+
+fn main() -> panic_boundary::Report {
+    // partial_eq_and_eq_example 的断言会失败, from_str_example 还是 todo!()
+    // 它们的 panic 会被捕获, 不影响其他示例, 但进程的退出码是 1
+    panic_boundary::run_examples(&[
+        ("default_example", default_example),
+        ("display_example", display_example),
+        ("to_string_example", to_string_example),
+        ("partial_eq_and_eq_example", partial_eq_and_eq_example),
+        ("add_example", add_example),
+        ("clone_example", clone_example),
+        ("copy_example", copy_example),
+        ("to_owned_example", to_owned_example),
+        ("deref_example", deref_example),
+        ("drop_example", drop_example),
+        ("from_and_into_example", from_and_into_example),
+        ("from_str_example", from_str_example),
+        ("as_ref_example", as_ref_example),
+    ])
 }
 
 /// **Default** Trait 默认
@@ -252,7 +261,7 @@ pub fn copy_example() {
     println!("before: {p1:#?}");
     println!("before: {p2:#?}");
 
-    /// Copy Trait 只赋值固定尺寸的值
+    // Copy Trait 只赋值固定尺寸的值
     // ? 为什么 Point 结构体里的字段都是固定尺寸的 (即复制语义), 那为什么不默认实现 Copy 呢?
     // > 因为 Rust 故意设计的, 在所有权的设计下, Rust 默认选择 Move 语义 (即所有权转移)
 }
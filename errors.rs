@@ -1,9 +1,21 @@
 /// 错误处理
 
-fn main() {
-    // example02();
-    // example04();
-    example06();
+#[path = "errors/app_error.rs"]
+mod app_error;
+#[path = "errors/panic_boundary.rs"]
+mod panic_boundary;
+#[path = "errors/retry.rs"]
+mod retry;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        // ("example02", example02),
+        // ("example04", example04),
+        ("example06", example06),
+        ("example08", example08),
+        ("example09", example09),
+        ("example10", example10),
+    ])
 }
 
 // 组合器
@@ -25,6 +37,7 @@ fn example01() {
     let _e2: Result<&str, &str> = Result::Err("err2");
 
     // 参考: https://course.rs/advance/errors.html#or-和-and
+    // 以上输入在所有组合器上的真值表见 errors/combinator_table.rs
 }
 
 /// # example02 or_else() 和 and_then()
@@ -84,12 +97,15 @@ fn example04() {
     let ok: Result<&str, &str> = Ok("everything is ok");
     let err: Result<&str, &str> = Err("404");
 
-    let ok2: Result<&str, i32> = Ok("everything is ok");
-    let err2: Result<&str, i32> = Err(404);
+    let ok2: Result<&str, MappedErr<&str, i32, std::num::ParseIntError>> = Ok("everything is ok");
+    let err2: Result<&str, MappedErr<&str, i32, std::num::ParseIntError>> = Err(MappedErr::Mapped(404));
 
-    let f1 = |_s: &str| _s.parse::<i32>().unwrap();
-    assert_eq!(ok.map_err(f1), ok2);
-    assert_eq!(err.map_err(f1), err2);
+    // ! 不要在 map_err 的闭包中 unwrap, 错误字符串不是数字时会在错误处理中 panic
+    // let f1 = |_s: &str| _s.parse::<i32>().unwrap();
+    // 使用可失败的 try_map_err, 参考 example08
+    let f1 = |_s: &&str| _s.parse::<i32>();
+    assert_eq!(ok.try_map_err(f1), ok2);
+    assert_eq!(err.try_map_err(f1), err2);
 }
 
 /// # example05 自定义错误类型
@@ -176,4 +192,245 @@ fn example07() {
         message: String, // 错误信息
     }
     // 为 AppError 实现 std::convert::From 特征, From 包含在 std::prelude 中
-}
\ No newline at end of file
+
+    // kind 可以用来决定如何处理错误, 比如是否重试, 参考 example09
+}
+
+/// # example08 可失败的错误转换
+/// example04 中 map_err 的闭包使用了 unwrap, 错误字符串不是数字时会在错误处理中 panic
+/// try_map_err 在转换失败时保留原始错误和失败原因, 错误处理本身不会再引入 panic
+#[allow(unused)]
+fn example08() {
+    use std::num::ParseIntError;
+
+    let ok: Result<&str, &str> = Ok("everything is ok");
+    let err: Result<&str, &str> = Err("404");
+    let bad: Result<&str, &str> = Err("not found");
+
+    let to_code = |s: &&str| s.parse::<i32>();
+
+    // 成功的值不受影响
+    assert_eq!(ok.try_map_err(to_code), Ok("everything is ok"));
+    // 错误转换成功
+    assert_eq!(err.try_map_err(to_code), Err(MappedErr::Mapped(404)));
+    // 错误转换失败, 原始错误 "not found" 依然保留
+    let res = bad.try_map_err(to_code);
+    match &res {
+        Err(MappedErr::Unmapped { original, cause }) => {
+            assert_eq!(*original, "not found");
+            assert_eq!(*cause, "not found".parse::<i32>().unwrap_err());
+        }
+        _ => unreachable!(),
+    }
+    println!("{}", res.unwrap_err());
+
+    // 取回原始错误, 或者退回到默认的错误码
+    let res: Result<&str, MappedErr<&str, i32, ParseIntError>> = bad.try_map_err(to_code);
+    assert_eq!(res.map_err(|e| e.mapped_or(500)), Err(500));
+}
+
+/// try_map_err 的错误类型
+/// - Mapped: 错误转换成功
+/// - Unmapped: 错误转换失败, 同时保留原始错误和转换失败的原因
+#[derive(Debug, PartialEq)]
+enum MappedErr<E, F, C> {
+    Mapped(F),
+    Unmapped { original: E, cause: C },
+}
+
+impl<E, F, C> MappedErr<E, F, C> {
+    /// 转换成功时返回转换后的错误, 否则返回 default
+    #[allow(unused)]
+    fn mapped_or(self, default: F) -> F {
+        match self {
+            MappedErr::Mapped(f) => f,
+            MappedErr::Unmapped { .. } => default,
+        }
+    }
+
+    /// 取回原始错误, 仅转换失败时存在
+    #[allow(unused)]
+    fn original(self) -> Option<E> {
+        match self {
+            MappedErr::Mapped(_) => None,
+            MappedErr::Unmapped { original, .. } => Some(original),
+        }
+    }
+}
+
+impl<E, F, C> std::fmt::Display for MappedErr<E, F, C>
+where
+    E: std::fmt::Display,
+    F: std::fmt::Display,
+    C: std::fmt::Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MappedErr::Mapped(mapped) => write!(f, "{}", mapped),
+            MappedErr::Unmapped { original, cause } => {
+                write!(f, "{} (转换错误失败: {})", original, cause)
+            }
+        }
+    }
+}
+
+/// 为 Result 扩展 try_map_err 方法
+/// 与 map_err 不同的是闭包可以失败, 闭包只借用原始错误, 失败时原始错误不会丢失
+trait TryMapErr<T, E> {
+    fn try_map_err<F, C>(
+        self,
+        op: impl FnOnce(&E) -> Result<F, C>,
+    ) -> Result<T, MappedErr<E, F, C>>;
+}
+
+impl<T, E> TryMapErr<T, E> for Result<T, E> {
+    fn try_map_err<F, C>(
+        self,
+        op: impl FnOnce(&E) -> Result<F, C>,
+    ) -> Result<T, MappedErr<E, F, C>> {
+        self.map_err(|original| match op(&original) {
+            Ok(mapped) => MappedErr::Mapped(mapped),
+            Err(cause) => MappedErr::Unmapped { original, cause },
+        })
+    }
+}
+
+/// # example09 根据错误类型重试
+/// AppError 的 kind 决定错误是暂时性的还是永久性的, 只有暂时性的错误才会被重试
+/// 示例中注入了 FakeClock, 所以不会真的等待
+#[allow(unused)]
+fn example09() {
+    use app_error::AppError;
+    use retry::{Backoff, ErrorClass, FakeClock, RetryPolicy};
+    use std::time::Duration;
+
+    let ms = Duration::from_millis;
+
+    // 模拟一个前 n 次超时的操作
+    fn flaky(calls: &mut u32, failures: u32) -> Result<&'static str, AppError> {
+        *calls += 1;
+        if *calls <= failures {
+            Err(AppError::new("timeout", 504, "请求超时"))
+        } else {
+            Ok("everything is ok")
+        }
+    }
+
+    // 错误分类
+    assert_eq!(
+        retry::classify(&AppError::new("timeout", 504, "")),
+        ErrorClass::Transient
+    );
+    assert_eq!(
+        retry::classify(&AppError::new("config", 400, "")),
+        ErrorClass::Permanent
+    );
+
+    // 固定退避: 失败两次后成功
+    let policy = RetryPolicy::new(5, Backoff::Fixed(ms(100)));
+    let clock = FakeClock::default();
+    let mut calls = 0;
+    let res = retry::retry_with_clock(&policy, &clock, || flaky(&mut calls, 2));
+    assert_eq!(res, Ok("everything is ok"));
+    assert_eq!(calls, 3);
+    assert_eq!(clock.slept(), [ms(100), ms(100)]);
+
+    // 指数退避: 等待时间翻倍, 但不超过 max
+    let policy = RetryPolicy::new(
+        6,
+        Backoff::Exponential {
+            base: ms(100),
+            max: ms(500),
+        },
+    );
+    let clock = FakeClock::default();
+    let mut calls = 0;
+    let res = retry::retry_with_clock(&policy, &clock, || flaky(&mut calls, 5));
+    assert!(res.is_ok());
+    assert_eq!(clock.slept(), [ms(100), ms(200), ms(400), ms(500), ms(500)]);
+
+    // 抖动退避: 每次等待时间落在 [0, 指数退避时间) 之间, 相同种子结果相同
+    let jittered = || {
+        RetryPolicy::new(
+            4,
+            Backoff::Jittered {
+                base: ms(100),
+                max: ms(1000),
+            },
+        )
+        .with_seed(42)
+    };
+    let clock = FakeClock::default();
+    let mut calls = 0;
+    let _ = retry::retry_with_clock(&jittered(), &clock, || flaky(&mut calls, 3));
+    for (delay, cap) in clock.slept().iter().zip([ms(100), ms(200), ms(400)]) {
+        assert!(*delay < cap);
+    }
+    let again = FakeClock::default();
+    let mut calls = 0;
+    let _ = retry::retry_with_clock(&jittered(), &again, || flaky(&mut calls, 3));
+    assert_eq!(clock.slept(), again.slept());
+
+    // 达到最大尝试次数后返回最后一次的错误
+    let policy = RetryPolicy::new(3, Backoff::Fixed(ms(10)));
+    let clock = FakeClock::default();
+    let mut calls = 0;
+    let res = retry::retry_with_clock(&policy, &clock, || flaky(&mut calls, 10));
+    assert_eq!(res.unwrap_err().kind, "timeout");
+    assert_eq!(calls, 3);
+    assert_eq!(clock.elapsed(), ms(20));
+
+    // 永久性错误不会重试
+    let clock = FakeClock::default();
+    let mut calls = 0;
+    let res: Result<(), AppError> = retry::retry_with_clock(&policy, &clock, || {
+        calls += 1;
+        Err(AppError::new("config", 400, "配置错误"))
+    });
+    println!("{:?}", res);
+    assert_eq!(calls, 1);
+    assert!(clock.slept().is_empty());
+
+    // 自定义分类规则: 所有错误都重试
+    let policy = policy.with_classify(|_| ErrorClass::Transient);
+    let clock = FakeClock::default();
+    let mut calls = 0;
+    let res: Result<(), AppError> = retry::retry_with_clock(&policy, &clock, || {
+        calls += 1;
+        Err(AppError::new("config", 400, "配置错误"))
+    });
+    assert_eq!(calls, 3);
+}
+
+/// # example10 把 panic 转为错误
+/// catch_panic 使用 catch_unwind 捕获闭包中的 panic, 并转为 AppError
+/// 一个示例 panic 不会再中止整个程序
+#[allow(unused)]
+fn example10() {
+    use panic_boundary::{catch_panic, PANIC_CODE, PANIC_KIND};
+
+    panic_boundary::install_panic_hook();
+
+    // 没有 panic 时原样返回
+    assert_eq!(catch_panic(|| 1 + 1), Ok(2));
+
+    // panic 被转为 AppError
+    let err = catch_panic(|| panic!("Factory ids overflowed")).unwrap_err();
+    assert_eq!(err.kind, PANIC_KIND);
+    assert_eq!(err.code, PANIC_CODE);
+    assert_eq!(err.message, "Factory ids overflowed");
+
+    // 格式化参数的 panic 和断言失败同样可以捕获
+    let err = catch_panic(|| panic!("code: {}", 404)).unwrap_err();
+    assert_eq!(err.message, "code: 404");
+    let err = catch_panic(|| assert_eq!(1, 2)).unwrap_err();
+    assert!(err.message.starts_with("assertion `left == right` failed"));
+
+    // 与 ? 配合使用
+    fn parse_code(s: &str) -> Result<i32, app_error::AppError> {
+        let code = catch_panic(|| s.parse::<i32>().unwrap())?;
+        Ok(code)
+    }
+    assert_eq!(parse_code("404"), Ok(404));
+    assert!(parse_code("not found").is_err());
+}
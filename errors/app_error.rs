@@ -0,0 +1,49 @@
+/// 通用的 AppError
+///
+/// 合并了 errors.rs 中 `example06` (错误码和错误信息) 和 `example07` (错误类型) 的 AppError,
+/// 供其他章节通过 `#[path = "..."] mod app_error;` 引入使用
+#[allow(unused)]
+struct Description;
+
+/// 自定义错误
+/// - kind: 错误类型, 如 "config" / "io" / "timeout"
+/// - code: 错误码
+/// - message: 错误信息
+#[derive(Clone, PartialEq, Eq)]
+pub struct AppError {
+    pub kind: String,
+    pub code: usize,
+    pub message: String,
+}
+
+impl AppError {
+    /// # new 创建错误实例
+    #[allow(unused)]
+    pub fn new(kind: &str, code: usize, message: impl Into<String>) -> AppError {
+        AppError {
+            kind: kind.to_string(),
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+// 为自定义错误实现 Display 特征
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.kind, self.message)
+    }
+}
+
+// 为自定义错误实现 Debug 特征, 格式与 errors.rs example06 保持一致
+impl std::fmt::Debug for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "AppError {{ kind: {}, code: {}, message: {} }}",
+            self.kind, self.code, self.message
+        )
+    }
+}
+
+impl std::error::Error for AppError {}
@@ -0,0 +1,234 @@
+/// 打印并校验组合器真值表
+///
+/// 真值表由 `combinators.rs` 生成, 这里对每一个单元格做断言
+#[path = "combinators.rs"]
+mod combinators;
+
+use combinators::TruthTable;
+
+fn main() {
+    for table in combinators::all_tables() {
+        println!("{}", combinators::render(&table));
+    }
+
+    example01();
+    example02();
+    example03();
+    example04();
+    example05();
+    example06();
+    println!("所有单元格校验通过");
+}
+
+/// 断言真值表的每一个单元格, 且没有多余的行
+fn assert_table(tables: &[TruthTable], expected: &[(&str, &str)]) {
+    let rows: usize = tables.iter().map(|t| t.rows.len()).sum();
+    assert_eq!(rows, expected.len(), "{} 的行数不一致", tables[0].combinator);
+    for (expr, output) in expected {
+        let cell = tables.iter().find_map(|t| t.cell(expr));
+        assert_eq!(cell, Some(*output), "单元格 {expr}");
+    }
+}
+
+/// # example01 or() 和 and()
+#[allow(unused)]
+fn example01() {
+    assert_table(
+        &combinators::or(),
+        &[
+            ("s1.or(s1)", r#"Some("some1")"#),
+            ("s1.or(s2)", r#"Some("some1")"#),
+            ("s1.or(non)", r#"Some("some1")"#),
+            ("s2.or(s1)", r#"Some("some2")"#),
+            ("s2.or(s2)", r#"Some("some2")"#),
+            ("s2.or(non)", r#"Some("some2")"#),
+            ("non.or(s1)", r#"Some("some1")"#),
+            ("non.or(s2)", r#"Some("some2")"#),
+            ("non.or(non)", "None"),
+            ("r1.or(r1)", r#"Ok("ok1")"#),
+            ("r1.or(r2)", r#"Ok("ok1")"#),
+            ("r1.or(e1)", r#"Ok("ok1")"#),
+            ("r1.or(e2)", r#"Ok("ok1")"#),
+            ("r2.or(r1)", r#"Ok("ok2")"#),
+            ("r2.or(r2)", r#"Ok("ok2")"#),
+            ("r2.or(e1)", r#"Ok("ok2")"#),
+            ("r2.or(e2)", r#"Ok("ok2")"#),
+            ("e1.or(r1)", r#"Ok("ok1")"#),
+            ("e1.or(r2)", r#"Ok("ok2")"#),
+            ("e1.or(e1)", r#"Err("err1")"#),
+            ("e1.or(e2)", r#"Err("err2")"#),
+            ("e2.or(r1)", r#"Ok("ok1")"#),
+            ("e2.or(r2)", r#"Ok("ok2")"#),
+            ("e2.or(e1)", r#"Err("err1")"#),
+            ("e2.or(e2)", r#"Err("err2")"#),
+        ],
+    );
+    assert_table(
+        &combinators::and(),
+        &[
+            ("s1.and(s1)", r#"Some("some1")"#),
+            ("s1.and(s2)", r#"Some("some2")"#),
+            ("s1.and(non)", "None"),
+            ("s2.and(s1)", r#"Some("some1")"#),
+            ("s2.and(s2)", r#"Some("some2")"#),
+            ("s2.and(non)", "None"),
+            ("non.and(s1)", "None"),
+            ("non.and(s2)", "None"),
+            ("non.and(non)", "None"),
+            ("r1.and(r1)", r#"Ok("ok1")"#),
+            ("r1.and(r2)", r#"Ok("ok2")"#),
+            ("r1.and(e1)", r#"Err("err1")"#),
+            ("r1.and(e2)", r#"Err("err2")"#),
+            ("r2.and(r1)", r#"Ok("ok1")"#),
+            ("r2.and(r2)", r#"Ok("ok2")"#),
+            ("r2.and(e1)", r#"Err("err1")"#),
+            ("r2.and(e2)", r#"Err("err2")"#),
+            ("e1.and(r1)", r#"Err("err1")"#),
+            ("e1.and(r2)", r#"Err("err1")"#),
+            ("e1.and(e1)", r#"Err("err1")"#),
+            ("e1.and(e2)", r#"Err("err1")"#),
+            ("e2.and(r1)", r#"Err("err2")"#),
+            ("e2.and(r2)", r#"Err("err2")"#),
+            ("e2.and(e1)", r#"Err("err2")"#),
+            ("e2.and(e2)", r#"Err("err2")"#),
+        ],
+    );
+}
+
+/// # example02 xor() 和 zip()
+#[allow(unused)]
+fn example02() {
+    assert_table(
+        &combinators::xor(),
+        &[
+            ("s1.xor(s1)", "None"),
+            ("s1.xor(s2)", "None"),
+            ("s1.xor(non)", r#"Some("some1")"#),
+            ("s2.xor(s1)", "None"),
+            ("s2.xor(s2)", "None"),
+            ("s2.xor(non)", r#"Some("some2")"#),
+            ("non.xor(s1)", r#"Some("some1")"#),
+            ("non.xor(s2)", r#"Some("some2")"#),
+            ("non.xor(non)", "None"),
+        ],
+    );
+    assert_table(
+        &combinators::zip(),
+        &[
+            ("s1.zip(s1)", r#"Some(("some1", "some1"))"#),
+            ("s1.zip(s2)", r#"Some(("some1", "some2"))"#),
+            ("s1.zip(non)", "None"),
+            ("s2.zip(s1)", r#"Some(("some2", "some1"))"#),
+            ("s2.zip(s2)", r#"Some(("some2", "some2"))"#),
+            ("s2.zip(non)", "None"),
+            ("non.zip(s1)", "None"),
+            ("non.zip(s2)", "None"),
+            ("non.zip(non)", "None"),
+        ],
+    );
+}
+
+/// # example03 and_then() 和 or_else()
+/// 结果与 and() / or() 相同, 区别只在于 rhs 是否被惰性求值
+#[allow(unused)]
+fn example03() {
+    for (lazy, eager) in [
+        (combinators::and_then(), combinators::and()),
+        (combinators::or_else(), combinators::or()),
+    ] {
+        let lazy_rows = lazy.iter().flat_map(|t| t.rows.iter());
+        let eager_rows = eager.iter().flat_map(|t| t.rows.iter());
+        assert_eq!(lazy_rows.clone().count(), eager_rows.clone().count());
+        for (l, e) in lazy_rows.zip(eager_rows) {
+            let expr = e.expr.replacen(eager[0].combinator, lazy[0].combinator, 1);
+            assert_eq!(l.expr, expr);
+            assert_eq!(l.output, e.output, "单元格 {}", l.expr);
+        }
+    }
+}
+
+/// # example04 ok_or() 和 transpose()
+#[allow(unused)]
+fn example04() {
+    assert_table(
+        &combinators::ok_or(),
+        &[
+            ("s1.ok_or(e1)", r#"Ok("some1")"#),
+            ("s1.ok_or(e2)", r#"Ok("some1")"#),
+            ("s2.ok_or(e1)", r#"Ok("some2")"#),
+            ("s2.ok_or(e2)", r#"Ok("some2")"#),
+            ("non.ok_or(e1)", r#"Err("err1")"#),
+            ("non.ok_or(e2)", r#"Err("err2")"#),
+        ],
+    );
+    assert_table(
+        &combinators::transpose(),
+        &[
+            ("Some(r1).transpose()", r#"Ok(Some("ok1"))"#),
+            ("Some(r2).transpose()", r#"Ok(Some("ok2"))"#),
+            ("Some(e1).transpose()", r#"Err("err1")"#),
+            ("Some(e2).transpose()", r#"Err("err2")"#),
+            ("None.transpose()", "Ok(None)"),
+            ("Ok(s1).transpose()", r#"Some(Ok("some1"))"#),
+            ("Ok(s2).transpose()", r#"Some(Ok("some2"))"#),
+            ("Ok(non).transpose()", "None"),
+            ("e1.transpose()", r#"Some(Err("err1"))"#),
+            ("e2.transpose()", r#"Some(Err("err2"))"#),
+        ],
+    );
+}
+
+/// # example05 flatten() 和 unwrap_or_default()
+#[allow(unused)]
+fn example05() {
+    assert_table(
+        &combinators::flatten(),
+        &[
+            ("Some(s1).flatten()", r#"Some("some1")"#),
+            ("Some(s2).flatten()", r#"Some("some2")"#),
+            ("Some(non).flatten()", "None"),
+            ("None.flatten()", "None"),
+        ],
+    );
+    assert_table(
+        &combinators::unwrap_or_default(),
+        &[
+            ("s1.unwrap_or_default()", r#""some1""#),
+            ("s2.unwrap_or_default()", r#""some2""#),
+            ("non.unwrap_or_default()", r#""""#),
+            ("r1.unwrap_or_default()", r#""ok1""#),
+            ("r2.unwrap_or_default()", r#""ok2""#),
+            ("e1.unwrap_or_default()", r#""""#),
+            ("e2.unwrap_or_default()", r#""""#),
+        ],
+    );
+}
+
+/// # example06 map_or_else() 和 inspect()
+#[allow(unused)]
+fn example06() {
+    assert_table(
+        &combinators::map_or_else(),
+        &[
+            ("s1.map_or_else(|| 0, len)", "5"),
+            ("s2.map_or_else(|| 0, len)", "5"),
+            ("non.map_or_else(|| 0, len)", "0"),
+            ("r1.map_or_else(|e| -len(e), len)", "3"),
+            ("r2.map_or_else(|e| -len(e), len)", "3"),
+            ("e1.map_or_else(|e| -len(e), len)", "-4"),
+            ("e2.map_or_else(|e| -len(e), len)", "-4"),
+        ],
+    );
+    assert_table(
+        &combinators::inspect(),
+        &[
+            ("s1.inspect(..)", r#"(Some("some1"), Some("some1"))"#),
+            ("s2.inspect(..)", r#"(Some("some2"), Some("some2"))"#),
+            ("non.inspect(..)", "(None, None)"),
+            ("r1.inspect(..)", r#"(Ok("ok1"), Some("ok1"))"#),
+            ("r2.inspect(..)", r#"(Ok("ok2"), Some("ok2"))"#),
+            ("e1.inspect(..)", r#"(Err("err1"), None)"#),
+            ("e2.inspect(..)", r#"(Err("err2"), None)"#),
+        ],
+    );
+}
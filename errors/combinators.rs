@@ -0,0 +1,273 @@
+/// 组合器真值表
+///
+/// errors.rs `example01` 中声明的 `s1, s2, non, r1, r2, e1, e2` 是所有组合器的输入,
+/// 这里把每个组合器在这些输入上的结果逐个求值, 生成一张张真值表
+///
+/// *本文件只负责生成真值表, 打印和校验见 `combinator_table.rs`*
+#[allow(unused)]
+struct Description;
+
+/// Option 输入: 名称和值
+pub const OPTIONS: [(&str, Option<&str>); 3] = [
+    ("s1", Some("some1")),
+    ("s2", Some("some2")),
+    ("non", None),
+];
+
+/// Result 输入: 名称和值
+pub const RESULTS: [(&str, Result<&str, &str>); 4] = [
+    ("r1", Ok("ok1")),
+    ("r2", Ok("ok2")),
+    ("e1", Err("err1")),
+    ("e2", Err("err2")),
+];
+
+/// 真值表中的一行
+/// - `expr` 是求值的表达式, 如 `s1.or(s2)`
+/// - `output` 是表达式结果的 Debug 输出, 如 `Some("some1")`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub expr: String,
+    pub output: String,
+}
+
+/// 一个组合器的真值表
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthTable {
+    pub combinator: &'static str,
+    pub rows: Vec<Row>,
+}
+
+impl TruthTable {
+    fn new(combinator: &'static str) -> Self {
+        Self {
+            combinator,
+            rows: Vec::new(),
+        }
+    }
+
+    fn push<T: std::fmt::Debug>(&mut self, expr: String, output: T) {
+        self.rows.push(Row {
+            expr,
+            output: format!("{output:?}"),
+        });
+    }
+
+    /// 按表达式查找单元格, 找不到时返回 None
+    pub fn cell(&self, expr: &str) -> Option<&str> {
+        self.rows
+            .iter()
+            .find(|row| row.expr == expr)
+            .map(|row| row.output.as_str())
+    }
+}
+
+/// 生成二元组合器的真值表
+/// `$inputs` 可以是 OPTIONS 或 RESULTS, 两两组合后求值 `$body`
+macro_rules! binary_table {
+    ($name:literal, $inputs:expr, |$a:ident, $b:ident| $body:expr) => {{
+        let mut table = TruthTable::new($name);
+        for (lhs, $a) in $inputs {
+            for (rhs, $b) in $inputs {
+                table.push(format!("{}.{}({})", lhs, $name, rhs), $body);
+            }
+        }
+        table
+    }};
+}
+
+/// # or
+/// 若 lhs 是 Some / Ok 则返回 lhs, 否则返回 rhs
+pub fn or() -> Vec<TruthTable> {
+    vec![
+        binary_table!("or", OPTIONS, |a, b| a.or(b)),
+        binary_table!("or", RESULTS, |a, b| a.or(b)),
+    ]
+}
+
+/// # and
+/// 若 lhs 是 Some / Ok 则返回 rhs, 否则返回 lhs
+pub fn and() -> Vec<TruthTable> {
+    vec![
+        binary_table!("and", OPTIONS, |a, b| a.and(b)),
+        binary_table!("and", RESULTS, |a, b| a.and(b)),
+    ]
+}
+
+/// # xor
+/// 仅 Option 拥有, 两者中有且只有一个是 Some 时返回它, 否则返回 None
+pub fn xor() -> Vec<TruthTable> {
+    vec![binary_table!("xor", OPTIONS, |a, b| a.xor(b))]
+}
+
+/// # and_then
+/// 与 and 相同, 但 rhs 由闭包惰性产生, 闭包收到 lhs 中的值
+// 表格要展示的正是闭包形式, 不能按 clippy 的建议换成 and
+#[allow(clippy::unnecessary_lazy_evaluations)]
+pub fn and_then() -> Vec<TruthTable> {
+    vec![
+        binary_table!("and_then", OPTIONS, |a, b| a.and_then(|_| b)),
+        binary_table!("and_then", RESULTS, |a, b| a.and_then(|_| b)),
+    ]
+}
+
+/// # or_else
+/// 与 or 相同, 但 rhs 由闭包惰性产生, Result 的闭包收到 lhs 中的错误
+// 表格要展示的正是闭包形式, 不能按 clippy 的建议换成 or
+#[allow(clippy::unnecessary_lazy_evaluations)]
+pub fn or_else() -> Vec<TruthTable> {
+    vec![
+        binary_table!("or_else", OPTIONS, |a, b| a.or_else(|| b)),
+        binary_table!("or_else", RESULTS, |a, b| a.or_else(|_| b)),
+    ]
+}
+
+/// # zip
+/// 仅 Option 拥有, 两者都是 Some 时返回 Some((lhs, rhs)), 否则返回 None
+pub fn zip() -> Vec<TruthTable> {
+    vec![binary_table!("zip", OPTIONS, |a, b| a.zip(b))]
+}
+
+/// # ok_or
+/// 把 Option 转为 Result, None 变为 Err(rhs)
+/// rhs 取 e1 / e2 中的错误值
+pub fn ok_or() -> Vec<TruthTable> {
+    let mut table = TruthTable::new("ok_or");
+    for (lhs, a) in OPTIONS {
+        for (rhs, b) in RESULTS.iter().filter(|(_, r)| r.is_err()) {
+            let err = b.unwrap_err();
+            table.push(format!("{lhs}.ok_or({rhs})"), a.ok_or(err));
+        }
+    }
+    vec![table]
+}
+
+/// # transpose
+/// `Option<Result<T, E>>` 与 `Result<Option<T>, E>` 互相转换
+pub fn transpose() -> Vec<TruthTable> {
+    let mut option_table = TruthTable::new("transpose");
+    for (name, r) in RESULTS {
+        option_table.push(format!("Some({name}).transpose()"), Some(r).transpose());
+    }
+    option_table.push(
+        "None.transpose()".to_string(),
+        None::<Result<&str, &str>>.transpose(),
+    );
+
+    let mut result_table = TruthTable::new("transpose");
+    for (name, s) in OPTIONS {
+        result_table.push(
+            format!("Ok({name}).transpose()"),
+            Ok::<_, &str>(s).transpose(),
+        );
+    }
+    for (name, e) in RESULTS.iter().filter(|(_, r)| r.is_err()) {
+        let err: Result<Option<&str>, &str> = Err(e.unwrap_err());
+        result_table.push(format!("{name}.transpose()"), err.transpose());
+    }
+
+    vec![option_table, result_table]
+}
+
+/// # flatten
+/// 把 `Option<Option<T>>` 压平为 `Option<T>`
+/// *`Result::flatten` 仍未稳定, 这里只列出 Option*
+pub fn flatten() -> Vec<TruthTable> {
+    let mut table = TruthTable::new("flatten");
+    for (name, s) in OPTIONS {
+        table.push(format!("Some({name}).flatten()"), Some(s).flatten());
+    }
+    table.push("None.flatten()".to_string(), None::<Option<&str>>.flatten());
+    vec![table]
+}
+
+/// # unwrap_or_default
+/// Some / Ok 时解包, 否则返回 `T::default()`, 对于 &str 就是 ""
+pub fn unwrap_or_default() -> Vec<TruthTable> {
+    let mut option_table = TruthTable::new("unwrap_or_default");
+    for (name, s) in OPTIONS {
+        option_table.push(format!("{name}.unwrap_or_default()"), s.unwrap_or_default());
+    }
+    let mut result_table = TruthTable::new("unwrap_or_default");
+    for (name, r) in RESULTS {
+        result_table.push(format!("{name}.unwrap_or_default()"), r.unwrap_or_default());
+    }
+    vec![option_table, result_table]
+}
+
+/// # map_or_else
+/// Some / Ok 时调用第二个闭包, 否则调用第一个闭包
+/// 这里统一映射为字符数量, 缺省时 Option 返回 0, Result 返回错误的字符数量取负
+pub fn map_or_else() -> Vec<TruthTable> {
+    let mut option_table = TruthTable::new("map_or_else");
+    for (name, s) in OPTIONS {
+        option_table.push(
+            format!("{name}.map_or_else(|| 0, len)"),
+            s.map_or_else(|| 0, |v| v.len() as isize),
+        );
+    }
+    let mut result_table = TruthTable::new("map_or_else");
+    for (name, r) in RESULTS {
+        result_table.push(
+            format!("{name}.map_or_else(|e| -len(e), len)"),
+            r.map_or_else(|e| -(e.len() as isize), |v| v.len() as isize),
+        );
+    }
+    vec![option_table, result_table]
+}
+
+/// # inspect
+/// 对 Some / Ok 中的值做只读操作后原样返回, 常用于打日志
+/// 结果记录为 `(返回值, 闭包看到的值)`
+pub fn inspect() -> Vec<TruthTable> {
+    let mut option_table = TruthTable::new("inspect");
+    for (name, s) in OPTIONS {
+        let mut seen = None;
+        let output = s.inspect(|v| seen = Some(*v));
+        option_table.push(format!("{name}.inspect(..)"), (output, seen));
+    }
+    let mut result_table = TruthTable::new("inspect");
+    for (name, r) in RESULTS {
+        let mut seen = None;
+        let output = r.inspect(|v| seen = Some(*v));
+        result_table.push(format!("{name}.inspect(..)"), (output, seen));
+    }
+    vec![option_table, result_table]
+}
+
+/// 按顺序生成所有组合器的真值表
+pub fn all_tables() -> Vec<TruthTable> {
+    let generators: [fn() -> Vec<TruthTable>; 12] = [
+        or,
+        and,
+        xor,
+        and_then,
+        or_else,
+        zip,
+        ok_or,
+        transpose,
+        flatten,
+        unwrap_or_default,
+        map_or_else,
+        inspect,
+    ];
+    generators.iter().flat_map(|generate| generate()).collect()
+}
+
+/// 将真值表渲染为 Markdown 表格
+pub fn render(table: &TruthTable) -> String {
+    let width = table
+        .rows
+        .iter()
+        .map(|row| row.expr.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("表达式".chars().count());
+    let mut out = format!("## {}\n\n", table.combinator);
+    out.push_str(&format!("| {:<width$} | 结果\n", "表达式"));
+    out.push_str(&format!("| {:-<width$} | ----\n", ""));
+    for row in &table.rows {
+        out.push_str(&format!("| {:<width$} | {}\n", row.expr, row.output));
+    }
+    out
+}
@@ -0,0 +1,106 @@
+/// panic 边界
+///
+/// 有些示例会故意 panic (比如 global_variable.rs 的 `generate_id` 溢出, common_traits.rs 的断言失败),
+/// 直接调用会让整个章节的 main 中止, 后面的示例都无法运行
+///
+/// 这里用 `std::panic::catch_unwind` 把 panic 转为 `AppError`,
+/// 并安装自定义的 panic hook, 按 errors.rs `example06` 中 AppError 的 Debug 格式输出 panic 信息
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::panic::{self, AssertUnwindSafe};
+use std::process::{ExitCode, Termination};
+use std::sync::Once;
+
+/// panic 转换成的 AppError 的 kind 和 code
+pub const PANIC_KIND: &str = "panic";
+pub const PANIC_CODE: usize = 500;
+
+/// 从 panic 的载荷中取出信息
+/// `panic!("...")` 的载荷是 &str, 带格式化参数时是 String, 其他类型无法得知内容
+fn payload_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        String::from("Box<dyn Any>")
+    }
+}
+
+/// 执行 f, 如果 f 发生 panic 则转为 AppError 返回
+///
+/// ! 只能捕获 unwind 方式的 panic, `panic = "abort"` 时依然会中止进程
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, AppError> {
+    panic::catch_unwind(AssertUnwindSafe(f))
+        .map_err(|payload| AppError::new(PANIC_KIND, PANIC_CODE, payload_message(&*payload)))
+}
+
+/// 安装自定义的 panic hook, 多次调用只会安装一次
+///
+/// 输出格式与 AppError 的 Debug 相同, 例如:
+/// `AppError { kind: panic, code: 500, message: Factory ids overflowed (at global_variable.rs:66:13) }`
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        panic::set_hook(Box::new(|info| {
+            let mut message = payload_message(info.payload());
+            if let Some(location) = info.location() {
+                message.push_str(&format!(" (at {location})"));
+            }
+            eprintln!("{:?}", AppError::new(PANIC_KIND, PANIC_CODE, message));
+        }));
+    });
+}
+
+/// 所有示例的运行结果
+///
+/// 作为 main 的返回值: 有示例失败时进程以退出码 1 结束, 脚本和 CI 可以据此发现失败
+#[derive(Debug, Default)]
+pub struct Report {
+    pub failures: Vec<AppError>,
+}
+
+impl Report {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Termination for Report {
+    fn report(self) -> ExitCode {
+        if self.is_success() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// 依次运行章节中的示例, 某个示例 panic 不会影响后面的示例
+/// 返回所有失败示例的错误, 通常直接作为 main 的返回值:
+///
+/// ```ignore
+/// fn main() -> panic_boundary::Report {
+///     panic_boundary::run_examples(&[("example01", example01)])
+/// }
+/// ```
+#[must_use = "从 main 返回 Report, 否则示例失败时进程依然以 0 退出"]
+pub fn run_examples(examples: &[(&str, fn())]) -> Report {
+    install_panic_hook();
+    let mut failures = Vec::new();
+    for (name, example) in examples {
+        if let Err(mut err) = catch_panic(example) {
+            eprintln!("{name} 运行失败, 继续运行后面的示例");
+            err.message = format!("{name}: {}", err.message);
+            failures.push(err);
+        }
+    }
+    if !failures.is_empty() {
+        eprintln!("共 {} 个示例, {} 个失败", examples.len(), failures.len());
+    }
+    Report { failures }
+}
@@ -0,0 +1,277 @@
+/// 重试与恢复策略
+///
+/// errors.rs `example07` 中 AppError 的 `kind` 字段描述了错误类型,
+/// 这里根据 kind 把错误分为 **暂时性** (可以重试) 和 **永久性** (重试也没用) 两类,
+/// 再配合退避策略 (固定 / 指数 / 抖动) 和最大尝试次数决定是否重试
+///
+/// *依赖 crate 根引入的 `app_error` 模块*
+///
+/// 等待由 `Clock` / `AsyncClock` 完成, 可以注入 `FakeClock` 让示例中的重试立即完成
+#[allow(unused)]
+struct Description;
+
+use crate::app_error::AppError;
+use std::cell::RefCell;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+/// 错误分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// 暂时性错误, 稍后重试可能成功
+    Transient,
+    /// 永久性错误, 重试也不会成功
+    Permanent,
+}
+
+/// 视为暂时性错误的 kind
+pub const TRANSIENT_KINDS: [&str; 4] = ["timeout", "io", "unavailable", "busy"];
+
+/// 默认的分类规则: kind 属于 TRANSIENT_KINDS 的是暂时性错误, 其余都是永久性错误
+pub fn classify(err: &AppError) -> ErrorClass {
+    if TRANSIENT_KINDS.contains(&err.kind.as_str()) {
+        ErrorClass::Transient
+    } else {
+        ErrorClass::Permanent
+    }
+}
+
+/// 退避策略, 决定两次尝试之间等待多久
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backoff {
+    /// 每次等待相同的时间
+    Fixed(Duration),
+    /// 等待时间按 base * 2^(n-1) 增长, 不超过 max
+    Exponential { base: Duration, max: Duration },
+    /// 在 [0, 指数退避时间) 之间随机等待 (full jitter), 避免大量客户端同时重试
+    Jittered { base: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// 第 attempt 次尝试 (从 1 开始) 失败后的等待时间
+    /// random 是 [0, 1) 之间的随机数, 仅 Jittered 使用
+    pub fn delay(&self, attempt: u32, random: f64) -> Duration {
+        match self {
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { base, max } => exponential(*base, *max, attempt),
+            Backoff::Jittered { base, max } => exponential(*base, *max, attempt).mul_f64(random),
+        }
+    }
+}
+
+fn exponential(base: Duration, max: Duration, attempt: u32) -> Duration {
+    let factor = 2u32.checked_pow(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+    base.checked_mul(factor).map_or(max, |delay| delay.min(max))
+}
+
+/// 重试策略
+pub struct RetryPolicy {
+    /// 最多尝试的次数 (包含第一次), 0 和 1 一样只尝试一次
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    /// 错误分类规则, 只有 Transient 的错误才会重试
+    pub classify: fn(&AppError) -> ErrorClass,
+    /// Jittered 使用的随机数种子
+    pub seed: u64,
+}
+
+#[allow(unused)]
+impl RetryPolicy {
+    /// # new 创建重试策略, 使用默认的分类规则
+    pub fn new(max_attempts: u32, backoff: Backoff) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff,
+            classify,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// 替换分类规则
+    pub fn with_classify(mut self, classify: fn(&AppError) -> ErrorClass) -> RetryPolicy {
+        self.classify = classify;
+        self
+    }
+
+    /// 替换随机数种子
+    pub fn with_seed(mut self, seed: u64) -> RetryPolicy {
+        self.seed = seed;
+        self
+    }
+
+    /// 第 attempt 次尝试失败后是否应该重试
+    pub fn should_retry(&self, attempt: u32, err: &AppError) -> bool {
+        attempt < self.max_attempts && (self.classify)(err) == ErrorClass::Transient
+    }
+}
+
+/// 简单的 xorshift 随机数生成器, 仅用于抖动
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        // 种子不能为 0, 否则永远只会生成 0
+        XorShift(seed.max(1))
+    }
+
+    /// 生成 [0, 1) 之间的随机数
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// 同步等待
+pub trait Clock {
+    fn sleep(&self, dur: Duration);
+}
+
+/// 异步等待
+pub trait AsyncClock {
+    fn sleep_async(&self, dur: Duration) -> impl Future<Output = ()>;
+}
+
+/// 真实的时钟
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn sleep(&self, dur: Duration) {
+        std::thread::sleep(dur);
+    }
+}
+
+impl AsyncClock for SystemClock {
+    /// 没有异步运行时, 在后台线程上睡眠, 醒来后唤醒 Future
+    fn sleep_async(&self, dur: Duration) -> impl Future<Output = ()> {
+        ThreadSleep::new(dur)
+    }
+}
+
+/// 在后台线程上计时的 Future
+struct ThreadSleep {
+    state: Arc<Mutex<(bool, Option<Waker>)>>,
+}
+
+impl ThreadSleep {
+    fn new(dur: Duration) -> ThreadSleep {
+        let state = Arc::new(Mutex::new((false, None::<Waker>)));
+        let timer = Arc::clone(&state);
+        std::thread::spawn(move || {
+            std::thread::sleep(dur);
+            let mut state = timer.lock().unwrap();
+            state.0 = true;
+            if let Some(waker) = state.1.take() {
+                waker.wake();
+            }
+        });
+        ThreadSleep { state }
+    }
+}
+
+impl Future for ThreadSleep {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.0 {
+            Poll::Ready(())
+        } else {
+            state.1 = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// 假的时钟, 不会真的等待, 只记录每次等待的时间
+#[derive(Default)]
+pub struct FakeClock {
+    slept: RefCell<Vec<Duration>>,
+}
+
+#[allow(unused)]
+impl FakeClock {
+    /// 每次等待的时间
+    pub fn slept(&self) -> Vec<Duration> {
+        self.slept.borrow().clone()
+    }
+
+    /// 总共等待的时间
+    pub fn elapsed(&self) -> Duration {
+        self.slept.borrow().iter().sum()
+    }
+}
+
+impl Clock for FakeClock {
+    fn sleep(&self, dur: Duration) {
+        self.slept.borrow_mut().push(dur);
+    }
+}
+
+impl AsyncClock for FakeClock {
+    /// 在 Future 第一次被 poll 时才记录, 创建之后没有 .await 就 drop 的等待不算
+    async fn sleep_async(&self, dur: Duration) {
+        self.slept.borrow_mut().push(dur);
+    }
+}
+
+/// 按策略重试 op, 使用真实的时钟等待
+/// 返回第一次成功的结果, 或者最后一次的错误
+#[allow(unused)]
+pub fn retry<T>(
+    policy: &RetryPolicy,
+    op: impl FnMut() -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    retry_with_clock(policy, &SystemClock, op)
+}
+
+/// 与 retry 相同, 但由调用方注入时钟
+pub fn retry_with_clock<T>(
+    policy: &RetryPolicy,
+    clock: &impl Clock,
+    mut op: impl FnMut() -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    let mut rng = XorShift::new(policy.seed);
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(err) if policy.should_retry(attempt, &err) => {
+                clock.sleep(policy.backoff.delay(attempt, rng.next_f64()));
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// retry 的异步版本, op 每次调用返回一个新的 Future
+#[allow(unused)]
+pub async fn retry_async<T, F, Fut>(
+    policy: &RetryPolicy,
+    clock: &impl AsyncClock,
+    mut op: F,
+) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let mut rng = XorShift::new(policy.seed);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) if policy.should_retry(attempt, &err) => {
+                clock
+                    .sleep_async(policy.backoff.delay(attempt, rng.next_f64()))
+                    .await;
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+/// 错误累积
+///
+/// `?` 和 and_then 等组合器遇到第一个错误就会返回 (短路),
+/// 而表单或配置校验往往希望一次性收集所有字段的错误
+///
+/// `Validation<T, E>` 在组合时不会短路, 而是把两边的错误累积起来
+#[allow(unused)]
+struct Description;
+
+/// 校验结果
+/// - Valid: 校验通过
+/// - Invalid: 校验失败, 包含至少一个错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum Validation<T, E> {
+    Valid(T),
+    Invalid(Vec<E>),
+}
+
+#[allow(unused)]
+impl<T, E> Validation<T, E> {
+    /// 创建只包含一个错误的校验结果
+    pub fn invalid(err: E) -> Self {
+        Validation::Invalid(vec![err])
+    }
+
+    /// 条件成立时通过, 否则返回 err
+    pub fn check(value: T, ok: bool, err: E) -> Self {
+        if ok {
+            Validation::Valid(value)
+        } else {
+            Validation::invalid(err)
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Validation::Valid(_))
+    }
+
+    /// 所有累积的错误, 校验通过时为空
+    pub fn errors(&self) -> &[E] {
+        match self {
+            Validation::Valid(_) => &[],
+            Validation::Invalid(errs) => errs,
+        }
+    }
+
+    /// 映射通过时的值
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Validation<U, E> {
+        match self {
+            Validation::Valid(t) => Validation::Valid(f(t)),
+            Validation::Invalid(errs) => Validation::Invalid(errs),
+        }
+    }
+
+    /// 映射每一个错误
+    pub fn map_err<F>(self, f: impl FnMut(E) -> F) -> Validation<T, F> {
+        match self {
+            Validation::Valid(t) => Validation::Valid(t),
+            Validation::Invalid(errs) => Validation::Invalid(errs.into_iter().map(f).collect()),
+        }
+    }
+
+    /// 组合两个校验结果, 两者都通过时得到 (T, U), 否则累积两边所有错误
+    pub fn zip<U>(self, other: Validation<U, E>) -> Validation<(T, U), E> {
+        self.combine(other, |t, u| (t, u))
+    }
+
+    /// 组合两个校验结果, 两者都通过时用 f 合并, 否则累积两边所有错误
+    pub fn combine<U, V>(
+        self,
+        other: Validation<U, E>,
+        f: impl FnOnce(T, U) -> V,
+    ) -> Validation<V, E> {
+        match (self, other) {
+            (Validation::Valid(t), Validation::Valid(u)) => Validation::Valid(f(t, u)),
+            (Validation::Valid(_), Validation::Invalid(errs)) => Validation::Invalid(errs),
+            (Validation::Invalid(errs), Validation::Valid(_)) => Validation::Invalid(errs),
+            (Validation::Invalid(mut errs), Validation::Invalid(more)) => {
+                errs.extend(more);
+                Validation::Invalid(errs)
+            }
+        }
+    }
+
+    /// 转为 Result, 保留所有错误
+    pub fn into_result(self) -> Result<T, Vec<E>> {
+        match self {
+            Validation::Valid(t) => Ok(t),
+            Validation::Invalid(errs) => Err(errs),
+        }
+    }
+}
+
+/// 从 Result 转换, Err 变为只包含一个错误的 Invalid
+impl<T, E> From<Result<T, E>> for Validation<T, E> {
+    fn from(value: Result<T, E>) -> Self {
+        match value {
+            Ok(t) => Validation::Valid(t),
+            Err(e) => Validation::invalid(e),
+        }
+    }
+}
+
+/// 转为 Result, 保留所有错误
+impl<T, E> From<Validation<T, E>> for Result<T, Vec<E>> {
+    fn from(value: Validation<T, E>) -> Self {
+        value.into_result()
+    }
+}
+
+/// 收集多个校验结果, 全部通过时得到 Vec<T>, 否则累积所有错误
+impl<T, E> FromIterator<Validation<T, E>> for Validation<Vec<T>, E> {
+    fn from_iter<I: IntoIterator<Item = Validation<T, E>>>(iter: I) -> Self {
+        iter.into_iter()
+            .fold(Validation::Valid(Vec::new()), |acc, v| {
+                acc.combine(v, |mut ts, t| {
+                    ts.push(t);
+                    ts
+                })
+            })
+    }
+}
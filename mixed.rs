@@ -1,9 +1,16 @@
 #[allow(unused_imports)]
 use std::ops::Deref;
 
-fn main() {
-    drop_example01();
-    drop_example02();
+#[path = "errors/app_error.rs"]
+mod app_error;
+#[path = "errors/panic_boundary.rs"]
+mod panic_boundary;
+
+fn main() -> panic_boundary::Report {
+    panic_boundary::run_examples(&[
+        ("drop_example01", drop_example01),
+        ("drop_example02", drop_example02),
+    ])
 }
 
 #[allow(unused)]
@@ -22,6 +29,7 @@ fn drop_example02() {
     drop(foo);
 }
 
+/// 在 drop 中 println! 只能用眼睛确认顺序, 用 DropLog 断言顺序的版本见 advance/smart_pointer/drop_order.rs example01
 #[allow(unused)]
 fn drop_example01() {
     struct HasDrop1;
@@ -66,6 +74,7 @@ fn drop_example01() {
 
 #[allow(unused)]
 fn deref_example01() {
+    // 把值放到堆上的完整 GoodBox 见 advance/smart_pointer/good_box.rs
     struct GoodBox<T>(T);
 
     impl<T> GoodBox<T> {
@@ -147,6 +156,7 @@ fn trait_object() {
         }
     }
 
+    // 用 arena 代替逐个 Box 分配见 advance/smart_pointer/arena_demo.rs
     let elements: Vec<Box<dyn Draw>> = vec![Box::new(Select { id: 3 }), Box::new(Button { id: 0 })];
     for elem in elements.into_iter() {
         elem.draw();